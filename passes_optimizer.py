@@ -71,9 +71,19 @@ HEADER = '''
 // This source file is generated by by passes_optimizer.py.
 
 use super::*;
-use inkwell::passes::PassManagerSubType;
 
-pub fn add_passes<T: PassManagerSubType>(passmgr: &PassManager<T>) {
+// The passes are run in groups in this order, and `--time-passes` reports the duration of each group.
+pub const PASS_GROUPS: [(&str, fn(&PassManager<Module>)); 3] = [
+    ("llvm_passes_early", add_passes_early),
+    ("llvm_passes_middle", add_passes_middle),
+    ("llvm_passes_late", add_passes_late),
+];
+'''
+
+GROUP_NAMES = ['early', 'middle', 'late']
+
+GROUP_HEADER = '''
+pub fn add_passes_{}(passmgr: &PassManager<Module>) {{
 '''
 
 FOOTER = '''
@@ -163,14 +173,16 @@ def get_all_passes():
 
 
 def write_source_file(passes):
+    # Split the passes into groups of almost the same size, keeping the order.
+    group_size = ceil(len(passes) / len(GROUP_NAMES))
     with open(SOURCE_FILE, 'w') as f:
         f.write(HEADER)
-        f.write('\n')
-        for p in passes:
-            f.write(ADD_PASS_FORMAT.format(p))
-            f.write('\n')
-        f.write(FOOTER)
-        f.write('\n')
+        for i, name in enumerate(GROUP_NAMES):
+            f.write(GROUP_HEADER.format(name))
+            for p in passes[i * group_size:(i + 1) * group_size]:
+                f.write(ADD_PASS_FORMAT.format(p))
+                f.write('\n')
+            f.write(FOOTER)
 
 
 def run_benchmark(run_bench_iteration=RUN_BENCH_ITERATION, timeout=60):
@@ -0,0 +1,286 @@
+// The programmatic API of the compiler, for tools written in Rust (e.g., build systems, editors or test harnesses).
+//
+// A `FixProgram` is created from source files (on the file system or in memory) and a `Configuration`.
+// It can be type-checked to get diagnostics, queried for the inferred types of global values,
+// built to an object file or an executable, or run as a child process with its stdout and stderr captured.
+//
+// Compile errors are returned as `Diagnostic`s instead of terminating the process.
+// The compiler keeps state in thread-local and global variables, so a program should be compiled on one thread at a time.
+// Caches and intermediate files are written to `.fixlang` in the current directory, as the `fix` command does.
+
+use std::{
+    fs,
+    os::unix::process::ExitStatusExt,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::*;
+
+pub use crate::configuration::{Configuration, FixOptimizationLevel, LinkType, OutputFileType};
+pub use crate::time_passes::TimePassesFormat;
+pub use crate::toolchain::Toolchain;
+
+// An error found by the compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    // The locations in source codes where the error is detected.
+    pub locations: Vec<DiagnosticLocation>,
+    // Additional messages, each of which may have a location.
+    pub notes: Vec<(String, Option<DiagnosticLocation>)>,
+    // The error formatted as the `fix` command prints it.
+    pub rendered: String,
+}
+
+// A range in a source file. `line` and `column` are 1-based and point to `start`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosticLocation {
+    pub file: PathBuf,
+    // Byte offsets of the range.
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl DiagnosticLocation {
+    fn from_span(span: &Span) -> Self {
+        let (line, column) = span.start_line_col();
+        DiagnosticLocation {
+            file: span.input.file_path.clone(),
+            start: span.start,
+            end: span.end,
+            line,
+            column,
+        }
+    }
+}
+
+impl From<CompileError> for Diagnostic {
+    fn from(err: CompileError) -> Self {
+        Diagnostic {
+            message: err.msg.clone(),
+            locations: err.srcs.iter().map(DiagnosticLocation::from_span).collect(),
+            notes: err
+                .notes
+                .iter()
+                .map(|(note, src)| {
+                    (
+                        note.clone(),
+                        src.as_ref().map(DiagnosticLocation::from_span),
+                    )
+                })
+                .collect(),
+            rendered: err.to_string(),
+        }
+    }
+}
+
+// The result of running a program by `FixProgram::run`.
+#[derive(Clone, Debug)]
+pub struct RunOutput {
+    // The exit code of the program, or `None` if it is terminated by a signal.
+    pub exit_code: Option<i32>,
+    // The signal which terminated the program.
+    pub signal: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+// A Fix program loaded from source files.
+pub struct FixProgram {
+    config: Configuration,
+    sources: Vec<SourceFile>,
+    program: Program,
+    // The result of front-end phases, which are run at most once on `program`.
+    frontend: Option<Result<TypeCheckContext, Diagnostic>>,
+}
+
+impl FixProgram {
+    // Load the source files specified by `config.source_files`.
+    pub fn load(config: Configuration) -> Result<Self, Diagnostic> {
+        let sources = config
+            .source_files
+            .iter()
+            .map(|path| SourceFile::from_file_path(path.clone()))
+            .collect();
+        Self::from_source_files(sources, config)
+    }
+
+    // Load source files given as pairs of a path and the content, which do not need to exist on the file system.
+    // `config.source_files` is replaced with the given paths.
+    pub fn load_from_sources(
+        sources: &[(PathBuf, String)],
+        mut config: Configuration,
+    ) -> Result<Self, Diagnostic> {
+        config.source_files = sources.iter().map(|(path, _)| path.clone()).collect();
+        let sources = sources
+            .iter()
+            .map(|(path, content)| SourceFile::from_string(path.clone(), content.clone()))
+            .collect();
+        Self::from_source_files(sources, config)
+    }
+
+    fn from_source_files(
+        sources: Vec<SourceFile>,
+        mut config: Configuration,
+    ) -> Result<Self, Diagnostic> {
+        if config.time_passes.is_some() {
+            enable_time_passes();
+        }
+        let program = link_source_files(&sources, &mut config)?;
+        Ok(FixProgram {
+            config,
+            sources,
+            program,
+            frontend: None,
+        })
+    }
+
+    pub fn config(&self) -> &Configuration {
+        &self.config
+    }
+
+    // Type-check all global values defined in the source files, and returns the errors found.
+    // Global values are checked one by one, so that as many errors as possible are reported.
+    pub fn check(&mut self) -> Vec<Diagnostic> {
+        let typechecker = match self.frontend() {
+            Ok(typechecker) => typechecker,
+            Err(err) => return vec![err],
+        };
+        let program = &self.program;
+        let modules = program.modules_of_files(&self.config.source_files);
+        let mut names = program.global_values.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        let mut errors = vec![];
+        for name in names {
+            if let Err(err) = catch_compile_error_in(|| {
+                program.check_type_of_global_value(&name, &modules, &typechecker)
+            }) {
+                errors.push(err.into());
+            }
+        }
+        for export in &program.export_statements {
+            if let Err(err) = catch_compile_error_in(|| export.function_type(program)) {
+                errors.push(err.into());
+            }
+        }
+        errors
+    }
+
+    // The types of global values (including trait methods) defined in the source files, as pairs of the full name and the type, sorted by name.
+    pub fn global_value_types(&mut self) -> Result<Vec<(String, String)>, Diagnostic> {
+        self.frontend()?;
+        let modules = self.program.modules_of_files(&self.config.source_files);
+        let mut types = self
+            .program
+            .global_values
+            .iter()
+            .filter(|(name, _)| modules.contains(&name.module()))
+            .map(|(name, gv)| (name.to_string(), gv.scm.to_string()))
+            .collect::<Vec<_>>();
+        types.sort();
+        Ok(types)
+    }
+
+    // The type of a global value specified by the full name, e.g., `Main::main`.
+    pub fn type_of(&mut self, name: &str) -> Result<Option<String>, Diagnostic> {
+        self.frontend()?;
+        Ok(self
+            .program
+            .global_values
+            .iter()
+            .find(|(full_name, _)| full_name.to_string() == name)
+            .map(|(_, gv)| gv.scm.to_string()))
+    }
+
+    // Build the program into the file specified by `config`, and returns its path.
+    pub fn build(self) -> Result<PathBuf, Diagnostic> {
+        let (program, config) = self.into_fresh_program()?;
+        let out_path = config.get_output_file_path();
+        catch_compile_error_in(|| {
+            build_program(program, config.clone());
+            report_time_passes(&config);
+        })?;
+        Ok(out_path)
+    }
+
+    // Run the program with `config.program_args`, capturing its stdout and stderr.
+    // The program is built into a temporary executable and run as a child process,
+    // so that the program cannot terminate or break the current process.
+    pub fn run(self) -> Result<RunOutput, Diagnostic> {
+        let (program, config) = self.into_fresh_program()?;
+        let exec_path = temporary_executable_path();
+        let mut build_config = config.clone();
+        build_config.output_file_type = OutputFileType::Executable;
+        build_config.out_file_path = Some(exec_path.clone());
+        catch_compile_error_in(|| {
+            build_program(program, build_config);
+            report_time_passes(&config);
+        })?;
+        let output = Command::new(&exec_path).args(&config.program_args).output();
+        let _ = fs::remove_file(&exec_path);
+        let output = catch_compile_error_in(|| {
+            output.unwrap_or_else(|e| {
+                error_exit(&format!(
+                    "Failed to run `{}`: {}",
+                    exec_path.to_string_lossy(),
+                    e
+                ))
+            })
+        })?;
+        Ok(RunOutput {
+            exit_code: output.status.code(),
+            signal: output.status.signal(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    // Run front-end phases if not yet, and returns the type checker.
+    fn frontend(&mut self) -> Result<TypeCheckContext, Diagnostic> {
+        if self.frontend.is_none() {
+            let program = &mut self.program;
+            self.frontend =
+                Some(catch_compile_error_in(|| run_frontend(program)).map_err(Into::into));
+        }
+        self.frontend.clone().unwrap()
+    }
+
+    // Front-end phases mutate the program, so the program is loaded again if they have already been run.
+    fn into_fresh_program(mut self) -> Result<(Program, Configuration), Diagnostic> {
+        if self.frontend.is_some() {
+            self.program = link_source_files(&self.sources, &mut self.config)?;
+        }
+        Ok((self.program, self.config))
+    }
+}
+
+// Parse source files, link them with the standard library and resolve imports.
+fn link_source_files(
+    sources: &[SourceFile],
+    config: &mut Configuration,
+) -> Result<Program, Diagnostic> {
+    catch_compile_error_in(|| {
+        let mut program = make_std_mod();
+        for src in sources {
+            program.link(parse_source_file(src.clone()));
+        }
+        let _timer = time_pass("resolve_imports");
+        program.resolve_imports(config);
+        program
+    })
+    .map_err(Into::into)
+}
+
+// A path of an executable built by `FixProgram::run`, which is unique in the process.
+fn temporary_executable_path() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = touch_directory(INTERMEDIATE_PATH);
+    dir.join(format!(
+        "run.{}.{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
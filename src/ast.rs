@@ -1,3 +1,4 @@
+pub mod export;
 pub mod expr;
 pub mod import;
 pub mod inline_llvm;
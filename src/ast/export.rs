@@ -0,0 +1,130 @@
+use super::*;
+
+// Statement `FFI_EXPORT[value_name, c_function_name];`, which exports a global value as a C function.
+#[derive(Clone)]
+pub struct ExportStatement {
+    pub value_name: FullName,
+    pub c_function_name: Name,
+    pub source: Option<Span>,
+}
+
+// The signature of a C function implemented by a Fix value, i.e., an exported function or a callback.
+// Such a value has type `A1 -> ... -> An -> R` or `A1 -> ... -> An -> IO R`,
+// where `Ai` and `R` are types which can be passed to C (`R` may be also `()`).
+pub struct ExportedFunctionType {
+    pub params: Vec<Rc<TyCon>>,
+    pub ret: Rc<TyCon>,
+    // Is the result type `IO R`?
+    pub is_io: bool,
+}
+
+impl ExportStatement {
+    // Get the signature of the exported C function. Raises an error if the value cannot be exported.
+    // This should be called after type aliases in the program are resolved.
+    pub fn function_type(&self, program: &Program) -> ExportedFunctionType {
+        let gv = match program.global_values.get(&self.value_name) {
+            Some(gv) => gv,
+            None => error_exit_with_src(
+                &format!(
+                    "Unknown global value `{}` in `FFI_EXPORT`.",
+                    self.value_name.to_string()
+                ),
+                &self.source,
+            ),
+        };
+        let cannot_export = |msg: &str| {
+            format!(
+                "Cannot export `{}` of type `{}`: {}",
+                self.value_name.to_string(),
+                gv.scm.to_string(),
+                msg
+            )
+        };
+        if !gv.scm.vars.is_empty() || !gv.scm.preds.is_empty() {
+            error_exit_with_src(
+                &cannot_export("the type of an exported value should not be generic."),
+                &self.source,
+            );
+        }
+
+        match ExportedFunctionType::from_type(&gv.scm.ty, false) {
+            Ok(fun_ty) => fun_ty,
+            Err(msg) => error_exit_with_src(&cannot_export(&msg), &self.source),
+        }
+    }
+}
+
+impl ExportedFunctionType {
+    // Get the signature of a C function which calls a Fix function of type `ty`.
+    // If `allow_unit_params` is true, parameters of type `()` are allowed; they are not passed from C.
+    pub fn from_type(
+        ty: &Rc<TypeNode>,
+        allow_unit_params: bool,
+    ) -> Result<ExportedFunctionType, String> {
+        let mut params = vec![];
+        let mut ty = ty.clone();
+        while let Type::FunTy(src, dst) = &ty.ty {
+            match ffi_tycon(src) {
+                Some(tc) if allow_unit_params || !tc.is_unit() => params.push(tc),
+                _ => {
+                    return Err(format!(
+                        "the parameter type `{}` cannot be passed from C.",
+                        src.to_string()
+                    ))
+                }
+            }
+            ty = dst.clone();
+        }
+        let mut is_io = false;
+        if let Type::TyApp(fun, arg) = &ty.ty {
+            if let Type::TyCon(tc) = &fun.ty {
+                if tc.name == FullName::from_strs(&[STD_NAME], IO_NAME) {
+                    is_io = true;
+                    ty = arg.clone();
+                }
+            }
+        }
+        let ret = match ffi_tycon(&ty) {
+            Some(tc) => tc,
+            None => {
+                return Err(format!(
+                    "the result type `{}` cannot be returned to C.",
+                    ty.to_string()
+                ))
+            }
+        };
+        Ok(ExportedFunctionType { params, ret, is_io })
+    }
+
+    // The types of parameters of the C function, i.e., parameters except `()`.
+    pub fn c_params(&self) -> Vec<Rc<TyCon>> {
+        self.params
+            .iter()
+            .filter(|tc| !tc.is_unit())
+            .cloned()
+            .collect()
+    }
+}
+
+// If the type can be passed to or returned from C functions, i.e., it is one of numeric types, `Ptr` or `()`, get its type constructor.
+fn ffi_tycon(ty: &Rc<TypeNode>) -> Option<Rc<TyCon>> {
+    let tc = match &ty.ty {
+        Type::TyCon(tc) => tc,
+        _ => return None,
+    };
+    if tc.is_unit() {
+        return Some(tc.clone());
+    }
+    if tc.name.namespace != NameSpace::new_str(&[STD_NAME]) {
+        return None;
+    }
+    let ffi_names = [
+        I8_NAME, U8_NAME, I16_NAME, U16_NAME, I32_NAME, U32_NAME, I64_NAME, U64_NAME, F32_NAME,
+        F64_NAME, PTR_NAME,
+    ];
+    if ffi_names.contains(&tc.name.name.as_str()) {
+        Some(tc.clone())
+    } else {
+        None
+    }
+}
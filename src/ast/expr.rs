@@ -400,6 +400,25 @@ impl ExprNode {
         Rc::new(ret)
     }
 
+    pub fn set_call_c_tys(&self, ret_ty: Rc<TyCon>, param_tys: Vec<Rc<TyCon>>) -> Rc<ExprNode> {
+        let mut ret = self.clone();
+        match &*self.expr {
+            Expr::CallC(fun_name, _, _, is_var_args, args) => {
+                ret.expr = Rc::new(Expr::CallC(
+                    fun_name.clone(),
+                    ret_ty,
+                    param_tys,
+                    *is_var_args,
+                    args.clone(),
+                ));
+            }
+            _ => {
+                panic!()
+            }
+        }
+        Rc::new(ret)
+    }
+
     pub fn set_call_c_arg(&self, arg: Rc<ExprNode>, idx: usize) -> Rc<ExprNode> {
         let mut ret = self.clone();
         match &*self.expr {
@@ -520,8 +539,20 @@ impl ExprNode {
                 }
                 expr
             }
-            Expr::CallC(_, _, _, _, args) => {
-                let mut expr = self.clone();
+            Expr::CallC(_, ret_ty, param_tys, _, args) => {
+                let resolve = |tc: &Rc<TyCon>| {
+                    if tc.is_unit() {
+                        return tc.clone();
+                    }
+                    let mut tc = tc.as_ref().clone();
+                    if let Err(msg) = tc.resolve_namespace(ctx) {
+                        error_exit_with_src(&msg, &self.source)
+                    }
+                    Rc::new(tc)
+                };
+                let mut expr = self
+                    .clone()
+                    .set_call_c_tys(resolve(ret_ty), param_tys.iter().map(resolve).collect());
                 for (i, arg) in args.iter().enumerate() {
                     expr = expr.set_call_c_arg(arg.resolve_namespace(ctx), i);
                 }
@@ -585,8 +616,22 @@ impl ExprNode {
                 }
                 expr
             }
-            Expr::CallC(_, _, _, _, args) => {
-                let mut expr = self.clone();
+            Expr::CallC(_, ret_ty, param_tys, _, args) => {
+                // A type alias to a type constructor, such as `type CInt = I32;`, can be used in `CALL_C`.
+                let resolve =
+                    |tc: &Rc<TyCon>| match type_tycon(tc).resolve_type_aliases(type_env).ty {
+                        Type::TyCon(ref resolved) => resolved.clone(),
+                        _ => error_exit_with_src(
+                            &format!(
+                                "Cannot use `{}` in `CALL_C`: it is not a type constructor.",
+                                tc.to_string()
+                            ),
+                            &self.source,
+                        ),
+                    };
+                let mut expr = self
+                    .clone()
+                    .set_call_c_tys(resolve(ret_ty), param_tys.iter().map(resolve).collect());
                 for (i, arg) in args.iter().enumerate() {
                     expr = expr.set_call_c_arg(arg.resolve_type_aliases(type_env), i);
                 }
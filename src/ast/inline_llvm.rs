@@ -64,6 +64,9 @@ pub enum LLVMGenerator {
         InlineLLVMGetReleaseFunctionOfBoxedValueFunctionBody,
     ),
     GetRetainFunctionOfBoxedValueFunctionBody(InlineLLVMGetRetainFunctionOfBoxedValueFunctionBody),
+    GetCCallbackFunctionBody(InlineLLVMGetCCallbackFunctionBody),
+    StoreUnalignedBody(InlineLLVMStoreUnalignedBody),
+    LoadUnalignedBody(InlineLLVMLoadUnalignedBody),
 }
 
 impl LLVMGenerator {
@@ -141,6 +144,9 @@ impl LLVMGenerator {
             LLVMGenerator::GetRetainFunctionOfBoxedValueFunctionBody(x) => {
                 x.generate(gc, ty, rvo, bvs)
             }
+            LLVMGenerator::GetCCallbackFunctionBody(x) => x.generate(gc, ty, rvo, bvs),
+            LLVMGenerator::StoreUnalignedBody(x) => x.generate(gc, ty, rvo, bvs),
+            LLVMGenerator::LoadUnalignedBody(x) => x.generate(gc, ty, rvo, bvs),
         }
     }
 
@@ -1,6 +1,6 @@
 use inkwell::{debug_info::AsDIScope, module::Linkage};
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::cell::RefCell;
 
 use super::*;
 
@@ -65,6 +65,7 @@ pub struct GlobalValueDefn {
     pub src: Option<Span>,
 }
 
+#[derive(Clone)]
 pub struct GlobalValue {
     // Type of this symbol.
     // For example, in case "trait a: Show { show: a -> String }",
@@ -236,6 +237,7 @@ impl<'a> NameResolutionContext {
 
 // Program of fix a collection of modules.
 // A program can link another program which consists of a single module.
+#[derive(Clone)]
 pub struct Program {
     // List of tuple sizes used in this program.
     pub used_tuple_sizes: Vec<u32>,
@@ -249,12 +251,18 @@ pub struct Program {
 
     // Import statements to be resolved.
     pub unresolved_imports: Vec<ImportStatement>,
+    // Global values exported as C functions by `FFI_EXPORT`.
+    pub export_statements: Vec<ExportStatement>,
     // For each linked module `m`, `visible_mods[m]` is the set of modules imported by `m`.
     // Each module imports itself.
     // This is used to namespace resolution and overloading resolution.
     pub visible_mods: HashMap<Name, HashSet<Name>>,
     // For each module, the path to the source file.
     pub module_to_files: HashMap<Name, SourceFile>,
+    // Results of type-checking of global values cached in `.fixlang`, shared by clones of this program.
+    pub type_check_cache: Rc<RefCell<TypeCheckCache>>,
+    // Documents of types, traits and global values implemented in the compiler, which are shown by `fix doc`.
+    pub builtin_docs: HashMap<FullName, String>,
 }
 
 impl Program {
@@ -262,6 +270,7 @@ impl Program {
     pub fn single_module(module_name: Name, src: &SourceFile) -> Program {
         let mut fix_mod = Program {
             unresolved_imports: vec![],
+            export_statements: vec![],
             visible_mods: Default::default(),
             type_defns: Default::default(),
             global_values: Default::default(),
@@ -271,6 +280,8 @@ impl Program {
             type_env: Default::default(),
             used_tuple_sizes: Vec::from_iter(0..=TUPLE_SIZE_BASE),
             module_to_files: Default::default(),
+            type_check_cache: Default::default(),
+            builtin_docs: Default::default(),
         };
         fix_mod.add_visible_mod(&module_name, &module_name);
         fix_mod.add_visible_mod(&module_name, &STD_NAME.to_string());
@@ -388,6 +399,23 @@ impl Program {
         );
     }
 
+    // Add a global value implemented in the compiler with its document.
+    pub fn add_builtin_value(
+        &mut self,
+        name: FullName,
+        value: (Rc<ExprNode>, Rc<Scheme>),
+        doc: &str,
+    ) {
+        self.add_builtin_doc(&name, doc);
+        self.add_global_value(name, value);
+    }
+
+    // Set the document of a type, trait or global value implemented in the compiler.
+    pub fn add_builtin_doc(&mut self, name: &FullName, doc: &str) {
+        self.builtin_docs
+            .insert(name.clone(), doc.trim().to_string());
+    }
+
     // Add global values.
     pub fn add_global_values(&mut self, exprs: Vec<GlobalValueDefn>, types: Vec<GlobalValueDecl>) {
         struct GlobalValue {
@@ -484,28 +512,44 @@ impl Program {
     }
 
     // Generate codes of global symbols.
-    pub fn generate_code(&self, gc: &mut GenerationContext) {
+    // Symbols in `external_symbols` are already compiled into another LLVM module, so they are only declared.
+    // If `export` is true, global symbols are given external linkage so that other LLVM modules can use them.
+    pub fn generate_code(
+        &self,
+        gc: &mut GenerationContext,
+        external_symbols: &HashSet<FullName>,
+        export: bool,
+    ) {
+        // Symbols are sorted by name so that the generated code does not depend on the order of the hash map.
+        let mut symbols = self.instantiated_global_symbols.iter().collect::<Vec<_>>();
+        symbols.sort_by_cached_key(|(name, _)| name.to_string());
+
         // First, declara accessor function (a function that returns a pointer to the global value) for a global value, or function for global function value.
-        let global_objs = self
-            .instantiated_global_symbols
-            .iter()
+        let global_objs = symbols
+            .into_iter()
             .map(|(name, sym)| {
                 gc.typeresolver = sym.type_resolver.clone();
                 let obj_ty = sym.type_resolver.substitute_type(&sym.ty);
+                let linkage = if export || external_symbols.contains(name) {
+                    Linkage::External
+                } else {
+                    Linkage::Internal
+                };
                 if obj_ty.is_funptr() {
                     // Declare lambda function.
                     let lam = sym.expr.as_ref().unwrap().clone();
                     let lam = lam.set_inferred_type(obj_ty.clone());
                     let lam_fn = gc.declare_lambda_function(lam, Some(name));
+                    lam_fn.set_linkage(linkage);
                     gc.add_global_object(name.clone(), lam_fn, obj_ty.clone());
                     (name, lam_fn, sym.clone(), obj_ty)
                 } else {
                     // Declare accessor function.
                     let acc_fn_name = format!("Get#{}", name.to_string());
                     let acc_fn_type = ptr_to_object_type(gc.context).fn_type(&[], false);
-                    let acc_fn =
-                        gc.module
-                            .add_function(&acc_fn_name, acc_fn_type, Some(Linkage::Internal));
+                    let acc_fn = gc
+                        .module
+                        .add_function(&acc_fn_name, acc_fn_type, Some(linkage));
 
                     // Create debug info subprogram
                     if gc.has_di() {
@@ -526,6 +570,9 @@ impl Program {
 
         // Implement functions.
         for (name, acc_fn, sym, obj_ty) in global_objs {
+            if external_symbols.contains(name) {
+                continue;
+            }
             gc.typeresolver = sym.type_resolver;
             if obj_ty.is_funptr() {
                 // Implement lambda function.
@@ -545,8 +592,8 @@ impl Program {
                 let flag_name = format!("InitFlag#{}", name.to_string());
                 let (flag_ty, flag_init_val) = if gc.config.threaded {
                     (
-                        pthread_once_init_flag_type(gc.context),
-                        pthread_once_init_flag_value(gc.context),
+                        pthread_once_init_flag_type(gc.context, &gc.target_triple()),
+                        pthread_once_init_flag_value(gc.context, &gc.target_triple()),
                     )
                 } else {
                     let ty = gc.context.i8_type();
@@ -701,104 +748,21 @@ impl Program {
         define_module: &Name,
         tc: &TypeCheckContext,
     ) {
-        fn cache_file_name(
-            name: &FullName,
-            hash_of_dependent_codes: &str,
-            scheme: &Rc<Scheme>,
-        ) -> String {
-            let data = format!(
-                "{}_{}_{}",
-                name.to_string(),
-                hash_of_dependent_codes,
-                scheme.to_string()
-            );
-            format!("{:x}", md5::compute(data))
-        }
-        fn load_cache(
-            name: &FullName,
-            hash_of_dependent_codes: &str,
-            required_scheme: &Rc<Scheme>,
-        ) -> Option<TypedExpr> {
-            let cache_file_name = cache_file_name(name, hash_of_dependent_codes, required_scheme);
-            let cache_dir = touch_directory(TYPE_CHECK_CACHE_PATH);
-            let cache_file = cache_dir.join(cache_file_name);
-            let cache_file_display = cache_file.display();
-            if !cache_file.exists() {
-                return None;
-            }
-            let mut cache_file = match File::open(&cache_file) {
-                Err(_) => {
-                    return None;
-                }
-                Ok(file) => file,
-            };
-            let mut cache_bytes = vec![];
-            match cache_file.read_to_end(&mut cache_bytes) {
-                Ok(_) => {}
-                Err(why) => {
-                    eprintln!(
-                        "warning: Failed to read cache file {}: {}.",
-                        cache_file_display, why
-                    );
-                    return None;
-                }
-            }
-            let expr: TypedExpr = match serde_pickle::from_slice(&cache_bytes, Default::default()) {
-                Ok(res) => res,
-                Err(why) => {
-                    eprintln!(
-                        "warning: Failed to parse content of cache file {}: {}.",
-                        cache_file_display, why
-                    );
-                    return None;
-                }
-            };
-            Some(expr)
-        }
-
-        fn save_cache(
-            te: &TypedExpr,
-            required_scheme: &Rc<Scheme>,
-            name: &FullName,
-            hash_of_dependent_codes: &str,
-        ) {
-            let cache_file_name = cache_file_name(name, hash_of_dependent_codes, required_scheme);
-            let cache_dir = touch_directory(TYPE_CHECK_CACHE_PATH);
-            let cache_file = cache_dir.join(cache_file_name);
-            let cache_file_display = cache_file.display();
-            let mut cache_file = match File::create(&cache_file) {
-                Err(_) => {
-                    eprintln!(
-                        "warning: Failed to create cache file {}.",
-                        cache_file_display
-                    );
-                    return;
-                }
-                Ok(file) => file,
-            };
-            let serialized = serde_pickle::to_vec(&te, Default::default()).unwrap();
-            match cache_file.write_all(&serialized) {
-                Ok(_) => {}
-                Err(_) => {
-                    eprintln!(
-                        "warning: Failed to write cache file {}.",
-                        cache_file_display
-                    );
-                }
-            }
-        }
-
-        // Load type-checking cache file.
-        let hash_of_dependent_codes = self.hash_of_dependent_codes(define_module);
-        let opt_cache = load_cache(name, &hash_of_dependent_codes, required_scheme);
-        if opt_cache.is_some() {
-            // If cache is available,
-            *te = opt_cache.unwrap();
+        // Load the result from cache.
+        let cache_key = TypeCheckCache::key(
+            name,
+            &self.hash_of_dependent_codes(define_module),
+            required_scheme,
+        );
+        let opt_cache = self.type_check_cache.borrow_mut().get(&cache_key);
+        if let Some(cache) = opt_cache {
+            *te = cache;
             te.type_resolver.kind_map = tc.type_env.kinds();
             return;
         }
 
         // Perform namespace inference.
+        let _timer = time_pass("type_check");
         let nrctx = NameResolutionContext {
             types: self.tycon_names_with_aliases(),
             traits: self.trait_names_with_aliases(),
@@ -815,8 +779,130 @@ impl Program {
         te.expr = tc.check_type(te.expr.clone(), required_scheme.clone());
         te.type_resolver = tc.resolver;
 
-        // Save the result to cache file.
-        save_cache(te, required_scheme, name, &hash_of_dependent_codes);
+        // Save the result to cache.
+        self.type_check_cache.borrow_mut().insert(cache_key, te);
+    }
+
+    // Infer the type of an expression which is written in the given module, e.g., an input of REPL.
+    pub fn infer_type_of_expr(
+        &self,
+        expr: Rc<ExprNode>,
+        define_module: &Name,
+        tc: &TypeCheckContext,
+    ) -> Rc<Scheme> {
+        let nrctx = NameResolutionContext {
+            types: self.tycon_names_with_aliases(),
+            traits: self.trait_names_with_aliases(),
+            imported_modules: self.visible_mods[define_module].clone(),
+        };
+        let expr = expr.resolve_namespace(&nrctx);
+        let expr = expr.resolve_type_aliases(&tc.type_env);
+        let mut tc = tc.clone();
+        tc.current_module = Some(define_module.clone());
+        tc.infer_type(expr)
+    }
+
+    // Perform type-checking of all global values (including trait method implementations) defined in the given modules,
+    // regardless of whether they are used from `main` or not.
+    pub fn check_type_of_global_values(&self, modules: &HashSet<Name>, tc: &TypeCheckContext) {
+        let mut names = self.global_values.keys().collect::<Vec<_>>();
+        names.sort(); // To report errors in a deterministic order.
+        for name in names {
+            self.check_type_of_global_value(name, modules, tc);
+        }
+    }
+
+    // Perform type-checking of a global value (or implementations of a method) defined in one of the given modules.
+    // Returns the type-checked expressions.
+    pub fn check_type_of_global_value(
+        &self,
+        name: &FullName,
+        modules: &HashSet<Name>,
+        tc: &TypeCheckContext,
+    ) -> Vec<TypedExpr> {
+        self.check_type_of_global_value_if(name, modules, tc, |_| true)
+    }
+
+    // Perform type-checking of a global value (or implementations of a method) defined in one of the given modules,
+    // only as far as it is used by the instantiated symbols, i.e., as far as it is type-checked by the instantiation.
+    // Returns the type-checked expressions.
+    pub fn check_type_of_used_global_value(
+        &self,
+        name: &FullName,
+        modules: &HashSet<Name>,
+        tc: &TypeCheckContext,
+    ) -> Vec<TypedExpr> {
+        let used_tys = self
+            .instantiated_global_symbols
+            .values()
+            .filter(|sym| &sym.template_name == name)
+            .map(|sym| sym.ty.clone())
+            .collect::<Vec<_>>();
+        if used_tys.is_empty() {
+            return vec![];
+        }
+        self.check_type_of_global_value_if(name, modules, tc, |scm| {
+            let mut tc = tc.clone();
+            let (_, ty) = tc.instantiate_scheme(scm, false);
+            used_tys
+                .iter()
+                .any(|used_ty| Substitution::unify(&tc.type_env.kinds(), &ty, used_ty).is_some())
+        })
+    }
+
+    // Perform type-checking of a global value (or implementations of a method) defined in one of the given modules,
+    // whose type scheme satisfies `filter`.
+    fn check_type_of_global_value_if(
+        &self,
+        name: &FullName,
+        modules: &HashSet<Name>,
+        tc: &TypeCheckContext,
+        filter: impl Fn(&Rc<Scheme>) -> bool,
+    ) -> Vec<TypedExpr> {
+        let mut typed_exprs = vec![];
+        let global_value = self.global_values.get(name).unwrap();
+        match &global_value.expr {
+            SymbolExpr::Simple(e) => {
+                let define_module = name.module();
+                if modules.contains(&define_module) && filter(&global_value.scm) {
+                    let mut e = e.clone();
+                    self.resolve_and_check_type(
+                        &mut e,
+                        &global_value.scm,
+                        name,
+                        &define_module,
+                        tc,
+                    );
+                    typed_exprs.push(e);
+                }
+            }
+            SymbolExpr::Method(impls) => {
+                for method in impls {
+                    if !modules.contains(&method.define_module) || !filter(&method.ty) {
+                        continue;
+                    }
+                    let mut e = method.expr.clone();
+                    self.resolve_and_check_type(
+                        &mut e,
+                        &method.ty,
+                        name,
+                        &method.define_module,
+                        tc,
+                    );
+                    typed_exprs.push(e);
+                }
+            }
+        }
+        typed_exprs
+    }
+
+    // Get the names of modules defined in the given source files.
+    pub fn modules_of_files(&self, files: &[PathBuf]) -> HashSet<Name> {
+        self.module_to_files
+            .iter()
+            .filter(|(_, src)| files.contains(&src.file_path))
+            .map(|(mod_name, _)| mod_name.clone())
+            .collect()
     }
 
     // Instantiate symbol.
@@ -890,16 +976,28 @@ impl Program {
         }
     }
 
-    // Instantiate main function.
-    pub fn instantiate_main_function(&mut self, tc: &TypeCheckContext) -> Rc<ExprNode> {
-        let main_func_name = FullName::from_strs(&[MAIN_MODULE_NAME], MAIN_FUNCTION_NAME);
-        if !self.global_values.contains_key(&main_func_name) {
-            error_exit(&format!("{} not found.", main_func_name.to_string()));
+    // Instantiate a global value of type `IO ()` (e.g., main function) and all called functions.
+    pub fn instantiate_io_action(
+        &mut self,
+        name: &FullName,
+        tc: &TypeCheckContext,
+    ) -> Rc<ExprNode> {
+        self.instantiate_global_value(name, &make_io_unit_ty(), tc)
+    }
+
+    // Instantiate a global value at the given type and all called functions.
+    pub fn instantiate_global_value(
+        &mut self,
+        name: &FullName,
+        ty: &Rc<TypeNode>,
+        tc: &TypeCheckContext,
+    ) -> Rc<ExprNode> {
+        if !self.global_values.contains_key(name) {
+            error_exit(&format!("{} not found.", name.to_string()));
         }
-        let main_ty = make_io_unit_ty();
-        let inst_name = self.require_instantiated_symbol(&main_func_name, &main_ty);
+        let inst_name = self.require_instantiated_symbol(name, ty);
         self.instantiate_symbols(tc);
-        expr_var(inst_name, None).set_inferred_type(main_ty)
+        expr_var(inst_name, None).set_inferred_type(ty.clone())
     }
 
     // Instantiate expression.
@@ -910,6 +1008,7 @@ impl Program {
                     expr.clone()
                 } else {
                     let ty = tr.substitute_type(&expr.ty.as_ref().unwrap());
+                    validate_c_callback_instance(&v.name, &ty, &expr.source);
                     let instance = self.require_instantiated_symbol(&v.name, &ty);
                     let v = v.set_name(instance);
                     expr.set_var_var(v)
@@ -1263,6 +1362,9 @@ impl Program {
         self.unresolved_imports
             .append(&mut other.unresolved_imports);
 
+        // Merge export statements.
+        self.export_statements.append(&mut other.export_statements);
+
         // Merge types.
         self.add_type_defns(other.type_defns);
 
@@ -194,6 +194,32 @@ impl TyCon {
         panic!("call get_c_type for {}", self.to_string())
     }
 
+    // Is this the unit type `()`?
+    pub fn is_unit(&self) -> bool {
+        self.name == make_tuple_name(0)
+    }
+
+    // Get the type name in C, used in generated header files.
+    pub fn get_c_type_name(&self) -> &'static str {
+        if self.is_unit() {
+            return "void";
+        }
+        match self.name.name.as_str() {
+            I8_NAME => "int8_t",
+            U8_NAME => "uint8_t",
+            I16_NAME => "int16_t",
+            U16_NAME => "uint16_t",
+            I32_NAME => "int32_t",
+            U32_NAME => "uint32_t",
+            I64_NAME => "int64_t",
+            U64_NAME => "uint64_t",
+            F32_NAME => "float",
+            F64_NAME => "double",
+            PTR_NAME => "void*",
+            _ => panic!("call get_c_type_name for {}", self.to_string()),
+        }
+    }
+
     pub fn is_singned_intger(self: &TyCon) -> bool {
         if self.name.namespace != NameSpace::new_str(&[STD_NAME]) {
             panic!("call is_singned_intger for {}", self.to_string())
@@ -783,14 +809,112 @@ impl Clone for Type {
 impl TypeNode {
     // Stringify. Name of type variables are normalized to names such as "t0", "t1", etc.
     pub fn to_string_normalize(self: &Rc<TypeNode>) -> String {
+        self.normalize_tyvars().to_string()
+    }
+
+    // Rename type variables to names such as "t0", "t1", etc.
+    pub fn normalize_tyvars(self: &Rc<TypeNode>) -> Rc<TypeNode> {
+        TypeNode::normalize_tyvars_of(&[self.clone()])
+            .pop()
+            .unwrap()
+    }
+
+    // Rename type variables of types to names such as "t0", "t1", etc. by a common renaming,
+    // so that a name refers to the same type variable in all types.
+    pub fn normalize_tyvars_of(tys: &[Rc<TypeNode>]) -> Vec<Rc<TypeNode>> {
         let mut tyvar_num = -1;
+        let mut renamed: HashSet<Name> = HashSet::default();
         let mut s = Substitution::default();
-        for (tyvar, kind) in self.free_vars() {
-            tyvar_num += 1;
-            let new_name = format!("t{}", tyvar_num);
-            s.add_substitution(&Substitution::single(&tyvar, type_tyvar(&new_name, &kind)))
+        for ty in tys {
+            for (tyvar, kind) in ty.free_vars() {
+                if !renamed.insert(tyvar.clone()) {
+                    continue;
+                }
+                tyvar_num += 1;
+                let new_name = format!("t{}", tyvar_num);
+                s.add_substitution(&Substitution::single(&tyvar, type_tyvar(&new_name, &kind)))
+            }
+        }
+        tys.iter().map(|ty| s.substitute_type(ty)).collect()
+    }
+
+    // Stringify with normalization, and also returns the range of the component specified by `path` in the resulting string.
+    // If `path` cannot be followed (e.g., it goes into a type variable), the deepest reachable component is used.
+    pub fn to_string_normalize_highlight(
+        self: &Rc<TypeNode>,
+        path: &[TypeComponent],
+    ) -> (String, (usize, usize)) {
+        self.normalize_tyvars().to_string_highlight(path)
+    }
+
+    // Stringify, and also returns the range of the component specified by `path` in the resulting string.
+    pub fn to_string_highlight(
+        self: &Rc<TypeNode>,
+        path: &[TypeComponent],
+    ) -> (String, (usize, usize)) {
+        const PLACEHOLDER: &str = "#HIGHLIGHT#";
+        let placeholder = type_tyvar(PLACEHOLDER, &kind_star());
+        let (replaced, target, brace) = self.replace_component(path, &placeholder);
+        let replaced = replaced.to_string();
+        let mut target = target.to_string();
+        let pos = replaced.find(PLACEHOLDER).unwrap();
+        let start = if brace { pos + 1 } else { pos };
+        let end = start + target.len();
+        if brace {
+            target = format!("({})", target);
+        }
+        (replaced.replacen(PLACEHOLDER, &target, 1), (start, end))
+    }
+
+    // Replace the component specified by `path` with `placeholder`.
+    // Returns the replaced type, the replaced component, and whether the component is braced when stringified.
+    fn replace_component(
+        self: &Rc<TypeNode>,
+        path: &[TypeComponent],
+        placeholder: &Rc<TypeNode>,
+    ) -> (Rc<TypeNode>, Rc<TypeNode>, bool) {
+        if path.is_empty() {
+            return (placeholder.clone(), self.clone(), false);
+        }
+        match (&self.ty, path[0]) {
+            (Type::TyApp(fun, arg), TypeComponent::TyAppArg) => {
+                let (new_arg, target, mut brace) = arg.replace_component(&path[1..], placeholder);
+                if Rc::ptr_eq(&target, arg) {
+                    brace = !self.is_tuple_type() && arg.is_braced_as_tyapp_arg();
+                }
+                (type_tyapp(fun.clone(), new_arg), target, brace)
+            }
+            (Type::FunTy(src, dst), TypeComponent::FunTySrc) => {
+                let (new_src, target, mut brace) = src.replace_component(&path[1..], placeholder);
+                if Rc::ptr_eq(&target, src) {
+                    brace = src.is_closure();
+                }
+                (type_fun(new_src, dst.clone()), target, brace)
+            }
+            (Type::FunTy(src, dst), TypeComponent::FunTyDst) => {
+                let (new_dst, target, brace) = dst.replace_component(&path[1..], placeholder);
+                (type_fun(src.clone(), new_dst), target, brace)
+            }
+            _ => (placeholder.clone(), self.clone(), false),
+        }
+    }
+
+    // Check if the top-level type constructor is a tuple.
+    fn is_tuple_type(&self) -> bool {
+        match self.toplevel_tycon() {
+            Some(tc) => get_tuple_n(&tc.name).is_some(),
+            None => false,
+        }
+    }
+
+    // Check if this type has to be braced when it appears as an argument of type application.
+    fn is_braced_as_tyapp_arg(&self) -> bool {
+        match &self.ty {
+            Type::TyVar(_) => false,
+            Type::TyCon(_) => false,
+            Type::TyApp(_, _) => !self.is_tuple_type(),
+            Type::FunTy(_, _) => true,
         }
-        s.substitute_type(self).to_string()
     }
 
     // Stringify.
@@ -816,17 +940,7 @@ impl TypeNode {
                         None => {}
                     }
                 }
-                let arg_brace_needed = match &arg.ty {
-                    Type::TyVar(_) => false,
-                    Type::TyCon(_) => false,
-                    Type::TyApp(fun, _) => {
-                        let tycon = fun.toplevel_tycon();
-                        let is_tuple =
-                            tycon.is_some() && get_tuple_n(&tycon.unwrap().name).is_some();
-                        !is_tuple
-                    }
-                    Type::FunTy(_, _) => true,
-                };
+                let arg_brace_needed = arg.is_braced_as_tyapp_arg();
                 let tyfun = fun.to_string();
                 let arg = arg.to_string();
                 if arg_brace_needed {
@@ -934,6 +1048,14 @@ pub fn tycon(name: FullName) -> Rc<TyCon> {
     Rc::new(TyCon { name })
 }
 
+// A component of a type, used to specify a path from a type to one of its sub-terms.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TypeComponent {
+    TyAppArg,
+    FunTySrc,
+    FunTyDst,
+}
+
 // Additional information of types.
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub struct TypeInfo {
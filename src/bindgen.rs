@@ -0,0 +1,778 @@
+// An implementation of `fix bindgen` command.
+//
+// It reads a C header file and generates a Fix module which declares a wrapper function `IO` for each C function prototype,
+// and a global value for each integer or floating point constant defined by `#define` or `enum`.
+// This is not a full C parser: the preprocessor is not run, and declarations which cannot be understood are skipped with a comment.
+
+use std::fs;
+
+use super::*;
+
+// C keywords that are ignored when mapping a C type to a Fix type.
+const C_QUALIFIERS: [&str; 8] = [
+    "const", "volatile", "restrict", "extern", "static", "inline", "register", "signed",
+];
+
+// A function implementing `fix bindgen` command.
+pub fn bindgen_command(header_path: &Path, module_name: Option<String>, out_path: Option<PathBuf>) {
+    let header = match read_file(header_path) {
+        Ok(src) => src,
+        Err(e) => error_exit(&e),
+    };
+    let module_name = module_name.unwrap_or_else(|| {
+        header_path
+            .file_stem()
+            .map(|s| to_camel_case(&s.to_string_lossy()))
+            .unwrap_or_default()
+    });
+    if module_name.is_empty() || !module_name.chars().next().unwrap().is_ascii_uppercase() {
+        error_exit(&format!(
+            "Invalid module name `{}`. Specify it by `--module`.",
+            module_name
+        ));
+    }
+    let source = generate_bindings(&header, &module_name);
+    match out_path {
+        Some(out_path) => {
+            if let Err(e) = fs::write(&out_path, source) {
+                error_exit(&format!(
+                    "Failed to write `{}`: {}",
+                    out_path.to_string_lossy(),
+                    e
+                ));
+            }
+        }
+        None => print!("{}", source),
+    }
+}
+
+// Generate the source code of a Fix module from the content of a C header file.
+pub fn generate_bindings(header: &str, module_name: &str) -> String {
+    let mut gen = BindingGenerator::default();
+    let code = remove_comments(header);
+    let code = gen.process_preprocessor_lines(&code);
+    for decl in split_declarations(&tokenize(&code)) {
+        gen.process_declaration(&decl);
+    }
+
+    let mut source = format!("module {};\n", module_name);
+    source += "// This module is generated by `fix bindgen`.\n";
+    for item in gen.items {
+        source += "\n";
+        source += &item;
+    }
+    source
+}
+
+#[derive(Default)]
+struct BindingGenerator {
+    // Fix types of typedef'd names. `Err` holds the reason why the type cannot be used in `CALL_C`.
+    typedefs: HashMap<String, Result<String, String>>,
+    // Names of defined Fix values, to avoid duplicate definitions.
+    defined: HashSet<String>,
+    // Generated definitions.
+    items: Vec<String>,
+}
+
+impl BindingGenerator {
+    // Collect constants defined by `#define NAME literal`, and remove all preprocessor lines.
+    fn process_preprocessor_lines(&mut self, code: &str) -> String {
+        let code = code.replace("\\\r\n", " ").replace("\\\n", " ");
+        let mut rest = String::new();
+        for line in code.lines() {
+            let trimmed = line.trim_start();
+            if !trimmed.starts_with('#') {
+                rest += line;
+                rest += "\n";
+                continue;
+            }
+            let directive = trimmed[1..].trim_start();
+            if !directive.starts_with("define") {
+                continue;
+            }
+            let mut words = directive["define".len()..]
+                .trim()
+                .splitn(2, char::is_whitespace);
+            let name = words.next().unwrap_or("");
+            let value = words.next().unwrap_or("").trim();
+            if name.is_empty() || name.contains('(') || value.is_empty() {
+                continue; // Function-like macros or flags.
+            }
+            if let Some((ty, lit)) = c_number_literal(value) {
+                self.add_constant(name, &ty, &lit, &format!("#define {} {}", name, value));
+            }
+        }
+        rest
+    }
+
+    fn process_declaration(&mut self, decl: &[String]) {
+        if decl.is_empty() {
+            return;
+        }
+        let decl = remove_attributes(decl);
+        let text = c_text(&decl);
+        if decl[0] == "typedef" {
+            self.process_typedef(&decl[1..]);
+        } else if decl[0] == "enum" && decl.contains(&"{".to_string()) {
+            self.process_enum(&decl);
+        } else if (decl[0] == "struct" || decl[0] == "union") && decl.contains(&"{".to_string()) {
+            // Definitions of structs are not needed since they are passed by pointers.
+        } else if decl.contains(&"(".to_string()) {
+            if let Err(reason) = self.process_function(&decl) {
+                self.items
+                    .push(format!("// Skipped `{}`: {}\n", text, reason));
+            }
+        }
+    }
+
+    fn process_typedef(&mut self, decl: &[String]) {
+        if decl.contains(&"(".to_string()) {
+            // Function pointer type, e.g., `typedef void (*callback)(int);`.
+            if let Some(pos) = decl.iter().position(|t| t == "*") {
+                if let Some(name) = decl.get(pos + 1) {
+                    self.typedefs.insert(name.clone(), Ok(PTR_NAME.to_string()));
+                }
+            }
+            return;
+        }
+        let name = match decl.last() {
+            Some(name) if is_identifier(name) => name.clone(),
+            _ => return,
+        };
+        let ty_tokens = &decl[..decl.len() - 1];
+        if ty_tokens.first().map(|t| t.as_str()) == Some("enum")
+            && ty_tokens.contains(&"{".to_string())
+        {
+            self.process_enum(ty_tokens);
+        }
+        let ty = self.fix_type(ty_tokens);
+        self.typedefs.insert(name, ty);
+    }
+
+    // Define constants for enumerators of `enum { ... }`.
+    fn process_enum(&mut self, decl: &[String]) {
+        let start = decl.iter().position(|t| t == "{").unwrap();
+        let end = decl.iter().rposition(|t| t == "}").unwrap_or(decl.len());
+        let mut next_value = Some(0_i64);
+        for item in decl[start + 1..end].split(|t| t == ",") {
+            let name = match item.first() {
+                Some(name) if is_identifier(name) => name,
+                _ => continue,
+            };
+            if item.len() > 1 && item[1] == "=" {
+                next_value = c_number_literal(&item[2..].join("")).and_then(|(ty, lit)| {
+                    if ty == I64_NAME {
+                        parse_int(&lit)
+                    } else {
+                        None
+                    }
+                });
+            }
+            match next_value {
+                Some(value) => {
+                    self.add_constant(
+                        name,
+                        I32_NAME,
+                        &typed_literal(&value.to_string(), I32_NAME),
+                        &c_text(item),
+                    );
+                    next_value = Some(value + 1);
+                }
+                None => self.items.push(format!(
+                    "// Skipped enumerator `{}`: its value is not a literal.\n",
+                    name
+                )),
+            }
+        }
+    }
+
+    // Generate a wrapper function for a function prototype.
+    fn process_function(&mut self, decl: &[String]) -> Result<(), String> {
+        // Find the parameter list.
+        let open = decl.iter().position(|t| t == "(").unwrap();
+        if open == 0 || !is_identifier(&decl[open - 1]) {
+            return Err("not a function prototype.".to_string());
+        }
+        let c_name = decl[open - 1].clone();
+        let close = matching_paren(decl, open).ok_or("unbalanced parentheses.".to_string())?;
+        if close + 1 != decl.len() {
+            return Err("not a function prototype.".to_string());
+        }
+        let ret_ty = self.fix_type(&decl[..open - 1])?;
+
+        // Parse parameters.
+        let mut param_tys = vec![];
+        let mut param_names = vec![];
+        let params = split_top_level(&decl[open + 1..close], ",");
+        let is_void = params.len() == 1 && params[0].len() == 1 && params[0][0] == "void";
+        if !is_void {
+            for (i, param) in params.iter().enumerate() {
+                if param.is_empty() {
+                    continue;
+                }
+                if param.len() == 1 && param[0] == "..." {
+                    return Err("variadic functions are not supported.".to_string());
+                }
+                let (ty_tokens, name) = split_param_name(param);
+                let ty = self.fix_type(&ty_tokens)?;
+                if ty == "()" {
+                    return Err("a parameter of type `void`.".to_string());
+                }
+                param_tys.push(ty);
+                let name = name
+                    .map(|name| to_fix_value_name(&name))
+                    .unwrap_or(format!("arg{}", i));
+                param_names.push(if param_names.contains(&name) {
+                    format!("{}{}", name, i)
+                } else {
+                    name
+                });
+            }
+        }
+
+        // Generate the wrapper.
+        let fix_name = to_fix_value_name(&c_name);
+        if !self.defined.insert(fix_name.clone()) {
+            return Err(format!("`{}` is already defined.", fix_name));
+        }
+        let mut sig = param_tys
+            .iter()
+            .map(|ty| ty.clone() + " -> ")
+            .collect::<String>();
+        sig += &format!("IO {}", ret_ty);
+        let call_c = std::iter::once(format!(
+            "CALL_C[{} {}({})",
+            ret_ty,
+            c_name,
+            param_tys.join(", ")
+        ))
+        .chain(param_names.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(", ")
+            + "]";
+        let body = if param_names.is_empty() {
+            format!("IO {{ _data : |_| {} }}", call_c)
+        } else {
+            format!(
+                "|{}| IO {{ _data : |_| {} }}",
+                param_names.join(", "),
+                call_c
+            )
+        };
+        self.items.push(format!(
+            "// {};\n{} : {};\n{} = {};\n",
+            c_text(decl),
+            fix_name,
+            sig,
+            fix_name,
+            body
+        ));
+        Ok(())
+    }
+
+    fn add_constant(&mut self, c_name: &str, ty: &str, lit: &str, comment: &str) {
+        let fix_name = to_fix_value_name(c_name);
+        if !self.defined.insert(fix_name.clone()) {
+            return;
+        }
+        self.items.push(format!(
+            "// {}\n{} : {};\n{} = {};\n",
+            comment, fix_name, ty, fix_name, lit
+        ));
+    }
+
+    // Get the Fix type (one of the types allowed in `CALL_C`) corresponding to a C type.
+    fn fix_type(&self, tokens: &[String]) -> Result<String, String> {
+        if tokens.iter().any(|t| t == "*" || t == "[") {
+            return Ok(PTR_NAME.to_string());
+        }
+        let words = tokens
+            .iter()
+            .filter(|t| !C_QUALIFIERS.contains(&t.as_str()))
+            .map(|t| t.as_str())
+            .collect::<Vec<_>>();
+        let text = c_text(tokens);
+        match words.first() {
+            Some(&"enum") => return Ok(I32_NAME.to_string()),
+            Some(&"struct") | Some(&"union") => {
+                return Err(format!("`{}` cannot be passed by value.", text))
+            }
+            _ => {}
+        }
+        if words.len() == 1 {
+            if let Some(ty) = self.typedefs.get(words[0]) {
+                return ty.clone();
+            }
+        }
+        let unsigned = words.contains(&"unsigned");
+        let longs = words.iter().filter(|w| **w == "long").count();
+        let base = words
+            .iter()
+            .filter(|w| **w != "unsigned" && **w != "long")
+            .collect::<Vec<_>>();
+        let ty = match (base.as_slice(), longs) {
+            ([], 0) => Some(if unsigned { U32_NAME } else { I32_NAME }),
+            ([], _) | ([&"int"], 1..=2) => Some(if unsigned { U64_NAME } else { I64_NAME }),
+            ([&"void"], 0) => Some("()"),
+            ([&"char"], 0) => Some(if unsigned { U8_NAME } else { I8_NAME }),
+            ([&"short"], 0) | ([&"short", &"int"], 0) => {
+                Some(if unsigned { U16_NAME } else { I16_NAME })
+            }
+            ([&"int"], 0) => Some(if unsigned { U32_NAME } else { I32_NAME }),
+            ([&"float"], 0) => Some(F32_NAME),
+            ([&"double"], 0) => Some(F64_NAME),
+            ([&"_Bool"], 0) | ([&"bool"], 0) => Some(BOOL_NAME),
+            ([&"int8_t"], 0) => Some(I8_NAME),
+            ([&"uint8_t"], 0) => Some(U8_NAME),
+            ([&"int16_t"], 0) => Some(I16_NAME),
+            ([&"uint16_t"], 0) => Some(U16_NAME),
+            ([&"int32_t"], 0) => Some(I32_NAME),
+            ([&"uint32_t"], 0) => Some(U32_NAME),
+            ([&"int64_t"], 0)
+            | ([&"ssize_t"], 0)
+            | ([&"ptrdiff_t"], 0)
+            | ([&"intptr_t"], 0)
+            | ([&"off_t"], 0)
+            | ([&"time_t"], 0) => Some(I64_NAME),
+            ([&"uint64_t"], 0) | ([&"size_t"], 0) | ([&"uintptr_t"], 0) => Some(U64_NAME),
+            _ => None,
+        };
+        match ty {
+            Some(ty) => Ok(ty.to_string()),
+            None => Err(format!("unknown type `{}`.", text)),
+        }
+    }
+}
+
+// Replace comments by spaces.
+fn remove_comments(src: &str) -> String {
+    let mut out = String::new();
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'/') {
+            while let Some(c) = chars.next() {
+                if c == '\n' {
+                    out.push('\n');
+                    break;
+                }
+            }
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut prev = ' ';
+            while let Some(c) = chars.next() {
+                if prev == '*' && c == '/' {
+                    break;
+                }
+                if c == '\n' {
+                    out.push('\n');
+                }
+                prev = c;
+            }
+            out.push(' ');
+        } else if c == '"' || c == '\'' {
+            // Keep string literals as they are, so that "//" in them is not treated as a comment.
+            out.push(c);
+            while let Some(d) = chars.next() {
+                out.push(d);
+                if d == '\\' {
+                    if let Some(e) = chars.next() {
+                        out.push(e);
+                    }
+                } else if d == c {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// Split C source into tokens: identifiers, numbers, string literals, `...` and single punctuation characters.
+fn tokenize(src: &str) -> Vec<String> {
+    let chars = src.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphanumeric()
+            || c == '_'
+            || c == '.' && chars.get(i + 1) != Some(&'.')
+        {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if c == '.' {
+            let start = i;
+            while i < chars.len() && chars[i] == '.' {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+            tokens.push(chars[start..i.min(chars.len())].iter().collect());
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+    tokens
+}
+
+// Split tokens into top-level declarations.
+// Bodies of `extern "C" { ... }` are treated as top-level, and function definitions (e.g., `static inline` functions) are dropped.
+fn split_declarations(tokens: &[String]) -> Vec<Vec<String>> {
+    let mut decls = vec![];
+    let mut cur: Vec<String> = vec![];
+    let mut depth = 0;
+    let mut extern_blocks = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let t = &tokens[i];
+        if depth == 0
+            && t == "extern"
+            && tokens.get(i + 1).map(|t| t.starts_with('"')) == Some(true)
+        {
+            if tokens.get(i + 2).map(|t| t.as_str()) == Some("{") {
+                extern_blocks += 1;
+                i += 3;
+            } else {
+                i += 2;
+            }
+            continue;
+        }
+        if depth == 0 && t == "}" && extern_blocks > 0 {
+            extern_blocks -= 1;
+            i += 1;
+            continue;
+        }
+        if depth == 0 && t == "{" && cur.last().map(|t| t.as_str()) == Some(")") {
+            // Function definition: skip its body.
+            let mut body_depth = 0;
+            while i < tokens.len() {
+                if tokens[i] == "{" {
+                    body_depth += 1;
+                } else if tokens[i] == "}" {
+                    body_depth -= 1;
+                    if body_depth == 0 {
+                        break;
+                    }
+                }
+                i += 1;
+            }
+            cur.clear();
+            i += 1;
+            continue;
+        }
+        if t == "{" {
+            depth += 1;
+        } else if t == "}" {
+            depth -= 1;
+        }
+        if depth == 0 && t == ";" {
+            decls.push(std::mem::take(&mut cur));
+        } else {
+            cur.push(t.clone());
+        }
+        i += 1;
+    }
+    decls
+}
+
+// Remove compiler-specific annotations such as `__attribute__((...))` or `__THROW`.
+fn remove_attributes(tokens: &[String]) -> Vec<String> {
+    let mut out = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].starts_with("__") && tokens[i] != "__restrict" {
+            i += 1;
+            if tokens.get(i).map(|t| t.as_str()) == Some("(") {
+                i = matching_paren(tokens, i)
+                    .map(|j| j + 1)
+                    .unwrap_or(tokens.len());
+            }
+            continue;
+        }
+        out.push(tokens[i].clone());
+        i += 1;
+    }
+    out
+}
+
+// Find the index of `)` corresponding to `(` at `open`.
+fn matching_paren(tokens: &[String], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, t) in tokens.iter().enumerate().skip(open) {
+        if t == "(" {
+            depth += 1;
+        } else if t == ")" {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+// Split tokens by a separator which is not enclosed by parentheses or brackets.
+fn split_top_level(tokens: &[String], sep: &str) -> Vec<Vec<String>> {
+    let mut items = vec![vec![]];
+    let mut depth = 0;
+    for t in tokens {
+        match t.as_str() {
+            "(" | "[" => depth += 1,
+            ")" | "]" => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 && t == sep {
+            items.push(vec![]);
+        } else {
+            items.last_mut().unwrap().push(t.clone());
+        }
+    }
+    items
+}
+
+// Split a parameter declaration into its type and its name (if exists).
+fn split_param_name(param: &[String]) -> (Vec<String>, Option<String>) {
+    // Function pointer, e.g., `void (*callback)(int)`.
+    if let Some(pos) = param.iter().position(|t| t == "(") {
+        let name = param[pos..].iter().find(|t| is_identifier(t)).cloned();
+        return (vec![PTR_NAME.to_string(), "*".to_string()], name);
+    }
+    // Arrays, e.g., `int xs[]`.
+    let (param, is_array) = match param.iter().position(|t| t == "[") {
+        Some(pos) => (&param[..pos], true),
+        None => (param, false),
+    };
+    let type_words = [
+        "void",
+        "char",
+        "short",
+        "int",
+        "long",
+        "float",
+        "double",
+        "signed",
+        "unsigned",
+        "_Bool",
+        "bool",
+        "const",
+        "volatile",
+        "restrict",
+        "__restrict",
+    ];
+    let idents = param.iter().filter(|t| is_identifier(t)).count();
+    let mut ty = param.to_vec();
+    let mut name = None;
+    if let Some(last) = param.last() {
+        if is_identifier(last) && idents >= 2 && !type_words.contains(&last.as_str()) {
+            let prev = &param[param.len() - 2];
+            if prev != "struct" && prev != "union" && prev != "enum" {
+                name = Some(last.clone());
+                ty.pop();
+            }
+        }
+    }
+    if is_array {
+        ty.push("*".to_string());
+    }
+    (ty, name)
+}
+
+// Convert a C number literal (e.g., `0x10UL`, `1.5f`, `(-1)`) to a Fix literal and its type.
+fn c_number_literal(value: &str) -> Option<(String, String)> {
+    let mut value = value.trim();
+    while value.starts_with('(') && value.ends_with(')') {
+        value = value[1..value.len() - 1].trim();
+    }
+    let (sign, body) = match value.strip_prefix('-') {
+        Some(body) => ("-", body.trim()),
+        None => ("", value),
+    };
+    let lower = body.to_ascii_lowercase();
+    if lower.is_empty() || !(lower.as_bytes()[0].is_ascii_digit() || lower.starts_with('.')) {
+        return None;
+    }
+    if lower.starts_with("0x") {
+        let digits = lower[2..].trim_end_matches(|c| c == 'u' || c == 'l');
+        let ty = if lower[2..].contains('u') {
+            U64_NAME
+        } else {
+            I64_NAME
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return Some((
+            ty.to_string(),
+            typed_literal(&format!("{}0x{}", sign, digits), ty),
+        ));
+    }
+    if lower.contains('.') || lower.contains('e') {
+        let mut digits = lower.trim_end_matches(|c| c == 'f' || c == 'l').to_string();
+        if digits.starts_with('.') {
+            digits = "0".to_string() + &digits;
+        }
+        let (mantissa, exponent) = match digits.find('e') {
+            Some(pos) => (digits[..pos].to_string(), digits[pos..].to_string()),
+            None => (digits.clone(), "".to_string()),
+        };
+        let mantissa = if !mantissa.contains('.') {
+            mantissa + ".0"
+        } else if mantissa.ends_with('.') {
+            mantissa + "0"
+        } else {
+            mantissa
+        };
+        let lit = format!("{}{}{}", sign, mantissa, exponent);
+        let valid = mantissa
+            .split('.')
+            .all(|s| s.chars().all(|c| c.is_ascii_digit()))
+            && exponent
+                .trim_start_matches('e')
+                .trim_start_matches(|c| c == '+' || c == '-')
+                .chars()
+                .all(|c| c.is_ascii_digit());
+        return if valid {
+            Some((F64_NAME.to_string(), lit))
+        } else {
+            None
+        };
+    }
+    let digits = lower.trim_end_matches(|c| c == 'u' || c == 'l');
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let ty = if lower.contains('u') {
+        U64_NAME
+    } else {
+        I64_NAME
+    };
+    let lit = if digits.len() > 1 && digits.starts_with('0') {
+        format!("{}0o{}", sign, &digits[1..]) // Octal literal.
+    } else {
+        format!("{}{}", sign, digits)
+    };
+    Some((ty.to_string(), typed_literal(&lit, ty)))
+}
+
+fn typed_literal(lit: &str, ty: &str) -> String {
+    if ty == I64_NAME {
+        lit.to_string()
+    } else {
+        format!("{}_{}", lit, ty)
+    }
+}
+
+// Parse a Fix integer literal generated by `c_number_literal`.
+fn parse_int(lit: &str) -> Option<i64> {
+    let (sign, body) = match lit.strip_prefix('-') {
+        Some(body) => (-1, body),
+        None => (1, lit),
+    };
+    let value = if let Some(hex) = body.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(oct) = body.strip_prefix("0o") {
+        i64::from_str_radix(oct, 8).ok()?
+    } else {
+        body.parse::<i64>().ok()?
+    };
+    Some(sign * value)
+}
+
+// Join tokens into C source text.
+fn c_text(tokens: &[String]) -> String {
+    let mut text = String::new();
+    for t in tokens {
+        let attach = [")", ",", "[", "]", "("].contains(&t.as_str())
+            || text.ends_with('(')
+            || text.ends_with('[');
+        if !text.is_empty() && !attach {
+            text.push(' ');
+        }
+        text += t;
+    }
+    text
+}
+
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Convert a C name to a valid Fix value name, e.g., `SDL_Init` to `sdl_init`.
+fn to_fix_value_name(c_name: &str) -> String {
+    let mut name = String::new();
+    let chars = c_name.chars().collect::<Vec<_>>();
+    let is_valid_already = chars[0].is_ascii_lowercase() || chars[0] == '_';
+    if is_valid_already {
+        name = c_name.to_string();
+    } else {
+        for (i, c) in chars.iter().enumerate() {
+            if c.is_ascii_uppercase() {
+                let prev_lower =
+                    i > 0 && (chars[i - 1].is_ascii_lowercase() || chars[i - 1].is_ascii_digit());
+                let next_lower = chars.get(i + 1).map(|c| c.is_ascii_lowercase()) == Some(true);
+                let prev_upper = i > 0 && chars[i - 1].is_ascii_uppercase();
+                if prev_lower || (prev_upper && next_lower) {
+                    name.push('_');
+                }
+                name.push(c.to_ascii_lowercase());
+            } else {
+                name.push(*c);
+            }
+        }
+        name = name.replace("__", "_");
+    }
+    let keywords = [
+        "let",
+        "eval",
+        "in",
+        "if",
+        "else",
+        "do",
+        "type",
+        "trait",
+        "impl",
+        "true",
+        "false",
+        "nullptr",
+        "module",
+        "import",
+        "namespace",
+    ];
+    if keywords.contains(&name.as_str()) {
+        name += "_";
+    }
+    name
+}
+
+// Convert a file name to a module name, e.g., `my_lib` to `MyLib`.
+fn to_camel_case(s: &str) -> String {
+    s.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut cs = w.chars();
+            let head = cs.next().unwrap().to_ascii_uppercase();
+            std::iter::once(head).chain(cs).collect::<String>()
+        })
+        .collect()
+}
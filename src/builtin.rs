@@ -3799,6 +3799,154 @@ pub fn get_retain_function_of_boxed_value() -> (Rc<ExprNode>, Rc<Scheme>) {
     (expr, scm)
 }
 
+// Get the C function type of a callback made from a Fix function of type `fun_ty`.
+pub fn c_callback_function_type(fun_ty: &Rc<TypeNode>) -> Result<ExportedFunctionType, String> {
+    match ExportedFunctionType::from_type(fun_ty, true) {
+        Ok(c_fun_ty) if fun_ty.is_closure() => Ok(c_fun_ty),
+        Ok(_) => Err(format!(
+            "Cannot make a C callback from a value of type `{}`: it is not a function.",
+            fun_ty.to_string()
+        )),
+        Err(msg) => Err(format!(
+            "Cannot make a C callback from a function of type `{}`: {}",
+            fun_ty.to_string(),
+            msg
+        )),
+    }
+}
+
+// Check the type argument of an instance of `Std::FFI::unsafe_make_c_callback(_context_first)`, so that an unsupported signature is reported at the call site.
+pub fn validate_c_callback_instance(name: &FullName, ty: &Rc<TypeNode>, src: &Option<Span>) {
+    let is_make_c_callback = [
+        "unsafe_make_c_callback",
+        "unsafe_make_c_callback_context_first",
+    ]
+    .iter()
+    .any(|n| *name == FullName::from_strs(&[STD_NAME, FFI_NAME], n));
+    if !is_make_c_callback || !ty.is_closure() {
+        return;
+    }
+    let fun_ty = ty.get_lambda_srcs()[0].clone();
+    if !fun_ty.free_vars().is_empty() {
+        return;
+    }
+    if let Err(msg) = c_callback_function_type(&fun_ty) {
+        error_exit_with_src(&msg, src);
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InlineLLVMGetCCallbackFunctionBody {
+    var_name: String,
+    // Is the context pointer the first parameter of the C function (otherwise it is the last one)?
+    context_first: bool,
+}
+
+impl InlineLLVMGetCCallbackFunctionBody {
+    pub fn generate<'c, 'm, 'b>(
+        &self,
+        gc: &mut GenerationContext<'c, 'm>,
+        _ret_ty: &Rc<TypeNode>,
+        rvo: Option<Object<'c>>,
+        _borrowed_vars: &Vec<FullName>,
+    ) -> Object<'c> {
+        // Get argument of type `Boxed f`.
+        let obj = gc.get_var(&FullName::local(&self.var_name)).ptr.get(gc);
+        gc.release(obj.clone());
+        let fun_ty = obj.ty.field_types(gc.type_env())[0].clone();
+        // Usually validated at instantiation (see `validate_c_callback_instance`).
+        let c_fun_ty = match c_callback_function_type(&fun_ty) {
+            Ok(c_fun_ty) => c_fun_ty,
+            Err(msg) => error_exit(&msg),
+        };
+
+        // Get the trampoline function, which takes the context pointer to `Boxed f` and the arguments of `f`.
+        let trampoline_name = format!(
+            "c_callback#{}#{}",
+            obj.ty.to_string_normalize(),
+            if self.context_first {
+                "context_first"
+            } else {
+                "context_last"
+            }
+        );
+        let func = if let Some(func) = gc.module.get_function(&trampoline_name) {
+            func
+        } else {
+            let context_idx = if self.context_first {
+                0
+            } else {
+                c_fun_ty.c_params().len()
+            };
+            let trampoline_ty = c_function_type_of_fix_function(gc, &c_fun_ty, Some(context_idx));
+            let trampoline = gc
+                .module
+                .add_function(&trampoline_name, trampoline_ty, None);
+            let bb = gc.context.append_basic_block(trampoline, "entry");
+            let _builder_guard = gc.push_builder();
+            gc.builder().position_at_end(bb);
+
+            // The context is owned by the C side, so retain it before extracting the function, which releases the context.
+            let mut c_args = trampoline.get_params();
+            let context_ptr = c_args.remove(context_idx).into_pointer_value();
+            let context_ptr = gc.cast_pointer(context_ptr, ptr_to_object_type(gc.context));
+            let boxed = Object::new(context_ptr, obj.ty.clone());
+            gc.retain(boxed.clone());
+            let fun = ObjectFieldType::get_struct_fields(gc, &boxed, vec![(0, None)])[0].clone();
+            build_call_fix_function_from_c(gc, &c_fun_ty, fun, c_args);
+
+            trampoline
+        };
+        let func_ptr = func.as_global_value().as_pointer_value();
+        let func_ptr = gc.cast_pointer(func_ptr, ptr_to_object_type(gc.context));
+
+        let ret = if rvo.is_some() {
+            rvo.unwrap()
+        } else {
+            allocate_obj(
+                make_ptr_ty(),
+                &vec![],
+                None,
+                gc,
+                Some("ret_val@unsafe_get_c_callback_function"),
+            )
+        };
+        ret.store_field_nocap(gc, 0, func_ptr);
+        ret
+    }
+}
+
+// `Boxed f -> Ptr`, which returns a pointer to the C function calling `f` stored in the context pointer.
+pub fn get_c_callback_function(context_first: bool) -> (Rc<ExprNode>, Rc<Scheme>) {
+    const TYPE_NAME: &str = "f";
+    const VAR_NAME: &str = "x";
+    let boxed_type = type_tyapp(
+        type_tycon(&tycon(FullName::from_strs(&[STD_NAME], BOXED_NAME))),
+        type_tyvar(TYPE_NAME, &kind_star()),
+    );
+    let ret_type = make_ptr_ty();
+    let scm = Scheme::generalize(
+        HashMap::from([(TYPE_NAME.to_string(), kind_star())]),
+        vec![],
+        type_fun(boxed_type, ret_type.clone()),
+    );
+    let expr = expr_abs(
+        vec![var_local(VAR_NAME)],
+        expr_llvm(
+            LLVMGenerator::GetCCallbackFunctionBody(InlineLLVMGetCCallbackFunctionBody {
+                var_name: VAR_NAME.to_string(),
+                context_first,
+            }),
+            vec![FullName::local(VAR_NAME)],
+            format!("unsafe_get_c_callback_function({})", VAR_NAME),
+            ret_type,
+            None,
+        ),
+        None,
+    );
+    (expr, scm)
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct InlineLLVMMarkThreadedFunctionBody {
     var_name: String,
@@ -3883,6 +4031,145 @@ pub fn quiet_nan_value(type_name: &str) -> (Rc<ExprNode>, Rc<Scheme>) {
     (expr, scm)
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InlineLLVMStoreUnalignedBody {
+    ptr_name: String,
+    value_name: String,
+}
+
+impl InlineLLVMStoreUnalignedBody {
+    pub fn generate<'c, 'm, 'b>(
+        &self,
+        gc: &mut GenerationContext<'c, 'm>,
+        ret_ty: &Rc<TypeNode>,
+        rvo: Option<Object<'c>>,
+        _borrowed_vars: &Vec<FullName>,
+    ) -> Object<'c> {
+        // Get arguments
+        let ptr = gc
+            .get_var_field(&FullName::local(&self.ptr_name), 0)
+            .into_pointer_value();
+        let value = gc.get_var_field(&FullName::local(&self.value_name), 0);
+
+        // Store the value. The buffer (e.g., the data of an `Array U8`) may not be aligned.
+        let ptr = gc.cast_pointer(ptr, value.get_type().ptr_type(AddressSpace::from(0)));
+        let store = gc.builder().build_store(ptr, value);
+        store.set_alignment(1).unwrap();
+
+        // Return unit.
+        if rvo.is_some() {
+            rvo.unwrap()
+        } else {
+            allocate_obj(
+                ret_ty.clone(),
+                &vec![],
+                None,
+                gc,
+                Some("ret_val@unsafe_store_unaligned"),
+            )
+        }
+    }
+}
+
+// `_unsafe_store_unaligned : Ptr -> a -> ()` built-in function for a numeric type `a`.
+pub fn store_unaligned_function(ty: Rc<TypeNode>) -> (Rc<ExprNode>, Rc<Scheme>) {
+    const PTR_NAME: &str = "ptr";
+    const VALUE_NAME: &str = "val";
+
+    let scm = Scheme::generalize(
+        Default::default(),
+        vec![],
+        type_fun(make_ptr_ty(), type_fun(ty.clone(), make_unit_ty())),
+    );
+    let expr = expr_abs(
+        vec![var_local(PTR_NAME)],
+        expr_abs(
+            vec![var_local(VALUE_NAME)],
+            expr_llvm(
+                LLVMGenerator::StoreUnalignedBody(InlineLLVMStoreUnalignedBody {
+                    ptr_name: PTR_NAME.to_string(),
+                    value_name: VALUE_NAME.to_string(),
+                }),
+                vec![FullName::local(PTR_NAME), FullName::local(VALUE_NAME)],
+                format!("unsafe_store_unaligned({},{})", PTR_NAME, VALUE_NAME),
+                make_unit_ty(),
+                None,
+            ),
+            None,
+        ),
+        None,
+    );
+    (expr, scm)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InlineLLVMLoadUnalignedBody {
+    ptr_name: String,
+}
+
+impl InlineLLVMLoadUnalignedBody {
+    pub fn generate<'c, 'm, 'b>(
+        &self,
+        gc: &mut GenerationContext<'c, 'm>,
+        ret_ty: &Rc<TypeNode>,
+        rvo: Option<Object<'c>>,
+        _borrowed_vars: &Vec<FullName>,
+    ) -> Object<'c> {
+        // Get argument
+        let ptr = gc
+            .get_var_field(&FullName::local(&self.ptr_name), 0)
+            .into_pointer_value();
+
+        // Load the value. The buffer (e.g., the data of an `Array U8`) may not be aligned.
+        let obj = if rvo.is_some() {
+            rvo.unwrap()
+        } else {
+            allocate_obj(
+                ret_ty.clone(),
+                &vec![],
+                None,
+                gc,
+                Some("ret_val@unsafe_load_unaligned"),
+            )
+        };
+        let value_ty = ret_ty.get_struct_type(gc, &vec![]).get_field_types()[0];
+        let ptr = gc.cast_pointer(ptr, value_ty.ptr_type(AddressSpace::from(0)));
+        let value = gc.builder().build_load(ptr, "value@unsafe_load_unaligned");
+        value
+            .as_instruction_value()
+            .unwrap()
+            .set_alignment(1)
+            .unwrap();
+        obj.store_field_nocap(gc, 0, value);
+        obj
+    }
+}
+
+// `_unsafe_load_unaligned : Ptr -> a` built-in function for a numeric type `a`.
+pub fn load_unaligned_function(ty: Rc<TypeNode>) -> (Rc<ExprNode>, Rc<Scheme>) {
+    const PTR_NAME: &str = "ptr";
+
+    let scm = Scheme::generalize(
+        Default::default(),
+        vec![],
+        type_fun(make_ptr_ty(), ty.clone()),
+    );
+    let expr = expr_abs(
+        vec![var_local(PTR_NAME)],
+        expr_llvm(
+            LLVMGenerator::LoadUnalignedBody(InlineLLVMLoadUnalignedBody {
+                ptr_name: PTR_NAME.to_string(),
+            }),
+            vec![FullName::local(PTR_NAME)],
+            format!("unsafe_load_unaligned({})", PTR_NAME),
+            ty,
+            None,
+        ),
+        None,
+    );
+    (expr, scm)
+}
+
 pub fn unary_operator_trait(trait_id: TraitId, method_name: Name) -> TraitInfo {
     const TYVAR_NAME: &str = "a";
     let kind = kind_star();
@@ -0,0 +1,539 @@
+// Lowering of the types in `CALL_C` to LLVM types following the C calling convention of the target platform.
+//
+// Numeric types, `Ptr` and `Bool` are passed as scalars.
+// An unboxed struct whose fields are numeric types, `Ptr`, `Bool` or such structs is passed as a C struct by value.
+// Its memory layout in Fix coincides with the C struct with the same fields, so it is passed by reinterpreting the memory
+// as the type which the platform ABI assigns to the struct (e.g., `{ i64, double }` or `[2 x i64]`),
+// or by passing a pointer to it (`byval` / `sret`).
+// As clang does, a parameter coerced to an LLVM struct is passed as separate scalar parameters, one for each field,
+// and on x86-64 a struct is passed in memory if the remaining registers cannot hold all of its eightbytes.
+
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::types::{AnyType, BasicMetadataTypeEnum, BasicType};
+use inkwell::values::BasicMetadataValueEnum;
+
+use super::*;
+
+// How a parameter or the result of a C function is passed.
+#[derive(Clone)]
+pub enum CAbiType<'c> {
+    // `()` as the result type.
+    Void,
+    // A scalar, passed as the value of field 0 of the Fix object.
+    // The second element is the name of the extension attribute (`signext` / `zeroext`) for small integers.
+    Scalar(BasicTypeEnum<'c>, Option<&'static str>),
+    // A small struct, passed as a value of the given type which has the same memory image as the struct.
+    // As a parameter, an LLVM struct is passed as its fields.
+    Coerced(BasicTypeEnum<'c>),
+    // A large struct of LLVM type `struct_ty`.
+    // As a parameter, a pointer to a copy of the struct is passed (with `byval` attribute if `byval` is true).
+    // As the result, the caller passes a pointer to the memory where the result is written as the hidden first argument (`sret`).
+    Indirect {
+        byval: bool,
+        struct_ty: StructType<'c>,
+    },
+}
+
+// The signature of a C function called by `CALL_C`, lowered to LLVM types.
+pub struct CFunctionAbi<'c> {
+    pub ret: CAbiType<'c>,
+    pub params: Vec<CAbiType<'c>>,
+    is_var_args: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CScalarClass {
+    Integer,
+    F32,
+    F64,
+}
+
+// Memory layout of a C struct, flattened to scalars.
+struct CStructLayout {
+    // Class, offset and size of each scalar field.
+    fields: Vec<(CScalarClass, u64, u64)>,
+    size: u64,
+}
+
+#[derive(PartialEq)]
+enum TargetAbi {
+    SysVX86_64,
+    AArch64,
+}
+
+impl<'c> CFunctionAbi<'c> {
+    pub fn new<'m>(
+        ret_ty: &Rc<TyCon>,
+        param_tys: &Vec<Rc<TyCon>>,
+        is_var_args: bool,
+        gc: &mut GenerationContext<'c, 'm>,
+        source: &Option<Span>,
+    ) -> Self {
+        let ret = c_abi_type(ret_ty, true, gc, source);
+        let mut params = param_tys
+            .iter()
+            .map(|param_ty| c_abi_type(param_ty, false, gc, source))
+            .collect::<Vec<_>>();
+        if target_abi(gc, source) == TargetAbi::SysVX86_64 {
+            pass_in_memory_if_out_of_registers(&ret, &mut params, param_tys, gc);
+        }
+        CFunctionAbi {
+            ret,
+            params,
+            is_var_args,
+        }
+    }
+
+    // Get the LLVM type of the C function.
+    pub fn function_type<'m>(&self, gc: &mut GenerationContext<'c, 'm>) -> FunctionType<'c> {
+        let mut llvm_params: Vec<BasicMetadataTypeEnum> = vec![];
+        if let CAbiType::Indirect { struct_ty, .. } = self.ret {
+            llvm_params.push(ptr_type(struct_ty).into());
+        }
+        for param in &self.params {
+            match param {
+                CAbiType::Scalar(ty, _) => llvm_params.push((*ty).into()),
+                CAbiType::Coerced(ty) => {
+                    for field_ty in coerced_param_types(*ty) {
+                        llvm_params.push(field_ty.into());
+                    }
+                }
+                CAbiType::Indirect { struct_ty, .. } => {
+                    llvm_params.push(ptr_type(*struct_ty).into())
+                }
+                CAbiType::Void => unreachable!(),
+            }
+        }
+        match self.ret {
+            CAbiType::Scalar(ty, _) | CAbiType::Coerced(ty) => {
+                ty.fn_type(&llvm_params, self.is_var_args)
+            }
+            CAbiType::Void | CAbiType::Indirect { .. } => gc
+                .context
+                .void_type()
+                .fn_type(&llvm_params, self.is_var_args),
+        }
+    }
+
+    // Declare the C function in the module.
+    pub fn declare_function<'m>(
+        &self,
+        name: &str,
+        gc: &mut GenerationContext<'c, 'm>,
+    ) -> FunctionValue<'c> {
+        let fn_ty = self.function_type(gc);
+        let func = gc.module.add_function(name, fn_ty, None);
+        for (loc, attr) in self.attributes(gc) {
+            func.add_attribute(loc, attr);
+        }
+        func
+    }
+
+    // Attributes of the result and parameters: `signext` / `zeroext` for small integers, and `sret` / `byval` for large structs.
+    fn attributes<'m>(&self, gc: &mut GenerationContext<'c, 'm>) -> Vec<(AttributeLoc, Attribute)> {
+        let mut attrs = vec![];
+        let mut offset = 0;
+        match self.ret {
+            CAbiType::Scalar(_, Some(ext)) => {
+                attrs.push((AttributeLoc::Return, enum_attribute(ext, gc)));
+            }
+            CAbiType::Indirect { struct_ty, .. } => {
+                attrs.push((
+                    AttributeLoc::Param(0),
+                    type_attribute("sret", struct_ty, gc),
+                ));
+                offset = 1;
+            }
+            _ => {}
+        }
+        for param in &self.params {
+            let loc = AttributeLoc::Param(offset);
+            offset += match param {
+                CAbiType::Coerced(ty) => coerced_param_types(*ty).len() as u32,
+                _ => 1,
+            };
+            match param {
+                CAbiType::Scalar(_, Some(ext)) => {
+                    attrs.push((loc, enum_attribute(ext, gc)));
+                }
+                CAbiType::Indirect {
+                    byval: true,
+                    struct_ty,
+                } => {
+                    attrs.push((loc, type_attribute("byval", *struct_ty, gc)));
+                }
+                _ => {}
+            }
+        }
+        attrs
+    }
+
+    // Call the C function with arguments. The result is written to `ret_obj`.
+    // Arguments after the declared parameters (variadic arguments) are passed as scalars.
+    pub fn build_call<'m>(
+        &self,
+        func: CallableValue<'c>,
+        fun_name: &str,
+        args: &Vec<Object<'c>>,
+        ret_obj: &Object<'c>,
+        gc: &mut GenerationContext<'c, 'm>,
+    ) {
+        let mut arg_vals: Vec<BasicMetadataValueEnum> = vec![];
+        if let CAbiType::Indirect { struct_ty, .. } = self.ret {
+            let ret_ptr = ret_obj.ptr(gc);
+            arg_vals.push(gc.cast_pointer(ret_ptr, ptr_type(struct_ty)).into());
+        }
+        for (i, arg) in args.iter().enumerate() {
+            let param = if i < self.params.len() {
+                self.params[i].clone()
+            } else {
+                CAbiType::Scalar(gc.context.i8_type().into(), None)
+            };
+            let val: BasicMetadataValueEnum = match param {
+                CAbiType::Scalar(_, _) => arg.load_field_nocap(gc, 0).into(),
+                CAbiType::Coerced(ty) => {
+                    // Copy the struct to a memory of the coerced type and load it (field by field, if it is an LLVM struct).
+                    let tmp = gc.build_alloca_at_entry(ty, "call_c_coerced_arg");
+                    let struct_val = arg.load_nocap(gc);
+                    let struct_ptr = gc.cast_pointer(tmp, ptr_type(arg.struct_ty(gc)));
+                    gc.builder().build_store(struct_ptr, struct_val);
+                    if ty.is_struct_type() {
+                        for i in 0..ty.into_struct_type().count_fields() {
+                            let field_ptr = gc
+                                .builder()
+                                .build_struct_gep(tmp, i, "call_c_coerced_arg_field")
+                                .unwrap();
+                            let field = gc
+                                .builder()
+                                .build_load(field_ptr, "call_c_coerced_arg_field");
+                            arg_vals.push(field.into());
+                        }
+                        continue;
+                    }
+                    gc.builder().build_load(tmp, "call_c_coerced_arg").into()
+                }
+                CAbiType::Indirect { byval, struct_ty } => {
+                    let ptr = if byval {
+                        // The callee receives a copy made by the calling sequence.
+                        arg.ptr(gc)
+                    } else {
+                        // Pass a pointer to a copy, since the callee may modify it.
+                        let tmp = gc.build_alloca_at_entry(struct_ty, "call_c_indirect_arg");
+                        let struct_val = arg.load_nocap(gc);
+                        gc.builder().build_store(tmp, struct_val);
+                        tmp
+                    };
+                    gc.cast_pointer(ptr, ptr_type(struct_ty)).into()
+                }
+                CAbiType::Void => unreachable!(),
+            };
+            arg_vals.push(val);
+        }
+
+        let call = gc
+            .builder()
+            .build_call(func, &arg_vals, &format!("CALL_C({})", fun_name));
+        // The attributes are also attached to the call site, since the callee may be a function pointer.
+        for (loc, attr) in self.attributes(gc) {
+            call.add_attribute(loc, attr);
+        }
+
+        let ret_val = call.try_as_basic_value().left();
+        match &self.ret {
+            CAbiType::Scalar(_, _) => ret_obj.store_field_nocap(gc, 0, ret_val.unwrap()),
+            CAbiType::Coerced(ty) => {
+                // Store the returned value to a memory of the coerced type and reload it as the struct.
+                let tmp = gc.build_alloca_at_entry(*ty, "call_c_coerced_ret");
+                gc.builder().build_store(tmp, ret_val.unwrap());
+                let struct_ty = ret_obj.struct_ty(gc);
+                let struct_ptr = gc.cast_pointer(tmp, ptr_type(struct_ty));
+                let struct_val = gc.builder().build_load(struct_ptr, "call_c_coerced_ret");
+                ret_obj.store_unbox(gc, struct_val);
+            }
+            CAbiType::Void | CAbiType::Indirect { .. } => {}
+        }
+    }
+}
+
+// The LLVM types of the parameters by which a coerced struct is passed: the fields if it is an LLVM struct, otherwise the type itself.
+fn coerced_param_types<'c>(ty: BasicTypeEnum<'c>) -> Vec<BasicTypeEnum<'c>> {
+    match ty {
+        BasicTypeEnum::StructType(struct_ty) => struct_ty.get_field_types(),
+        _ => vec![ty],
+    }
+}
+
+// On x86-64, 6 general purpose registers and 8 SSE registers are used for arguments.
+// A struct is passed in registers only if all of its eightbytes fit in the remaining registers; otherwise it is passed in memory,
+// and the registers are left for the following arguments.
+fn pass_in_memory_if_out_of_registers<'c, 'm>(
+    ret: &CAbiType<'c>,
+    params: &mut Vec<CAbiType<'c>>,
+    param_tys: &Vec<Rc<TyCon>>,
+    gc: &mut GenerationContext<'c, 'm>,
+) {
+    let mut int_regs: usize = 6;
+    let mut sse_regs: usize = 8;
+    if let CAbiType::Indirect { .. } = ret {
+        // The pointer to the result is passed as the hidden first argument.
+        int_regs -= 1;
+    }
+    for (param, param_ty) in params.iter_mut().zip(param_tys) {
+        let tys = match param {
+            CAbiType::Scalar(ty, _) => vec![*ty],
+            CAbiType::Coerced(ty) => coerced_param_types(*ty),
+            CAbiType::Indirect { .. } | CAbiType::Void => continue,
+        };
+        let sse_count = tys
+            .iter()
+            .filter(|ty| ty.is_float_type() || ty.is_vector_type())
+            .count();
+        let int_count = tys.len() - sse_count;
+        if int_count <= int_regs && sse_count <= sse_regs {
+            int_regs -= int_count;
+            sse_regs -= sse_count;
+        } else if let CAbiType::Coerced(_) = param {
+            *param = CAbiType::Indirect {
+                byval: true,
+                struct_ty: type_tycon(param_ty).get_struct_type(gc, &vec![]),
+            };
+        }
+    }
+}
+
+fn enum_attribute<'c, 'm>(name: &str, gc: &mut GenerationContext<'c, 'm>) -> Attribute {
+    gc.context
+        .create_enum_attribute(Attribute::get_named_enum_kind_id(name), 0)
+}
+
+fn type_attribute<'c, 'm>(
+    name: &str,
+    ty: StructType<'c>,
+    gc: &mut GenerationContext<'c, 'm>,
+) -> Attribute {
+    gc.context.create_type_attribute(
+        Attribute::get_named_enum_kind_id(name),
+        ty.as_any_type_enum(),
+    )
+}
+
+// Get how a value of the type is passed to or returned from a C function.
+fn c_abi_type<'c, 'm>(
+    tc: &Rc<TyCon>,
+    is_ret: bool,
+    gc: &mut GenerationContext<'c, 'm>,
+    source: &Option<Span>,
+) -> CAbiType<'c> {
+    if tc.is_unit() {
+        if !is_ret {
+            error_exit_with_src("Cannot use `()` as a parameter type of C function.", source)
+        }
+        return CAbiType::Void;
+    }
+    if let Some(ty) = c_scalar_type(tc, gc.context) {
+        let ext = if tc.name.name == I8_NAME || tc.name.name == I16_NAME {
+            Some("signext")
+        } else if tc.name.name == U8_NAME || tc.name.name == U16_NAME || tc.name.name == BOOL_NAME {
+            Some("zeroext")
+        } else {
+            None
+        };
+        return CAbiType::Scalar(ty, ext);
+    }
+
+    // `tc` should be an unboxed struct.
+    let ty = type_tycon(tc);
+    let layout = match gc.type_env().tycons.get(tc) {
+        Some(ti) if ti.variant == TyConVariant::Struct && ti.tyvars.is_empty() => {
+            c_struct_layout(&ty, gc.type_env())
+        }
+        _ => None,
+    };
+    let layout = match layout {
+        Some(layout) => layout,
+        None => error_exit_with_src(
+            &format!(
+                "Cannot use `{}` in `CALL_C`. Only `()`, numeric types, `Ptr`, `Bool` and unboxed structs whose fields are such types (except `()`) are allowed.",
+                tc.to_string()
+            ),
+            source,
+        ),
+    };
+    let struct_ty = ty.get_struct_type(gc, &vec![]);
+    let ctx = gc.context;
+    match target_abi(gc, source) {
+        TargetAbi::SysVX86_64 => {
+            if layout.size > 16 {
+                CAbiType::Indirect {
+                    byval: true,
+                    struct_ty,
+                }
+            } else {
+                // Classify each eightbyte.
+                let mut eightbytes = vec![];
+                let mut start = 0;
+                while start < layout.size {
+                    let end = (start + 8).min(layout.size);
+                    let classes = layout
+                        .fields
+                        .iter()
+                        .filter(|(_, offset, _)| start <= *offset && *offset < end)
+                        .map(|(class, _, _)| *class)
+                        .collect::<Vec<_>>();
+                    let eightbyte: BasicTypeEnum =
+                        if classes.iter().all(|c| *c == CScalarClass::F64) {
+                            ctx.f64_type().into()
+                        } else if classes.iter().all(|c| *c == CScalarClass::F32) {
+                            if end - start <= 4 {
+                                ctx.f32_type().into()
+                            } else {
+                                ctx.f32_type().vec_type(2).into()
+                            }
+                        } else {
+                            ctx.custom_width_int_type(((end - start) * 8) as u32).into()
+                        };
+                    eightbytes.push(eightbyte);
+                    start = end;
+                }
+                if eightbytes.len() == 1 {
+                    CAbiType::Coerced(eightbytes[0])
+                } else {
+                    CAbiType::Coerced(ctx.struct_type(&eightbytes, false).into())
+                }
+            }
+        }
+        TargetAbi::AArch64 => {
+            // Homogeneous floating-point aggregate.
+            let first_class = layout.fields[0].0;
+            let is_hfa = first_class != CScalarClass::Integer
+                && layout.fields.len() <= 4
+                && layout.fields.iter().all(|(c, _, _)| *c == first_class);
+            if is_hfa {
+                let elem_ty: BasicTypeEnum = if first_class == CScalarClass::F32 {
+                    ctx.f32_type().into()
+                } else {
+                    ctx.f64_type().into()
+                };
+                CAbiType::Coerced(elem_ty.array_type(layout.fields.len() as u32).into())
+            } else if layout.size <= 8 {
+                CAbiType::Coerced(ctx.i64_type().into())
+            } else if layout.size <= 16 {
+                CAbiType::Coerced(ctx.i64_type().array_type(2).into())
+            } else {
+                CAbiType::Indirect {
+                    byval: false,
+                    struct_ty,
+                }
+            }
+        }
+    }
+}
+
+// Convert "I8", "Ptr", "Bool", etc to corresponding LLVM type.
+// Returns none if the type is not a scalar C type.
+fn c_scalar_type<'c>(tc: &TyCon, ctx: &'c Context) -> Option<BasicTypeEnum<'c>> {
+    if tc.name.namespace != NameSpace::new_str(&[STD_NAME]) {
+        return None;
+    }
+    if tc.name.name == BOOL_NAME {
+        return Some(ctx.i8_type().as_basic_type_enum());
+    }
+    let scalars = [
+        I8_NAME, U8_NAME, I16_NAME, U16_NAME, I32_NAME, U32_NAME, I64_NAME, U64_NAME, F32_NAME,
+        F64_NAME, PTR_NAME,
+    ];
+    if scalars.contains(&tc.name.name.as_str()) {
+        tc.get_c_type(ctx)
+    } else {
+        None
+    }
+}
+
+// Get the layout of the C struct corresponding to an unboxed struct type.
+// Returns none if the type cannot be passed to C.
+fn c_struct_layout(ty: &Rc<TypeNode>, type_env: &TypeEnv) -> Option<CStructLayout> {
+    let mut layout = CStructLayout {
+        fields: vec![],
+        size: 0,
+    };
+    let align = add_struct_fields(ty, type_env, &mut layout.fields)?;
+    let end = layout
+        .fields
+        .iter()
+        .map(|(_, offset, size)| offset + size)
+        .max()?;
+    layout.size = round_up(end, align);
+    Some(layout)
+}
+
+// Add scalar fields of a struct, and return the alignment of the struct.
+fn add_struct_fields(
+    ty: &Rc<TypeNode>,
+    type_env: &TypeEnv,
+    fields: &mut Vec<(CScalarClass, u64, u64)>,
+) -> Option<u64> {
+    let ti = type_env.tycons.get(&ty.toplevel_tycon()?)?;
+    if ti.variant != TyConVariant::Struct || !ti.is_unbox {
+        return None;
+    }
+    let mut offset = 0;
+    let mut struct_align = 1;
+    for field_ty in ty.field_types(type_env) {
+        let tc = field_ty.toplevel_tycon()?;
+        let scalar = if tc.name.namespace == NameSpace::new_str(&[STD_NAME]) {
+            match tc.name.name.as_str() {
+                I8_NAME | U8_NAME | BOOL_NAME => Some((CScalarClass::Integer, 1)),
+                I16_NAME | U16_NAME => Some((CScalarClass::Integer, 2)),
+                I32_NAME | U32_NAME => Some((CScalarClass::Integer, 4)),
+                I64_NAME | U64_NAME | PTR_NAME => Some((CScalarClass::Integer, 8)),
+                F32_NAME => Some((CScalarClass::F32, 4)),
+                F64_NAME => Some((CScalarClass::F64, 8)),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        match scalar {
+            Some((class, size)) => {
+                offset = round_up(offset, size);
+                fields.push((class, offset, size));
+                offset += size;
+                struct_align = struct_align.max(size);
+            }
+            None => {
+                // Nested struct. Its alignment is determined after its fields are added.
+                let mut inner = vec![];
+                let align = add_struct_fields(&field_ty, type_env, &mut inner)?;
+                let inner_end = inner.iter().map(|(_, o, s)| o + s).max()?;
+                offset = round_up(offset, align);
+                for (class, o, s) in inner {
+                    fields.push((class, offset + o, s));
+                }
+                offset += round_up(inner_end, align);
+                struct_align = struct_align.max(align);
+            }
+        }
+    }
+    Some(struct_align)
+}
+
+fn round_up(n: u64, align: u64) -> u64 {
+    (n + align - 1) / align * align
+}
+
+fn target_abi<'c, 'm>(gc: &GenerationContext<'c, 'm>, source: &Option<Span>) -> TargetAbi {
+    let triple = gc.target_triple();
+    if triple.starts_with("x86_64") && !triple.contains("windows") {
+        TargetAbi::SysVX86_64
+    } else if triple.starts_with("aarch64") || triple.starts_with("arm64") {
+        TargetAbi::AArch64
+    } else {
+        error_exit_with_src(
+            &format!(
+                "Passing structs by value in `CALL_C` is not supported on the target `{}`.",
+                triple
+            ),
+            source,
+        )
+    }
+}
@@ -0,0 +1,551 @@
+// The command line interface `fix`.
+
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+use super::*;
+
+pub fn cli_main() {
+    let source_file = Arg::new("source-files")
+        .long("file")
+        .short('f')
+        .help("Source files to be compiled and linked. Exactly one file of them must define `Main` module and `main : IO ()`.")
+        .multiple_values(true)
+        .takes_value(true)
+        .required(true);
+    let static_link_library = Arg::new("static-link-library")
+        .long("static-link")
+        .short('s')
+        .action(clap::ArgAction::Append)
+        .help("Add statically linked library. For example, give \"abc\" to link \"libabc.so\".");
+    let dynamic_link_library = Arg::new("dynamic-link-library")
+        .long("dynamic-link")
+        .short('d')
+        .action(clap::ArgAction::Append)
+        .help("Add dynamically linked library. For example, give \"abc\" to link \"libabc.so\".");
+    let debug_info = Arg::new("debug-info")
+        .long("debug")
+        .short('g')
+        .takes_value(false)
+        .help("[Experimental] Generate debugging information. \n\
+              This option automatically turns on `-O none`. You can override this by explicitly specifying another optimization level.");
+    let opt_level = Arg::new("opt-level")
+        .long("opt-level")
+        .short('O')
+        .takes_value(true)
+        .value_parser(["none", "minimum", "default"])
+        // .default_value("default") // we do not set default value because we want to check if this option is specified by user.
+        .next_line_help(true)
+        .help("Set optimization level.\n\
+              - none: Perform no optimizations. Since tail recursion optimization is also omitted, programs that perform recursion may not work properly.\n\
+              - minimum: Perform only few optimizations to minimize compile time.\n\
+              - default: Compile to minimize execution time. This is the default option.").hide_possible_values(true);
+    let emit_llvm = Arg::new("emit-llvm")
+        .long("emit-llvm")
+        .takes_value(false)
+        .help("Emit LLVM-IR file.");
+    let emit = Arg::new("emit")
+        .long("emit")
+        .takes_value(true)
+        .value_delimiter(',')
+        .action(clap::ArgAction::Append)
+        .value_parser(["ast", "typed", "instances", "obj", "asm", "bc"])
+        .help("Print intermediate representations of the program to the standard output, or emit output files of other kinds. Specify comma-separated kinds.\n\
+              - ast: the parsed program.\n\
+              - typed: global values used by the program with the inferred type of each subexpression.\n\
+              - instances: the instantiated symbols with their concrete types.\n\
+              - obj, asm, bc: (`fix build` only) stop after generating an object file, an assembly file or an LLVM bitcode file instead of linking an executable.")
+        .hide_possible_values(true);
+    let time_passes = Arg::new("time-passes")
+        .long("time-passes")
+        .takes_value(true)
+        .min_values(0)
+        .require_equals(true)
+        .default_missing_value("text")
+        .value_parser(["text", "json"])
+        .help("Report the time spent in each phase of compilation and the number of instantiated symbols.\n\
+              - text: (default) print a table to stderr.\n\
+              - json: write a JSON file `time_passes.json` (or `time_passes_{output}.json` if `-o {output}` is given).")
+        .hide_possible_values(true);
+    let threaded = Arg::new("threaded")
+        .long("threaded")
+        .takes_value(false)
+        .help("Enable multi-threading. Turning this option ON increases overhead, it is recommended keeping this option OFF for single-threaded programs.");
+    let cc = Arg::new("cc")
+        .long("cc")
+        .takes_value(true)
+        .help("C compiler used to build the runtime (e.g., \"clang\"). Overrides `CC` environment variable. The default is \"gcc\".");
+    let linker = Arg::new("linker")
+        .long("linker")
+        .takes_value(true)
+        .help("C compiler driver used for linking (e.g., \"clang\"). Overrides `FIX_LINKER` environment variable. The default is the C compiler. To use another linker such as lld, add `--ldflag=-fuse-ld=lld`.");
+    let cflag = Arg::new("cflag")
+        .long("cflag")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+        .action(clap::ArgAction::Append)
+        .help("Add a flag passed to the C compiler, in addition to `CFLAGS` environment variable. For example, `--cflag=-O2`.");
+    let ldflag = Arg::new("ldflag")
+        .long("ldflag")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+        .action(clap::ArgAction::Append)
+        .help("Add a flag passed to the linker, in addition to `LDFLAGS` environment variable. For example, `--ldflag=-fuse-ld=lld`.");
+    let library_path = Arg::new("library-path")
+        .long("library-path")
+        .short('L')
+        .takes_value(true)
+        .action(clap::ArgAction::Append)
+        .help("Add a directory searched for linked libraries.");
+    let rpath = Arg::new("rpath")
+        .long("rpath")
+        .takes_value(true)
+        .action(clap::ArgAction::Append)
+        .help("Add a directory searched for dynamically linked libraries when the output is run.");
+    let output_file = Arg::new("output-file")
+        .long("output")
+        .short('o')
+        .takes_value(true)
+        .help("Path to output file.");
+    let toolchain_args = [cc, linker, cflag, ldflag, library_path, rpath];
+    let run_subc = App::new("run")
+        .about("Executes a Fix program.")
+        .arg(source_file.clone())
+        .arg(output_file.clone())
+        .arg(dynamic_link_library.clone())
+        .arg(debug_info.clone())
+        .arg(opt_level.clone())
+        .arg(emit_llvm.clone())
+        .arg(emit.clone())
+        .arg(time_passes.clone())
+        .arg(threaded.clone())
+        .args(toolchain_args.clone())
+        .arg(
+            Arg::new("program-args")
+                .last(true)
+                .takes_value(true)
+                .multiple_values(true)
+                .allow_hyphen_values(true)
+                .help("Arguments passed to the program, given after `--` (e.g., `fix run -f main.fix -- arg1 arg2`)."),
+        );
+    let build_subc = App::new("build")
+        .about("Builds an executable binary from source files.")
+        .arg(source_file.clone())
+        .arg(output_file.clone())
+        .arg(static_link_library.clone())
+        .arg(dynamic_link_library.clone())
+        .arg(debug_info.clone())
+        .arg(opt_level)
+        .arg(emit_llvm.clone())
+        .arg(emit.clone())
+        .arg(time_passes)
+        .arg(
+            Arg::new("lib")
+                .long("lib")
+                .takes_value(true)
+                .min_values(0)
+                .default_missing_value("shared")
+                .value_parser(["shared", "static"])
+                .help("Build a shared library (`--lib` or `--lib=shared`) or a static library (`--lib=static`) exporting values specified by `FFI_EXPORT[value_name, c_function_name];`, instead of an executable. A C header file is also generated."),
+        )
+        .arg(
+            Arg::new("incremental")
+                .long("incremental")
+                .takes_value(false)
+                .help("Generate code per module and cache the compiled object files in `.fixlang/objects`, so that only modules whose code changed are compiled again. Since functions are not inlined across modules, the output may be slower than the one built without this option."),
+        )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .short('j')
+                .takes_value(true)
+                .help("Split the generated code into the given number of LLVM modules, and optimize and compile them in parallel. Since functions are not inlined across modules, the output may be slower than the one built without this option. The default is 1."),
+        )
+        .arg(
+            Arg::new("target")
+                .long("target")
+                .takes_value(true)
+                .help("Generate code for the given target triple (e.g., `aarch64-unknown-linux-gnu` or `riscv64-unknown-linux-gnu`) instead of the host. To build the runtime and link the output, give a C compiler for the target by `--cc` (a cross compiler, or clang to which the triple is passed)."),
+        )
+        .arg(
+            Arg::new("target-cpu")
+                .long("target-cpu")
+                .takes_value(true)
+                .help("Generate code for the given CPU of the target (e.g., `cortex-a72`), or `native` for the host CPU. The default is the host CPU when compiling for the host, and a generic CPU otherwise."),
+        )
+        .arg(
+            Arg::new("profile-generate")
+                .long("profile-generate")
+                .takes_value(true)
+                .min_values(0)
+                .default_missing_value(".")
+                .value_name("dir")
+                .help("Instrument the output to collect a profile for profile-guided optimization. Running the output writes `default_*.profraw` to the current directory (or the given directory), which can be merged by `llvm-profdata merge -o {file}.profdata default_*.profraw`. Requires clang."),
+        )
+        .arg(
+            Arg::new("profile-use")
+                .long("profile-use")
+                .takes_value(true)
+                .value_name("file")
+                .conflicts_with("profile-generate")
+                .help("Optimize the output with a profile (`.profdata`) collected by an output of `--profile-generate` built from the same sources. Requires clang and `llvm-profdata`."),
+        )
+        .arg(threaded.clone())
+        .args(toolchain_args.clone());
+    let check_subc = App::new("check")
+        .about("Checks types of all global values in source files without generating executable binary.")
+        .arg(source_file.clone());
+    let test_subc = App::new("test")
+        .about("Runs tests in source files. A test is a global value of type `IO ()` or `IOFail ()` whose name is `test` or starts with `test_`.")
+        .arg(
+            Arg::new("filter")
+                .index(1)
+                .takes_value(true)
+                .help("Run only tests whose full names contain this string."),
+        )
+        .arg(source_file.clone())
+        .arg(dynamic_link_library.clone())
+        .arg(threaded.clone())
+        .args(toolchain_args.clone());
+    let doc_subc = App::new("doc")
+        .about("Generates documentation in Markdown and HTML from comments attached to types, traits and global values.")
+        .arg(source_file.clone().required_unless_present("std"))
+        .arg(
+            Arg::new("std")
+                .long("std")
+                .takes_value(false)
+                .help("Generate documentation of built-in modules."),
+        )
+        .arg(
+            Arg::new("output-file")
+                .long("output")
+                .short('o')
+                .takes_value(true)
+                .default_value("doc.md")
+                .help("Path to the Markdown file. The HTML file is written to the same path with extension \".html\"."),
+        );
+    let bindgen_subc = App::new("bindgen")
+        .about("Generates a Fix module which wraps functions and constants declared in a C header file by `CALL_C`.")
+        .arg(
+            Arg::new("header")
+                .index(1)
+                .takes_value(true)
+                .required(true)
+                .help("Path to the C header file."),
+        )
+        .arg(
+            Arg::new("module")
+                .long("module")
+                .short('m')
+                .takes_value(true)
+                .help("Name of the generated module. By default, it is made from the file name of the header (e.g., \"my_lib.h\" to \"MyLib\")."),
+        )
+        .arg(
+            Arg::new("output-file")
+                .long("output")
+                .short('o')
+                .takes_value(true)
+                .help("Path to the generated Fix source file. If omitted, the source is written to the standard output."),
+        );
+    let fmt_subc = App::new("fmt")
+        .about("Formats source files.")
+        .arg(source_file.clone())
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .takes_value(false)
+                .help("Check if source files are formatted without modifying them. Exits with a non-zero status if some file is not formatted."),
+        );
+    let lsp_subc = App::new("lsp")
+        .about("Starts the language server, which communicates with an editor by Language Server Protocol over stdio.");
+    let repl_subc = App::new("repl")
+        .about("Starts an interactive session which evaluates expressions and definitions.")
+        .arg(dynamic_link_library.clone())
+        .arg(threaded.clone())
+        .args(toolchain_args.clone());
+    let script_subc = App::new("script")
+        .about("Runs a source file as a script. The executable is cached and reused while the script and the modules it imports are unchanged. A script can start with `#!/usr/bin/env fix`, and `fix {file} {args}` is equivalent to `fix script {file} {args}`.")
+        .setting(AppSettings::TrailingVarArg)
+        .arg(
+            Arg::new("script-file")
+                .index(1)
+                .takes_value(true)
+                .required(true)
+                .help("Path to the source file."),
+        )
+        .arg(
+            Arg::new("program-args")
+                .index(2)
+                .takes_value(true)
+                .multiple_values(true)
+                .allow_hyphen_values(true)
+                .help("Arguments passed to the script."),
+        )
+        .arg(dynamic_link_library.clone())
+        .arg(threaded.clone())
+        .args(toolchain_args.clone());
+    let clean_subc = App::new("clean")
+        .about("Removes intermediate files or cache files.")
+        .arg(
+            Arg::new("cache-only")
+                .long("cache-only")
+                .takes_value(false)
+                .help("Remove only the caches of type-checking results and of object files of `--incremental` builds, keeping other intermediate files."),
+        );
+    let app = App::new("Fix-lang")
+        .bin_name("fix")
+        .setting(AppSettings::ArgRequiredElseHelp)
+        .subcommand(run_subc)
+        .subcommand(build_subc)
+        .subcommand(check_subc)
+        .subcommand(test_subc)
+        .subcommand(doc_subc)
+        .subcommand(bindgen_subc)
+        .subcommand(fmt_subc)
+        .subcommand(lsp_subc)
+        .subcommand(repl_subc)
+        .subcommand(script_subc)
+        .subcommand(clean_subc);
+
+    fn read_source_files_options(m: &ArgMatches) -> Vec<PathBuf> {
+        m.get_many::<String>("source-files")
+            .unwrap()
+            .map(|s| PathBuf::from(s))
+            .collect()
+    }
+
+    fn read_output_file_option(m: &ArgMatches) -> Option<PathBuf> {
+        m.get_one::<String>("output-file").map(|s| PathBuf::from(s))
+    }
+
+    fn read_library_options(m: &ArgMatches) -> Vec<(String, LinkType)> {
+        let mut options = vec![];
+        for (opt_id, link_type) in [
+            ("static-link-library", LinkType::Static),
+            ("dynamic-link-library", LinkType::Dynamic),
+        ] {
+            options.append(
+                &mut m
+                    .try_get_many::<String>(opt_id)
+                    .unwrap_or_default()
+                    .unwrap_or_default()
+                    .map(|v| (v.clone(), link_type))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        options
+    }
+
+    fn read_toolchain_options(m: &ArgMatches, toolchain: &mut Toolchain) {
+        let get_many = |id: &str| -> Vec<String> {
+            m.try_get_many::<String>(id)
+                .unwrap_or_default()
+                .unwrap_or_default()
+                .cloned()
+                .collect()
+        };
+        if let Some(cc) = m.get_one::<String>("cc") {
+            toolchain.cc = cc.clone();
+        }
+        if let Some(linker) = m.get_one::<String>("linker") {
+            toolchain.linker = Some(linker.clone());
+        }
+        toolchain.cflags.append(&mut get_many("cflag"));
+        toolchain.ldflags.append(&mut get_many("ldflag"));
+        for path in get_many("library-path") {
+            toolchain.library_paths.push(PathBuf::from(path));
+        }
+        for path in get_many("rpath") {
+            toolchain.rpaths.push(PathBuf::from(path));
+        }
+    }
+
+    fn create_config_from_matches(m: &ArgMatches) -> Configuration {
+        let mut config = Configuration::release();
+        config.source_files = read_source_files_options(m);
+        config.out_file_path = read_output_file_option(m);
+        config.linked_libraries.append(&mut read_library_options(m));
+        read_toolchain_options(m, &mut config.toolchain);
+        if m.contains_id("debug-info") {
+            config.set_debug_info();
+        }
+        config.emit_llvm = m.contains_id("emit-llvm");
+        for kind in m
+            .try_get_many::<String>("emit")
+            .unwrap_or_default()
+            .unwrap_or_default()
+        {
+            match kind.as_str() {
+                "ast" => config.emit_ast = true,
+                "typed" => config.emit_typed = true,
+                "instances" => config.emit_instances = true,
+                "obj" => config.set_output_file_type(OutputFileType::Object),
+                "asm" => config.set_output_file_type(OutputFileType::Assembly),
+                "bc" => config.set_output_file_type(OutputFileType::Bitcode),
+                _ => error_exit(&format!("Unknown kind of emission: `{}`.", kind)),
+            }
+        }
+        match m.get_one::<String>("time-passes").map(|s| s.as_str()) {
+            Some("text") => config.time_passes = Some(TimePassesFormat::Text),
+            Some("json") => config.time_passes = Some(TimePassesFormat::Json),
+            _ => {}
+        }
+        if config.time_passes.is_some() {
+            enable_time_passes();
+        }
+        if m.contains_id("threaded") {
+            config.set_threaded();
+        }
+        if m.contains_id("opt-level") {
+            // These lines should be after calling `set_debug_info`; otherwise, user cannot specify the optimization level while generating debug information.
+            let opt_level = m.get_one::<String>("opt-level").unwrap();
+            match opt_level.as_str() {
+                "none" => config.set_fix_opt_level(FixOptimizationLevel::None),
+                "minimum" => config.set_fix_opt_level(FixOptimizationLevel::Minimum),
+                "default" => config.set_fix_opt_level(FixOptimizationLevel::Default),
+                _ => panic!("Unknown optimization level: {}", opt_level),
+            }
+        }
+        config
+    }
+
+    // `fix {file} {args}` (e.g., by the shebang `#!/usr/bin/env fix`) runs the file as a script.
+    let mut args = std::env::args().collect::<Vec<_>>();
+    if args.len() >= 2
+        && !args[1].starts_with('-')
+        && app.find_subcommand(&args[1]).is_none()
+        && Path::new(&args[1]).is_file()
+    {
+        args.insert(1, "script".to_string());
+    }
+
+    match app.get_matches_from(args).subcommand() {
+        Some(("run", m)) => {
+            let mut config = create_config_from_matches(m);
+            if config.output_file_type != OutputFileType::Executable {
+                error_exit(
+                    "`--emit=obj`, `--emit=asm` and `--emit=bc` are available only in `fix build`.",
+                );
+            }
+            config.program_args = m
+                .try_get_many::<String>("program-args")
+                .unwrap_or_default()
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            // Exit with the exit status of the program.
+            std::process::exit(run_file(config));
+        }
+        Some(("build", m)) => {
+            let mut config = create_config_from_matches(m);
+            match m.get_one::<String>("lib").map(|s| s.as_str()) {
+                Some("shared") => config.set_output_file_type(OutputFileType::SharedLibrary),
+                Some("static") => config.set_output_file_type(OutputFileType::StaticLibrary),
+                _ => {}
+            }
+            if m.contains_id("incremental") {
+                if config.emit_llvm || !config.output_file_type.is_linked() {
+                    error_exit("`--incremental` cannot be used with `--emit-llvm`, `--emit=obj`, `--emit=asm` or `--emit=bc`.");
+                }
+                config.incremental = true;
+            }
+            if let Some(jobs) = m.get_one::<String>("jobs") {
+                config.codegen_jobs = match jobs.parse::<usize>() {
+                    Ok(jobs) if jobs > 0 => jobs,
+                    _ => error_exit(&format!(
+                        "Invalid value for `--jobs`: `{}`. Specify a positive integer.",
+                        jobs
+                    )),
+                };
+            }
+            if let Some(triple) = m.get_one::<String>("target") {
+                config.set_target_triple(triple);
+            }
+            if let Some(cpu) = m.get_one::<String>("target-cpu") {
+                config.target_cpu = Some(cpu.clone());
+            }
+            if let Some(dir) = m.get_one::<String>("profile-generate") {
+                config.profile_generate = Some(PathBuf::from(dir));
+            }
+            if let Some(path) = m.get_one::<String>("profile-use") {
+                config.profile_use = Some(PathBuf::from(path));
+            }
+            if config.profile_generate.is_some() || config.profile_use.is_some() {
+                if config.incremental || config.codegen_jobs > 1 {
+                    error_exit("`--profile-generate` and `--profile-use` cannot be used with `--incremental` or `--jobs`.");
+                }
+                if !config.output_file_type.is_linked() {
+                    error_exit("`--profile-generate` and `--profile-use` cannot be used with `--emit=obj`, `--emit=asm` or `--emit=bc`.");
+                }
+            }
+            build_file(config);
+        }
+        Some(("check", m)) => {
+            let mut config = Configuration::release();
+            config.source_files = read_source_files_options(m);
+            check_file(config);
+        }
+        Some(("test", m)) => {
+            let mut config = Configuration::release();
+            config.source_files = read_source_files_options(m);
+            config.linked_libraries.append(&mut read_library_options(m));
+            read_toolchain_options(m, &mut config.toolchain);
+            if m.contains_id("threaded") {
+                config.set_threaded();
+            }
+            std::process::exit(test_command(config, m.get_one::<String>("filter").cloned()));
+        }
+        Some(("doc", m)) => {
+            let sources = if m.contains_id("std") {
+                standard_library_sources()
+            } else {
+                read_source_files_options(m)
+                    .into_iter()
+                    .map(|path| SourceFile::from_file_path(path))
+                    .collect()
+            };
+            doc_command(sources, &read_output_file_option(m).unwrap());
+        }
+        Some(("bindgen", m)) => {
+            bindgen_command(
+                Path::new(m.get_one::<String>("header").unwrap()),
+                m.get_one::<String>("module").cloned(),
+                read_output_file_option(m),
+            );
+        }
+        Some(("fmt", m)) => {
+            format_files(&read_source_files_options(m), m.contains_id("check"));
+        }
+        Some(("lsp", _m)) => {
+            lsp_command();
+        }
+        Some(("repl", m)) => {
+            let mut config = Configuration::release();
+            config.linked_libraries.append(&mut read_library_options(m));
+            read_toolchain_options(m, &mut config.toolchain);
+            if m.contains_id("threaded") {
+                config.set_threaded();
+            }
+            repl_command(config);
+        }
+        Some(("script", m)) => {
+            let mut config = Configuration::release();
+            config.source_files = vec![PathBuf::from(m.get_one::<String>("script-file").unwrap())];
+            config.linked_libraries.append(&mut read_library_options(m));
+            read_toolchain_options(m, &mut config.toolchain);
+            if m.contains_id("threaded") {
+                config.set_threaded();
+            }
+            config.program_args = m
+                .try_get_many::<String>("program-args")
+                .unwrap_or_default()
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            script_command(config);
+        }
+        Some(("clean", m)) => {
+            clean_command(m.contains_id("cache-only"));
+        }
+        _ => eprintln!("Unknown command!"),
+    }
+}
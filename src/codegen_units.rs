@@ -0,0 +1,368 @@
+// Code generation split into multiple LLVM modules (`fix build --incremental` and `fix build -j {jobs}`).
+//
+// The instantiated symbols are partitioned into codegen units, plus an entry unit which contains `main` function (or functions exported to C).
+// - With `--incremental`, there is one unit per Fix module from which the symbols are instantiated.
+//   The object file of each unit is cached under `.fixlang/objects` keyed by the hash of the unoptimized LLVM IR of the unit,
+//   and units whose IR is unchanged are not optimized or compiled again. The IR itself is still generated for every unit.
+//   When a unit is compiled again, its object files compiled from older IR are removed.
+// - Otherwise, the symbols sorted by name are split into `jobs` units of almost the same number of symbols.
+//
+// The IR of units is generated on the main thread, since the program is not shared between threads.
+// Then the units are passed to worker threads as bitcode, and each thread optimizes and compiles them in its own LLVM context.
+//
+// Since LLVM cannot inline functions across units, the output may be slower than the one built into a single LLVM module.
+
+use build_time::build_time_utc;
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use either::Either;
+use inkwell::{memory_buffer::MemoryBuffer, module::Linkage, targets::TargetMachine};
+
+use super::*;
+
+// The name of the unit which contains the entry points of the output.
+const ENTRY_UNIT_NAME: &str = "#entry";
+
+// A codegen unit whose object file has to be generated.
+pub(crate) struct CompileJob {
+    pub name: String,
+    pub bitcode: Vec<u8>,
+    pub obj_path: PathBuf,
+}
+
+// Generate codegen units of a program and compile each of them to an object file (if not cached).
+// Returns the paths of the object files.
+pub fn build_codegen_units(mut fix_mod: Program, config: &Configuration) -> Vec<PathBuf> {
+    let entry = prepare_program(&mut fix_mod, config);
+
+    let obj_dir = codegen_units_dir(config);
+    fs::create_dir_all(obj_dir)
+        .map_err(|e| error_exit(&format!("Failed to create directory `{}`: {}", obj_dir, e)))
+        .unwrap();
+
+    let all_symbols = fix_mod
+        .instantiated_global_symbols
+        .keys()
+        .cloned()
+        .collect::<HashSet<_>>();
+    let mut units = partition_symbols(&fix_mod, config);
+    units.push((ENTRY_UNIT_NAME.to_string(), HashSet::default()));
+
+    let mut entry = Some(entry);
+    let mut tm = get_target_machine(config);
+    let mut obj_paths = vec![];
+    let mut jobs = vec![];
+    for (unit_name, unit_symbols) in units {
+        let unit_entry = if unit_name == ENTRY_UNIT_NAME {
+            entry.take()
+        } else {
+            None
+        };
+        let (obj_path, job, tm_) = generate_codegen_unit(
+            &unit_name,
+            &fix_mod,
+            &unit_symbols,
+            &all_symbols,
+            unit_entry,
+            tm,
+            config,
+        );
+        tm = tm_;
+        obj_paths.push(obj_path);
+        jobs.extend(job);
+    }
+    set_time_passes_counter("codegen_units", obj_paths.len());
+    set_time_passes_counter("compiled_codegen_units", jobs.len());
+    compile_jobs(&jobs, config);
+    if config.incremental {
+        evict_stale_objects(&obj_paths);
+    }
+    obj_paths
+}
+
+// The directory where object files of units are written.
+fn codegen_units_dir(config: &Configuration) -> &'static str {
+    if config.incremental {
+        CODEGEN_UNITS_CACHE_PATH
+    } else {
+        INTERMEDIATE_PATH
+    }
+}
+
+// Partition the instantiated symbols into units (other than the entry unit).
+fn partition_symbols(
+    fix_mod: &Program,
+    config: &Configuration,
+) -> Vec<(String, HashSet<FullName>)> {
+    if config.incremental {
+        // One unit per module of the definitions.
+        let mut units: BTreeMap<Name, HashSet<FullName>> = BTreeMap::new();
+        for (name, sym) in &fix_mod.instantiated_global_symbols {
+            units
+                .entry(sym.template_name.module())
+                .or_default()
+                .insert(name.clone());
+        }
+        units.into_iter().collect()
+    } else {
+        // Split symbols sorted by name, so that symbols in the same namespace tend to be in the same unit.
+        let mut symbols = fix_mod
+            .instantiated_global_symbols
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        symbols.sort_by_cached_key(|name| name.to_string());
+        let unit_count = config.codegen_jobs.max(1);
+        let unit_size = (symbols.len() + unit_count - 1) / unit_count;
+        symbols
+            .chunks(unit_size.max(1))
+            .enumerate()
+            .map(|(i, chunk)| (format!("part{}", i), chunk.iter().cloned().collect()))
+            .collect()
+    }
+}
+
+// Generate the IR of a codegen unit which implements `unit_symbols` (and the entry points given by `entry`).
+// Returns the path of the object file of the unit, and a job to generate it unless a cached one exists.
+fn generate_codegen_unit(
+    unit_name: &str,
+    fix_mod: &Program,
+    unit_symbols: &HashSet<FullName>,
+    all_symbols: &HashSet<FullName>,
+    entry: Option<ProgramEntry>,
+    tm: TargetMachine,
+    config: &Configuration,
+) -> (PathBuf, Option<CompileJob>, TargetMachine) {
+    let ctx = Context::create();
+    let module = ctx.create_module(unit_name);
+    module.set_triple(&tm.get_triple());
+    module.set_data_layout(&tm.get_target_data().get_data_layout());
+
+    let mut gc = GenerationContext::new(
+        &ctx,
+        &module,
+        Either::Left(tm),
+        config.clone(),
+        fix_mod.type_env(),
+    );
+    let ir_generation_timer = time_pass("llvm_ir_generation");
+    if config.debug_info {
+        gc.create_debug_info();
+    }
+    build_runtime(&mut gc);
+
+    // Symbols of other units are only declared.
+    let external_symbols = all_symbols
+        .difference(unit_symbols)
+        .cloned()
+        .collect::<HashSet<_>>();
+    fix_mod.generate_code(&mut gc, &external_symbols, true);
+
+    // Names of the functions which should be visible from other units.
+    let mut exported_names = unit_symbols
+        .iter()
+        .flat_map(|name| [name.to_string(), format!("Get#{}", name.to_string())])
+        .collect::<HashSet<_>>();
+    if let Some(entry) = entry {
+        match &entry {
+            ProgramEntry::Main(_) => {
+                exported_names.insert("main".to_string());
+            }
+            ProgramEntry::Exports(exported_values) => {
+                for (export, _, _) in exported_values {
+                    exported_names.insert(export.c_function_name.clone());
+                }
+                exported_names.insert(LIBRARY_INITIALIZER_NAME.to_string());
+            }
+        }
+        build_program_entry(&mut gc, entry, config);
+    }
+    gc.finalize_di();
+    link_codegen_unit(&module, &exported_names);
+    drop(ir_generation_timer);
+
+    // In incremental build, the unit is not compiled if an object file generated from the same IR exists.
+    let obj_path = if config.incremental {
+        let hash = unit_hash(&module, gc.target.as_ref().unwrap_left(), config);
+        let obj_path = PathBuf::from(CODEGEN_UNITS_CACHE_PATH).join(format!(
+            "{}.{}.o",
+            unit_file_name(unit_name),
+            hash
+        ));
+        if obj_path.exists() {
+            return (obj_path, None, gc.target.unwrap_left());
+        }
+        obj_path
+    } else {
+        PathBuf::from(INTERMEDIATE_PATH).join(format!("{}.o", unit_file_name(unit_name)))
+    };
+    let job = CompileJob {
+        name: unit_name.to_string(),
+        bitcode: module.write_bitcode_to_memory().as_slice().to_vec(),
+        obj_path: obj_path.clone(),
+    };
+    (obj_path, Some(job), gc.target.unwrap_left())
+}
+
+// Optimize and compile units on `config.codegen_jobs` threads.
+// An error on a worker thread is sent back and raised on the calling thread,
+// since whether `error_exit` exits the process or panics (see `set_panic_on_error`) is configured per thread.
+pub(crate) fn compile_jobs(jobs: &[CompileJob], config: &Configuration) {
+    let thread_count = config.codegen_jobs.min(jobs.len()).max(1);
+    let next_job = AtomicUsize::new(0);
+    let errors = Mutex::new(vec![]);
+    std::thread::scope(|s| {
+        for _ in 0..thread_count {
+            s.spawn(|| loop {
+                let i = next_job.fetch_add(1, Ordering::Relaxed);
+                if i >= jobs.len() {
+                    break;
+                }
+                // `CompileError` cannot be sent between threads, but errors in this phase have no source location.
+                if let Err(err) = catch_compile_error_in(|| compile_job(&jobs[i], config)) {
+                    errors.lock().unwrap().push((i, err.to_string()));
+                    // Skip the remaining jobs.
+                    next_job.store(jobs.len(), Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    // Report the error of the first failed job, so that the message does not depend on scheduling.
+    let mut errors = errors.into_inner().unwrap();
+    errors.sort();
+    if let Some((_, msg)) = errors.into_iter().next() {
+        error_exit(&msg);
+    }
+}
+
+// Optimize a unit and write its object file.
+fn compile_job(job: &CompileJob, config: &Configuration) {
+    let ctx = Context::create();
+    let buffer = MemoryBuffer::create_from_memory_range(&job.bitcode, &job.name);
+    let module = Module::parse_bitcode_from_buffer(&buffer, &ctx)
+        .map_err(|e| {
+            error_exit(&format!(
+                "Failed to load codegen unit `{}`: {}",
+                job.name,
+                e.to_string()
+            ))
+        })
+        .unwrap();
+    optimize_module(&module, config);
+
+    let _timer = time_pass("object_emission");
+    // Write to a temporary file first, so that an incomplete object file is not used by another build.
+    let tm = get_target_machine(config);
+    let tmp_path = PathBuf::from(format!(
+        "{}.{}.tmp",
+        job.obj_path.to_str().unwrap(),
+        std::process::id()
+    ));
+    tm.write_to_file(&module, inkwell::targets::FileType::Object, &tmp_path)
+        .map_err(|e| error_exit(&format!("Failed to write to file: {}", e)))
+        .unwrap();
+    fs::rename(&tmp_path, &job.obj_path)
+        .map_err(|e| {
+            error_exit(&format!(
+                "Failed to write `{}`: {}",
+                job.obj_path.to_str().unwrap(),
+                e
+            ))
+        })
+        .unwrap();
+}
+
+// Remove cached object files `{unit}.{hash}.o` of the units in `obj_paths` other than the current ones.
+// Object files of units which are not built now (e.g., modules of another program in the same directory) are kept.
+fn evict_stale_objects(obj_paths: &[PathBuf]) {
+    let unit_of = |file_name: &str| {
+        file_name
+            .strip_suffix(".o")
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(unit, _hash)| unit.to_string())
+    };
+    let current = obj_paths
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .collect::<HashSet<_>>();
+    let units = current
+        .iter()
+        .filter_map(|name| unit_of(name))
+        .collect::<HashSet<_>>();
+    let entries = match fs::read_dir(CODEGEN_UNITS_CACHE_PATH) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if current.contains(&file_name) {
+            continue;
+        }
+        if unit_of(&file_name).map_or(false, |unit| units.contains(&unit)) {
+            // Failing to remove a stale file only wastes disk space.
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+// Fix linkages of a codegen unit so that units can be linked together.
+// - Definitions other than `exported_names` are internalized, so that each unit has its own copy of runtime functions and helpers.
+// - Command line arguments are stored in global variables shared by all units.
+// - Unused declarations are removed, so that the IR of a unit does not change when unrelated symbols are added to the program.
+fn link_codegen_unit(module: &Module, exported_names: &HashSet<String>) {
+    for func in module.get_functions().collect::<Vec<_>>() {
+        let name = func.get_name().to_str().unwrap().to_string();
+        if func.count_basic_blocks() == 0 {
+            if func
+                .as_global_value()
+                .as_pointer_value()
+                .get_first_use()
+                .is_none()
+            {
+                unsafe { func.delete() };
+            }
+        } else if !exported_names.contains(&name) {
+            func.set_linkage(Linkage::Internal);
+        }
+    }
+    for gv in module.get_globals() {
+        let name = gv.get_name().to_str().unwrap().to_string();
+        if gv.get_initializer().is_none() || name.starts_with("llvm.") {
+            continue;
+        }
+        if name == GLOBAL_VAR_NAME_ARGC || name == GLOBAL_VAR_NAME_ARGV {
+            gv.set_linkage(Linkage::WeakAny);
+        } else if !exported_names.contains(&name) {
+            gv.set_linkage(Linkage::Internal);
+        }
+    }
+}
+
+// The hash of the unoptimized IR of a unit and everything else which affects the generated object file.
+fn unit_hash(module: &Module, tm: &TargetMachine, config: &Configuration) -> String {
+    let mut data = module.print_to_string().to_string();
+    data += build_time_utc!();
+    data += tm.get_triple().as_str().to_str().unwrap();
+    data += tm.get_cpu().to_str().unwrap();
+    data += tm.get_feature_string().to_str().unwrap();
+    data += &format!("{}", config.fix_opt_level as u8);
+    data += &config.is_library().to_string();
+    format!("{:x}", md5::compute(data))
+}
+
+// The file name of the object file of a unit.
+fn unit_file_name(unit_name: &str) -> String {
+    if unit_name == ENTRY_UNIT_NAME {
+        "entry".to_string()
+    } else {
+        format!("module.{}", unit_name)
+    }
+}
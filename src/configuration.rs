@@ -1,8 +1,14 @@
-use std::{env, path::PathBuf};
+use std::path::PathBuf;
 
-use inkwell::OptimizationLevel;
+use inkwell::{targets::TargetMachine, OptimizationLevel};
 
-use crate::misc::error_exit;
+use crate::{
+    ast::name::FullName,
+    constants::{MAIN_FUNCTION_NAME, MAIN_MODULE_NAME},
+    misc::error_exit,
+    time_passes::TimePassesFormat,
+    toolchain::Toolchain,
+};
 
 #[derive(Clone, Copy)]
 pub enum LinkType {
@@ -10,6 +16,29 @@ pub enum LinkType {
     Dynamic,
 }
 
+// The kind of the file generated by `fix build`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputFileType {
+    Executable,
+    Object,
+    Assembly,
+    Bitcode,
+    SharedLibrary,
+    StaticLibrary,
+}
+
+impl OutputFileType {
+    // Is the output linked from object files (i.e., an executable or a library)?
+    pub fn is_linked(&self) -> bool {
+        match self {
+            OutputFileType::Executable
+            | OutputFileType::SharedLibrary
+            | OutputFileType::StaticLibrary => true,
+            OutputFileType::Object | OutputFileType::Assembly | OutputFileType::Bitcode => false,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Configuration {
     // Source files.
@@ -25,6 +54,12 @@ pub struct Configuration {
     pub debug_info: bool,
     // Is emit llvm?
     pub emit_llvm: bool,
+    // Emit the parsed program, the type-annotated program and the instantiated symbols, respectively.
+    pub emit_ast: bool,
+    pub emit_typed: bool,
+    pub emit_instances: bool,
+    // The kind of the output file.
+    pub output_file_type: OutputFileType,
     // Output file name.
     pub out_file_path: Option<PathBuf>,
     // Use threads.
@@ -36,6 +71,26 @@ pub struct Configuration {
     pub runtime_c_macro: Vec<String>,
     // Execute `run` not by ExecutionEngin, but by building executable binary and running it.
     pub run_by_build: bool,
+    // The full name (e.g., `Main::main`) of the global value of type `IO ()` which is executed as the program.
+    pub entry_point: String,
+    // The C compiler and linker.
+    pub toolchain: Toolchain,
+    // Command line arguments passed to the program by `fix run -- args...`.
+    pub program_args: Vec<String>,
+    // Generate code per module and reuse cached object files (`fix build --incremental`).
+    pub incremental: bool,
+    // The number of threads which optimize and compile LLVM modules (`fix build -j {jobs}`).
+    pub codegen_jobs: usize,
+    // Report durations of compilation phases (`--time-passes`).
+    pub time_passes: Option<TimePassesFormat>,
+    // The target triple of the output (`--target`). If `None`, the host's one is used.
+    pub target_triple: Option<String>,
+    // The CPU of the target (`--target-cpu`). If `None`, the host's CPU is used when compiling for the host, and a generic CPU otherwise.
+    pub target_cpu: Option<String>,
+    // Instrument the output to generate a profile in the given directory (`--profile-generate`).
+    pub profile_generate: Option<PathBuf>,
+    // The profile used for optimization (`--profile-use`).
+    pub profile_use: Option<PathBuf>,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -54,11 +109,25 @@ impl Default for Configuration {
             linked_libraries: vec![],
             debug_info: false,
             emit_llvm: false,
+            emit_ast: false,
+            emit_typed: false,
+            emit_instances: false,
+            output_file_type: OutputFileType::Executable,
             out_file_path: None,
             threaded: false,
             async_task: false,
             runtime_c_macro: vec![],
             run_by_build: false,
+            entry_point: FullName::from_strs(&[MAIN_MODULE_NAME], MAIN_FUNCTION_NAME).to_string(),
+            toolchain: Toolchain::from_env(),
+            program_args: vec![],
+            incremental: false,
+            codegen_jobs: 1,
+            time_passes: None,
+            target_triple: None,
+            target_cpu: None,
+            profile_generate: None,
+            profile_use: None,
         }
     }
 }
@@ -89,6 +158,11 @@ impl Configuration {
         self.add_dyanmic_library("m");
     }
 
+    // Add `libdl.so` to dynamically linked libraries.
+    pub fn add_libdl(&mut self) {
+        self.add_dyanmic_library("dl");
+    }
+
     pub fn get_output_llvm_ir_path(&self, pre_opt: bool) -> PathBuf {
         match &self.out_file_path {
             None => {
@@ -117,9 +191,34 @@ impl Configuration {
         }
     }
 
+    // Get the path of a file emitted by the compiler, e.g., `{kind}.{extension}`, or `{kind}_{output}.{extension}` if `-o {output}` is given.
+    pub fn get_output_emit_path_with_extension(&self, kind: &str, extension: &str) -> PathBuf {
+        match &self.out_file_path {
+            None => PathBuf::from(format!("{}.{}", kind, extension)),
+            Some(out_file_path) => {
+                let file_stem = out_file_path.file_stem();
+                if file_stem.is_none() {
+                    error_exit(&format!(
+                        "Invalid output file path: `{}`",
+                        out_file_path.to_str().unwrap()
+                    ))
+                }
+                let file_name = format!(
+                    "{}_{}.{}",
+                    kind,
+                    file_stem.unwrap().to_str().unwrap(),
+                    extension
+                );
+                let mut out_file_path = out_file_path.clone();
+                out_file_path.set_file_name(file_name);
+                out_file_path
+            }
+        }
+    }
+
     pub fn get_output_executable_file_path(&self) -> PathBuf {
         match &self.out_file_path {
-            None => PathBuf::from(if env::consts::OS != "windows" {
+            None => PathBuf::from(if !self.get_target_triple().contains("windows") {
                 "a.out"
             } else {
                 "a.exe"
@@ -128,6 +227,64 @@ impl Configuration {
         }
     }
 
+    // Get the path of the output file of the kind specified by `output_file_type`.
+    pub fn get_output_file_path(&self) -> PathBuf {
+        match &self.out_file_path {
+            None => match self.output_file_type {
+                OutputFileType::Executable => self.get_output_executable_file_path(),
+                OutputFileType::Object => PathBuf::from("a.o"),
+                OutputFileType::Assembly => PathBuf::from("a.s"),
+                OutputFileType::Bitcode => PathBuf::from("a.bc"),
+                OutputFileType::SharedLibrary => PathBuf::from(if self.is_target_macos() {
+                    "a.dylib"
+                } else {
+                    "a.so"
+                }),
+                OutputFileType::StaticLibrary => PathBuf::from("a.a"),
+            },
+            Some(out_file_path) => out_file_path.clone(),
+        }
+    }
+
+    // Set the kind of the output file. Only one kind other than executable can be specified.
+    pub fn set_output_file_type(&mut self, file_type: OutputFileType) {
+        if self.output_file_type != OutputFileType::Executable && self.output_file_type != file_type
+        {
+            error_exit(
+                "Only one of `--emit=obj`, `--emit=asm`, `--emit=bc` and `--lib` can be specified.",
+            );
+        }
+        self.output_file_type = file_type;
+    }
+
+    // Is the output a library which exports values by `FFI_EXPORT` (instead of running `main`)?
+    pub fn is_library(&self) -> bool {
+        self.output_file_type == OutputFileType::SharedLibrary
+            || self.output_file_type == OutputFileType::StaticLibrary
+    }
+
+    // Set the target triple of the output, which is also passed to the C compiler if it is clang.
+    pub fn set_target_triple(&mut self, triple: &str) {
+        self.target_triple = Some(triple.to_string());
+        self.toolchain.target = Some(triple.to_string());
+    }
+
+    // Get the target triple of the output.
+    pub fn get_target_triple(&self) -> String {
+        match &self.target_triple {
+            Some(triple) => triple.clone(),
+            None => TargetMachine::get_default_triple()
+                .as_str()
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+
+    // Is the output for macOS?
+    pub fn is_target_macos(&self) -> bool {
+        is_macos_triple(&self.get_target_triple())
+    }
+
     // Set threaded = true, and add ptherad library to linked_libraries.
     pub fn set_threaded(&mut self) {
         self.threaded = true;
@@ -171,6 +328,15 @@ impl Configuration {
         }
     }
 
+    // Link the runtime into the generated module as LLVM bitcode, so that runtime functions can be inlined.
+    pub fn get_runtime_inlining(&self) -> bool {
+        match self.fix_opt_level {
+            FixOptimizationLevel::None => false,
+            FixOptimizationLevel::Minimum => false,
+            FixOptimizationLevel::Default => true,
+        }
+    }
+
     pub fn get_borrowing_optimization(&self) -> bool {
         match self.fix_opt_level {
             FixOptimizationLevel::None => false,
@@ -185,3 +351,8 @@ impl Configuration {
         }
     }
 }
+
+// Is the target triple for macOS (e.g., `x86_64-apple-darwin` or `arm64-apple-macosx`)?
+pub fn is_macos_triple(triple: &str) -> bool {
+    triple.contains("apple") || triple.contains("darwin") || triple.contains("macos")
+}
@@ -1,6 +1,10 @@
 use inkwell::{context::Context, types::IntType, values::IntValue};
 
-use crate::{ast::program::Program, configuration::Configuration, runtime::RuntimeFunctions};
+use crate::{
+    ast::program::Program,
+    configuration::{is_macos_triple, Configuration},
+    runtime::RuntimeFunctions,
+};
 
 pub const NAMESPACE_SEPARATOR: &str = "::";
 pub const MODULE_SEPARATOR: &str = ".";
@@ -8,6 +12,7 @@ pub const MODULE_SEPARATOR: &str = ".";
 pub const STD_NAME: &str = "Std";
 pub const FFI_NAME: &str = "FFI";
 pub const IO_NAME: &str = "IO";
+pub const IOFAIL_NAME: &str = "IOFail";
 pub const PTR_NAME: &str = "Ptr";
 pub const U8_NAME: &str = "U8";
 pub const I8_NAME: &str = "I8";
@@ -23,6 +28,8 @@ pub const BOOL_NAME: &str = "Bool";
 pub const ARRAY_NAME: &str = "Array";
 pub const LAZY_NAME: &str = "Lazy";
 pub const BOXED_NAME: &str = "Boxed";
+// `CALL_C[R *(A1, ..., An), fun_ptr, ...]` calls a C function through a pointer.
+pub const CALL_C_INDIRECT_NAME: &str = "*";
 pub const DESTRUCTOR_OBJECT_NAME: &str = "Destructor";
 pub const DESTRUCTOR_OBJECT_VALUE_FIELD_IDX: u32 = 0;
 pub const DESTRUCTOR_OBJECT_DTOR_FIELD_IDX: u32 = 1;
@@ -63,9 +70,14 @@ pub const CTRL_BLK_REFCNT_IDX: u32 = 0;
 pub const CTRL_BLK_REFCNT_STATE_IDX: u32 = 1;
 pub const CTRL_BLK_OBJ_ID_IDX: u32 = 2;
 
-pub const TYPE_CHECK_CACHE_PATH: &str = ".fixlang/type_check_cache";
+pub const TYPE_CHECK_CACHE_PATH: &str = ".fixlang/type_check_cache.db";
+pub const TYPE_CHECK_CACHE_LOCK_PATH: &str = ".fixlang/type_check_cache.lock";
 pub const DOT_FIXLANG: &str = ".fixlang";
 pub const INTERMEDIATE_PATH: &str = ".fixlang/intermediate";
+// Relative to the user cache directory (`$XDG_CACHE_HOME` or `~/.cache`).
+pub const SCRIPT_CACHE_PATH: &str = "fix/scripts";
+pub const CODEGEN_UNITS_CACHE_PATH: &str = ".fixlang/objects";
+pub const RUNTIME_OBJECT_FILE_NAME: &str = "fixruntime.o";
 
 pub const ASYNCTASK_NAME: &str = "AsyncTask";
 
@@ -108,6 +120,13 @@ pub const STANDARD_LIBRARIES: &[(
         None,
         None,
     ),
+    (
+        "DynamicLibrary",
+        include_str!("./fix/dynamic_library.fix"),
+        "dynamic_library",
+        Some(Configuration::add_libdl),
+        None,
+    ),
     ("Hash", include_str!("./fix/hash.fix"), "hash", None, None),
     (
         "HashMap",
@@ -225,20 +244,21 @@ pub const TUPLE_SIZE_BASE: u32 = 3;
 // Is tuple unboxed?
 pub const TUPLE_UNBOX: bool = true;
 
-// The type in LLVM corresponding to `pthread_once_t` of this system.
-pub fn pthread_once_init_flag_type<'c>(ctx: &'c Context) -> IntType<'c> {
+// The type in LLVM corresponding to `pthread_once_t` of the target system.
+pub fn pthread_once_init_flag_type<'c>(ctx: &'c Context, triple: &str) -> IntType<'c> {
     // TODO: we should compile C program including "sizeof(pthread_once_t)" and run it to get the correct size.
-    if std::env::consts::OS == "macos" {
+    if is_macos_triple(triple) {
         ctx.i128_type()
     } else {
         ctx.i32_type()
     }
 }
 
-// The value of `PTHREAD_ONCE_INIT` of this system.
-pub fn pthread_once_init_flag_value<'c>(ctx: &'c Context) -> IntValue<'c> {
-    pthread_once_init_flag_type(ctx).const_zero()
+// The value of `PTHREAD_ONCE_INIT` of the target system.
+pub fn pthread_once_init_flag_value<'c>(ctx: &'c Context, triple: &str) -> IntValue<'c> {
+    pthread_once_init_flag_type(ctx, triple).const_zero()
 }
 
+pub const LIBRARY_INITIALIZER_NAME: &str = "fixruntime_init_library";
 pub const GLOBAL_VAR_NAME_ARGC: &str = "fixruntime_argc";
 pub const GLOBAL_VAR_NAME_ARGV: &str = "fixruntime_argv";
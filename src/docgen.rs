@@ -0,0 +1,776 @@
+// Documentation generator used by `fix doc`.
+// Since comments are silent rules in the grammar, doc comments are recovered from the source text between items of the parse tree.
+// A doc comment of an item is the sequence of line comments just before it, without a blank line between them.
+// Items of `Std` implemented in the compiler (e.g., primitive types and `Array::fill`) are documented where they are added to `Std` (see `make_std_mod`).
+
+use std::{collections::BTreeMap, fs, iter::once};
+
+use super::*;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum DocItemKind {
+    Type,
+    Trait,
+    Value,
+    Impl,
+}
+
+struct DocItem {
+    kind: DocItemKind,
+    // Full name of the item, e.g., `AsyncTask::AsyncIOTask::IOTask`, used for cross-links.
+    // For trait implementations, this is the signature.
+    name: String,
+    // The signature shown in the heading, e.g., `get : Task a -> a`.
+    signature: String,
+    doc: String,
+    // Methods of a trait.
+    members: Vec<DocItem>,
+}
+
+#[derive(Default)]
+struct DocNamespace {
+    items: Vec<DocItem>,
+    namespaces: BTreeMap<Name, DocNamespace>,
+}
+
+impl DocNamespace {
+    // Get the descendant namespace at `path`, creating it if it does not exist.
+    fn namespace_mut(&mut self, path: &[Name]) -> &mut DocNamespace {
+        path.iter().fold(self, |ns, name| {
+            ns.namespaces.entry(name.clone()).or_default()
+        })
+    }
+
+    // Collect the names of items in this namespace and its descendants.
+    fn item_names(&self, names: &mut HashSet<String>) {
+        names.extend(self.items.iter().map(|item| item.name.clone()));
+        for ns in self.namespaces.values() {
+            ns.item_names(names);
+        }
+    }
+}
+
+struct DocModule {
+    name: Name,
+    doc: String,
+    root: DocNamespace,
+}
+
+struct DocCollector<'a> {
+    src: &'a str,
+    // Position up to which the source text is already examined.
+    pos: usize,
+}
+
+// A heading of the generated document.
+struct Heading {
+    level: usize,
+    // Markdown text of the heading.
+    text: String,
+    // Code in the heading, which is rendered with cross-links in HTML.
+    code: Option<String>,
+    // Full name of the type or trait documented under the heading, which is the target of cross-links.
+    link_name: Option<String>,
+    doc: String,
+    anchor: String,
+}
+
+// A function implementing `fix doc` command.
+// Writes a Markdown document to `out_path`, and an HTML document with the extension replaced by `.html`.
+pub fn doc_command(sources: Vec<SourceFile>, out_path: &Path) {
+    let mut modules = sources
+        .iter()
+        .map(|source| collect_module(source))
+        .collect::<Vec<_>>();
+    for module in &mut modules {
+        if module.name == STD_NAME {
+            collect_builtin_items(module);
+        }
+    }
+    // `Std` comes first, followed by other modules in alphabetical order.
+    modules.sort_by_key(|m| (m.name != STD_NAME, m.name.clone()));
+
+    let headings = document_headings(&modules);
+    let markdown = render_markdown(&headings);
+    if let Err(e) = fs::write(out_path, markdown) {
+        error_exit(&format!(
+            "Failed to write \"{}\": {}",
+            out_path.display(),
+            e
+        ));
+    }
+    let html_path = out_path.with_extension("html");
+    let title = out_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let html = render_html(&headings, &title);
+    if let Err(e) = fs::write(&html_path, html) {
+        error_exit(&format!(
+            "Failed to write \"{}\": {}",
+            html_path.display(),
+            e
+        ));
+    }
+}
+
+// Source files of the built-in modules.
+pub fn standard_library_sources() -> Vec<SourceFile> {
+    let mut sources = vec![SourceFile::from_string(
+        PathBuf::from("std.fix"),
+        STD_SOURCE.to_string(),
+    )];
+    for (_, source_content, file_name, _, _) in STANDARD_LIBRARIES {
+        sources.push(SourceFile::from_string(
+            PathBuf::from(format!("{}.fix", file_name)),
+            source_content.to_string(),
+        ));
+    }
+    sources
+}
+
+fn collect_module(source: &SourceFile) -> DocModule {
+    let source_code = source.string();
+    let module = parse_to_pairs(source, &source_code).next().unwrap();
+    let mut pairs = module.into_inner().peekable();
+    let module_defn = pairs.next().unwrap();
+    let name = module_defn
+        .clone()
+        .into_inner()
+        .next()
+        .unwrap()
+        .as_str()
+        .to_string();
+    let mut collector = DocCollector {
+        src: &source_code,
+        pos: module_defn.as_span().end(),
+    };
+    // The first paragraph of comments after the module definition documents the module.
+    let next_start = pairs
+        .peek()
+        .map(|p| p.as_span().start())
+        .unwrap_or(source_code.len());
+    let doc = collector.module_doc(next_start);
+    let mut root = DocNamespace::default();
+    for pair in pairs {
+        collector.collect(pair, &NameSpace::new(vec![name.clone()]), &mut root);
+    }
+    DocModule { name, doc, root }
+}
+
+// Add items of `Std` which are implemented in the compiler and so have no source code:
+// types such as `I64` and `Array`, traits such as `Eq`, their implementations and values such as `Array::fill`.
+fn collect_builtin_items(module: &mut DocModule) {
+    let program = make_std_mod();
+    let mut documented = HashSet::default();
+    module.root.item_names(&mut documented);
+    let mut items = vec![];
+
+    // Types.
+    let mut types = bulitin_tycons()
+        .into_iter()
+        .map(|(tc, info)| (tc.name, info.tyvars))
+        .collect::<Vec<_>>();
+    for defn in &program.type_defns {
+        if defn.source.is_none() {
+            types.push((defn.name.clone(), defn.tyvars.clone()));
+        }
+    }
+    for (name, tyvars) in types {
+        // Function pointers and dynamic objects are internal types.
+        if name.name.starts_with('#') {
+            continue;
+        }
+        let signature = once(format!("type {}", name.name))
+            .chain(tyvars)
+            .collect::<Vec<_>>()
+            .join(" ");
+        items.push((
+            name.clone(),
+            builtin_item(DocItemKind::Type, &name, signature, &program),
+        ));
+    }
+
+    // Traits and their implementations. An implementation is placed in the namespace of the type.
+    for (id, info) in &program.trait_env.traits {
+        if info.source.is_some() {
+            continue;
+        }
+        let signature = format!("trait {} : {}", info.type_var.name, id.name.name);
+        let mut item = builtin_item(DocItemKind::Trait, &id.name, signature, &program);
+        let mut methods = info.methods.keys().collect::<Vec<_>>();
+        methods.sort();
+        for method in methods {
+            item.members.push(DocItem {
+                kind: DocItemKind::Value,
+                name: method.clone(),
+                signature: format!(
+                    "{} : {}",
+                    method,
+                    builtin_signature(&info.method_scheme(method))
+                ),
+                doc: String::default(),
+                members: vec![],
+            });
+        }
+        items.push((id.name.clone(), item));
+    }
+    for (id, instances) in &program.trait_env.instances {
+        for inst in instances {
+            let tycon = match inst.qual_pred.predicate.ty.toplevel_tycon() {
+                Some(tycon) => tycon,
+                None => continue,
+            };
+            if inst.source.is_some() {
+                continue;
+            }
+            let signature = unqualify_std(&format!(
+                "impl {} : {}",
+                inst.qual_pred.predicate.ty.to_string(),
+                id.name.to_string()
+            ));
+            let name = FullName::new(&tycon.name.to_namespace(), &signature);
+            items.push((
+                name,
+                DocItem {
+                    kind: DocItemKind::Impl,
+                    name: signature.clone(),
+                    signature,
+                    doc: String::default(),
+                    members: vec![],
+                },
+            ));
+        }
+    }
+
+    // Values.
+    for (name, gv) in &program.global_values {
+        if !matches!(gv.expr, SymbolExpr::Simple(_)) {
+            continue;
+        }
+        let signature = format!("{} : {}", name.name, builtin_signature(&gv.scm));
+        items.push((
+            name.clone(),
+            builtin_item(DocItemKind::Value, name, signature, &program),
+        ));
+    }
+
+    for (name, item) in items {
+        if documented.contains(&item.name) {
+            continue;
+        }
+        let path = &name.namespace.names[1..];
+        module.root.namespace_mut(path).items.push(item);
+    }
+}
+
+fn builtin_item(
+    kind: DocItemKind,
+    name: &FullName,
+    signature: String,
+    program: &Program,
+) -> DocItem {
+    DocItem {
+        kind,
+        name: name.to_string(),
+        signature,
+        doc: program.builtin_docs.get(name).cloned().unwrap_or_default(),
+        members: vec![],
+    }
+}
+
+// Stringify a type scheme in the way it is written in the source code of `Std`,
+// i.e., type variables are named `a`, `b`, ... and names in `Std` are not qualified.
+fn builtin_signature(scm: &Rc<Scheme>) -> String {
+    let text = unqualify_std(&scm.to_string());
+    let mut out = String::default();
+    let mut rest = text.as_str();
+    let mut vars: Vec<&str> = vec![];
+    while !rest.is_empty() {
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len())
+            .max(1);
+        let token = &rest[..len];
+        let is_tyvar = token.len() > 1
+            && token.starts_with('t')
+            && token[1..].chars().all(|c| c.is_ascii_digit());
+        if is_tyvar {
+            let idx = match vars.iter().position(|v| *v == token) {
+                Some(idx) => idx,
+                None => {
+                    vars.push(token);
+                    vars.len() - 1
+                }
+            };
+            out.push((b'a' + idx as u8) as char);
+        } else {
+            out += token;
+        }
+        rest = &rest[len..];
+    }
+    out
+}
+
+fn unqualify_std(text: &str) -> String {
+    text.replace(&format!("{}::", STD_NAME), "")
+}
+
+impl<'a> DocCollector<'a> {
+    // Split the source text from the current position to `until` into lines with their end positions,
+    // skipping the rest of the line where the previous item ends.
+    fn lines(&self, until: usize) -> Vec<(&'a str, usize)> {
+        let gap = &self.src[self.pos..until];
+        let mut offset = match gap.find('\n') {
+            Some(i) => self.pos + i + 1,
+            None => return vec![],
+        };
+        let mut lines = vec![];
+        for line in self.src[offset..until].split_inclusive('\n') {
+            offset += line.len();
+            lines.push((line.trim(), offset));
+        }
+        lines
+    }
+
+    fn module_doc(&mut self, until: usize) -> String {
+        let mut doc = vec![];
+        for (line, end) in self.lines(until) {
+            match line.strip_prefix("//") {
+                Some(comment) => {
+                    doc.push(strip_comment_space(comment));
+                    self.pos = end - 1;
+                }
+                None => break,
+            }
+        }
+        doc.join("\n")
+    }
+
+    // Get the doc comment of an item starting at `start`, and move the current position to the end of the item.
+    // Comments separated from the item by a blank line are not its doc comment.
+    fn doc(&mut self, start: usize, end: usize) -> String {
+        let mut doc: Vec<&str> = vec![];
+        for (line, line_end) in self.lines(start) {
+            // Skip the indentation of the item, which is not a line.
+            if !self.src[..line_end].ends_with('\n') {
+                continue;
+            }
+            match line.strip_prefix("//") {
+                Some(comment) => doc.push(strip_comment_space(comment)),
+                None => doc.clear(),
+            }
+        }
+        self.pos = end;
+        while doc.first() == Some(&"") {
+            doc.remove(0);
+        }
+        while doc.last() == Some(&"") {
+            doc.pop();
+        }
+        doc.join("\n")
+    }
+
+    fn collect(&mut self, pair: Pair<'a, Rule>, namespace: &NameSpace, ns_doc: &mut DocNamespace) {
+        let span = pair.as_span();
+        match pair.as_rule() {
+            Rule::global_defns => {
+                let child = pair.into_inner().next().unwrap();
+                self.collect(child, namespace, ns_doc);
+            }
+            Rule::global_defns_in_namespace => {
+                let mut pairs = pair.into_inner();
+                let ns_pair = pairs.next().unwrap();
+                self.doc(span.start(), ns_pair.as_span().end());
+                let mut namespace = namespace.clone();
+                let mut ns_doc = ns_doc;
+                for name in ns_pair.as_str().split("::") {
+                    namespace.names.push(name.to_string());
+                    ns_doc = ns_doc.namespaces.entry(name.to_string()).or_default();
+                }
+                for pair in pairs {
+                    self.collect(pair, &namespace, ns_doc);
+                }
+                self.pos = span.end();
+            }
+            Rule::type_defn => {
+                let doc = self.doc(span.start(), span.end());
+                let name = pair.into_inner().next().unwrap().as_str();
+                let text = span.as_str();
+                ns_doc.items.push(DocItem {
+                    kind: DocItemKind::Type,
+                    name: FullName::new(namespace, name).to_string(),
+                    signature: normalize_signature(&text[..text.find('=').unwrap()]),
+                    doc,
+                    members: vec![],
+                });
+            }
+            Rule::global_name_type_sign => {
+                let doc = self.doc(span.start(), span.end());
+                let name = pair.into_inner().next().unwrap().as_str();
+                ns_doc.items.push(DocItem {
+                    kind: DocItemKind::Value,
+                    name: FullName::new(namespace, name).to_string(),
+                    signature: normalize_signature(span.as_str().trim_end_matches(';')),
+                    doc,
+                    members: vec![],
+                });
+            }
+            Rule::trait_defn => {
+                let doc = self.doc(span.start(), span.start());
+                let text = span.as_str();
+                let mut item = DocItem {
+                    kind: DocItemKind::Trait,
+                    name: String::default(),
+                    signature: normalize_signature(&text[..text.find('{').unwrap()]),
+                    doc,
+                    members: vec![],
+                };
+                for pair in pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::trait_name => {
+                            item.name = FullName::new(namespace, pair.as_str()).to_string();
+                            self.pos = pair.as_span().end();
+                        }
+                        Rule::trait_member_defn => {
+                            let span = pair.as_span();
+                            let doc = self.doc(span.start(), span.end());
+                            let name = pair.into_inner().next().unwrap().as_str();
+                            item.members.push(DocItem {
+                                kind: DocItemKind::Value,
+                                name: name.to_string(),
+                                signature: normalize_signature(span.as_str().trim_end_matches(';')),
+                                doc,
+                                members: vec![],
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                self.pos = span.end();
+                ns_doc.items.push(item);
+            }
+            Rule::trait_alias_defn => {
+                let doc = self.doc(span.start(), span.end());
+                let name = pair.into_inner().next().unwrap().as_str();
+                ns_doc.items.push(DocItem {
+                    kind: DocItemKind::Trait,
+                    name: FullName::new(namespace, name).to_string(),
+                    signature: normalize_signature(span.as_str().trim_end_matches(';')),
+                    doc,
+                    members: vec![],
+                });
+            }
+            Rule::trait_impl => {
+                let doc = self.doc(span.start(), span.end());
+                let text = span.as_str();
+                let signature = normalize_signature(&text[..text.find('{').unwrap()]);
+                ns_doc.items.push(DocItem {
+                    kind: DocItemKind::Impl,
+                    name: signature.clone(),
+                    signature,
+                    doc,
+                    members: vec![],
+                });
+            }
+            _ => {
+                // Definitions of global values and import statements are not documented.
+                self.doc(span.start(), span.end());
+            }
+        }
+    }
+}
+
+fn strip_comment_space(comment: &str) -> &str {
+    comment.strip_prefix(' ').unwrap_or(comment)
+}
+
+// Remove comments in a signature and normalize whitespaces.
+fn normalize_signature(text: &str) -> String {
+    text.lines()
+        .map(|line| match line.find("//") {
+            Some(i) => &line[..i],
+            None => line,
+        })
+        .flat_map(|line| line.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Create the list of headings of the document.
+fn document_headings(modules: &[DocModule]) -> Vec<Heading> {
+    fn add_namespace(ns: &DocNamespace, level: usize, headings: &mut Vec<Heading>) {
+        let mut items = ns.items.iter().collect::<Vec<_>>();
+        items.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+        for item in items {
+            add_item(item, level, headings);
+        }
+        for (name, ns) in &ns.namespaces {
+            headings.push(Heading::new(
+                level,
+                format!("namespace {}", name),
+                true,
+                String::default(),
+            ));
+            add_namespace(ns, level + 1, headings);
+        }
+    }
+    fn add_item(item: &DocItem, level: usize, headings: &mut Vec<Heading>) {
+        let mut heading = Heading::new(level, item.signature.clone(), true, item.doc.clone());
+        if item.kind == DocItemKind::Type || item.kind == DocItemKind::Trait {
+            heading.link_name = Some(item.name.clone());
+        }
+        headings.push(heading);
+        for member in &item.members {
+            add_item(member, level + 1, headings);
+        }
+    }
+
+    let mut headings = vec![Heading::new(
+        1,
+        "Table of contents".to_string(),
+        false,
+        String::default(),
+    )];
+    for module in modules {
+        headings.push(Heading::new(
+            1,
+            format!("Module `{}`", module.name),
+            false,
+            module.doc.clone(),
+        ));
+        add_namespace(&module.root, 2, &mut headings);
+    }
+
+    // Give unique anchors in the same way as GitHub does.
+    let mut anchor_counts: HashMap<String, usize> = Default::default();
+    for heading in &mut headings {
+        let count = anchor_counts.entry(heading.anchor.clone()).or_default();
+        if *count > 0 {
+            heading.anchor += &format!("-{}", count);
+        }
+        *count += 1;
+    }
+    headings
+}
+
+impl Heading {
+    fn new(level: usize, text: String, is_code: bool, doc: String) -> Self {
+        let (text, code) = if is_code {
+            (format!("`{}`", text), Some(text))
+        } else {
+            (text, None)
+        };
+        let anchor = text
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+            .map(|c| if c == ' ' { '-' } else { c })
+            .collect();
+        Heading {
+            level: level.min(6),
+            text,
+            code,
+            link_name: None,
+            doc,
+            anchor,
+        }
+    }
+}
+
+fn render_markdown(headings: &[Heading]) -> String {
+    let mut out = String::default();
+    let mut first = true;
+    for heading in headings {
+        if first {
+            // Table of contents.
+            out += &format!("{} {}\n\n", "#".repeat(heading.level), heading.text);
+            for heading in headings {
+                out += &format!(
+                    "{}- [{}](#{})\n",
+                    "  ".repeat(heading.level - 1),
+                    heading.text,
+                    heading.anchor
+                );
+            }
+            out += "\n";
+            first = false;
+            continue;
+        }
+        out += &format!("{} {}\n", "#".repeat(heading.level), heading.text);
+        if !heading.doc.is_empty() {
+            out += &heading.doc;
+            out += "\n";
+        }
+        out += "\n";
+    }
+    out
+}
+
+fn render_html(headings: &[Heading], title: &str) -> String {
+    // Map names of types and traits to anchors of their headings for cross-links.
+    let mut links: HashMap<String, String> = Default::default();
+    let mut short_links: HashMap<String, String> = Default::default();
+    for heading in headings {
+        if let Some(name) = &heading.link_name {
+            links.insert(name.clone(), heading.anchor.clone());
+            let short_name = name.rsplit("::").next().unwrap().to_string();
+            short_links
+                .entry(short_name)
+                .or_insert(heading.anchor.clone());
+        }
+    }
+    let link = |name: &str| {
+        links
+            .get(name)
+            .or_else(|| short_links.get(name.rsplit("::").next().unwrap()))
+    };
+
+    let mut out = String::default();
+    out += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n";
+    out += &format!("<title>{}</title>\n", escape_html(title));
+    out +=
+        "<style>\nbody { font-family: sans-serif; max-width: 60em; margin: auto; padding: 1em; }\n\
+            code, pre { background: #f4f4f4; }\npre { padding: 0.5em; overflow-x: auto; }\n\
+            a { text-decoration: none; }\n</style>\n</head>\n<body>\n";
+    for heading in headings {
+        let content = match &heading.code {
+            Some(code) => format!("<code>{}</code>", link_code(code, &link)),
+            None => inline_html(&heading.text, &link),
+        };
+        if heading.level == 1 && heading.text == "Table of contents" {
+            out += &format!("<h1 id=\"{}\">{}</h1>\n<ul>\n", heading.anchor, content);
+            for heading in headings.iter().skip(1) {
+                out += &format!(
+                    "<li style=\"margin-left: {}em\"><a href=\"#{}\">{}</a></li>\n",
+                    (heading.level - 1) * 2,
+                    heading.anchor,
+                    inline_html(&heading.text, &|_: &str| None)
+                );
+            }
+            out += "</ul>\n";
+            continue;
+        }
+        out += &format!(
+            "<h{} id=\"{}\">{}</h{}>\n",
+            heading.level, heading.anchor, content, heading.level
+        );
+        out += &doc_html(&heading.doc, &link);
+    }
+    out += "</body>\n</html>\n";
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Render code, adding links to names of types and traits.
+fn link_code<'a>(code: &str, link: &dyn Fn(&str) -> Option<&'a String>) -> String {
+    let mut out = String::default();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_uppercase() {
+            // Read a capital name which may be qualified by namespaces.
+            let mut len = 0;
+            loop {
+                len += rest[len..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len() - len);
+                let next = &rest[len..];
+                if next.starts_with("::")
+                    && next[2..]
+                        .chars()
+                        .next()
+                        .map_or(false, |c| c.is_ascii_uppercase())
+                {
+                    len += 2;
+                } else {
+                    break;
+                }
+            }
+            let name = &rest[..len];
+            match link(name) {
+                Some(anchor) => {
+                    out += &format!("<a href=\"#{}\">{}</a>", anchor, escape_html(name))
+                }
+                None => out += &escape_html(name),
+            }
+            rest = &rest[len..];
+        } else if c.is_alphanumeric() || c == '_' {
+            // Skip a lowercase name so that capital letters in it are not linked.
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            out += &escape_html(&rest[..len]);
+            rest = &rest[len..];
+        } else {
+            out += &escape_html(&c.to_string());
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+// Render a text with inline codes.
+fn inline_html<'a>(text: &str, link: &dyn Fn(&str) -> Option<&'a String>) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, s)| {
+            if i % 2 == 1 {
+                format!("<code>{}</code>", link_code(s, link))
+            } else {
+                escape_html(s)
+            }
+        })
+        .collect()
+}
+
+// Render a doc comment: paragraphs separated by blank lines, and code blocks fenced by ```.
+fn doc_html<'a>(doc: &str, link: &dyn Fn(&str) -> Option<&'a String>) -> String {
+    let mut out = String::default();
+    let mut paragraph: Vec<&str> = vec![];
+    let mut code_block: Option<Vec<&str>> = None;
+    let flush = |paragraph: &mut Vec<&str>, out: &mut String| {
+        if !paragraph.is_empty() {
+            out.push_str(&format!(
+                "<p>{}</p>\n",
+                inline_html(&paragraph.join("\n"), link)
+            ));
+            paragraph.clear();
+        }
+    };
+    for line in doc.lines() {
+        if line.trim_start().starts_with("```") {
+            match code_block.take() {
+                Some(code) => {
+                    out += &format!(
+                        "<pre><code>{}</code></pre>\n",
+                        escape_html(&code.join("\n"))
+                    )
+                }
+                None => {
+                    flush(&mut paragraph, &mut out);
+                    code_block = Some(vec![]);
+                }
+            }
+        } else if let Some(code) = code_block.as_mut() {
+            code.push(line);
+        } else if line.trim().is_empty() {
+            flush(&mut paragraph, &mut out);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    if let Some(code) = code_block {
+        out += &format!(
+            "<pre><code>{}</code></pre>\n",
+            escape_html(&code.join("\n"))
+        );
+    }
+    flush(&mut paragraph, &mut out);
+    out
+}
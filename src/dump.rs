@@ -0,0 +1,267 @@
+// Dumping intermediate representations of a program, printed by `--emit=ast,typed,instances`.
+//
+// Expressions are printed as trees; each node is written on its own line, indented by its depth,
+// and followed by its type if it is known.
+
+use std::fs;
+
+use super::*;
+
+// Dump types, traits, trait instances and global values defined in the given modules, as parsed.
+pub fn dump_ast(program: &Program, modules: &HashSet<Name>) -> String {
+    let mut out = String::new();
+
+    let mut type_defns = program
+        .type_defns
+        .iter()
+        .filter(|defn| modules.contains(&defn.name.module()))
+        .collect::<Vec<_>>();
+    type_defns.sort_by_key(|defn| defn.name.to_string());
+    for defn in type_defns {
+        out += &dump_type_defn(defn);
+        out += "\n";
+    }
+
+    let mut traits = program
+        .trait_env
+        .traits
+        .values()
+        .filter(|info| modules.contains(&info.id.name.module()))
+        .collect::<Vec<_>>();
+    traits.sort_by_key(|info| info.id.to_string());
+    for info in traits {
+        out += &format!(
+            "trait {} : {} {{\n",
+            info.type_var.name,
+            info.id.to_string()
+        );
+        let mut methods = info.methods.iter().collect::<Vec<_>>();
+        methods.sort_by_key(|(name, _)| name.clone());
+        for (name, qual_ty) in methods {
+            out += &format!("    {} : {};\n", name, qual_ty.ty.to_string());
+        }
+        out += "}\n\n";
+    }
+
+    let mut trait_ids = program.trait_env.instances.keys().collect::<Vec<_>>();
+    trait_ids.sort_by_key(|id| id.to_string());
+    for trait_id in trait_ids {
+        for inst in &program.trait_env.instances[trait_id] {
+            if !modules.contains(&inst.define_module) {
+                continue;
+            }
+            out += &format!(
+                "impl {} {{\n",
+                inst.qual_pred.predicate.to_string_normalize()
+            );
+            let mut methods = inst.methods.iter().collect::<Vec<_>>();
+            methods.sort_by_key(|(name, _)| name.clone());
+            for (name, expr) in methods {
+                out += &format!("    {} =\n", name);
+                dump_expr(expr, None, 2, "", &mut out);
+            }
+            out += "}\n\n";
+        }
+    }
+
+    for name in sorted_global_value_names(program, modules) {
+        let gv = &program.global_values[name];
+        out += &format!("{} : {};\n", name.to_string(), gv.scm.to_string());
+        match &gv.expr {
+            SymbolExpr::Simple(e) => {
+                out += &format!("{} =\n", name.to_string());
+                dump_expr(&e.expr, None, 1, "", &mut out);
+            }
+            SymbolExpr::Method(impls) => {
+                for method in impls {
+                    out += &format!(
+                        "{} = (implementation for {})\n",
+                        name.to_string(),
+                        method.ty.to_string()
+                    );
+                    dump_expr(&method.expr.expr, None, 1, "", &mut out);
+                }
+            }
+        }
+        out += "\n";
+    }
+    out
+}
+
+// Dump global values (and implementations of trait methods) defined in the given modules after type-checking.
+// Each subexpression is annotated with its inferred type.
+// Only the values used by the instantiated symbols are dumped, since the others are not type-checked by the compiler.
+pub fn dump_typed(program: &Program, modules: &HashSet<Name>, tc: &TypeCheckContext) -> String {
+    let mut out = String::new();
+    let mut names = program.global_values.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        let gv = &program.global_values[name];
+        let typed_exprs = program.check_type_of_used_global_value(name, modules, tc);
+        if typed_exprs.is_empty() {
+            continue;
+        }
+        out += &format!("{} : {};\n", name.to_string(), gv.scm.to_string());
+        for te in typed_exprs {
+            out += &format!("{} =\n", name.to_string());
+            dump_expr(&te.expr, Some(&te.type_resolver), 1, "", &mut out);
+        }
+        out += "\n";
+    }
+    out
+}
+
+// Dump the instantiated symbols with their concrete types.
+pub fn dump_instances(program: &Program) -> String {
+    let mut out = String::new();
+    let mut symbols = program
+        .instantiated_global_symbols
+        .iter()
+        .collect::<Vec<_>>();
+    symbols.sort_by_key(|(name, _)| name.to_string());
+    for (name, sym) in symbols {
+        out += &format!("{} : {};\n", name.to_string(), sym.ty.to_string());
+        out += &format!("    (instance of {})\n", sym.template_name.to_string());
+        match &sym.expr {
+            Some(expr) => {
+                out += &format!("{} =\n", name.to_string());
+                dump_expr(expr, Some(&sym.type_resolver), 1, "", &mut out);
+            }
+            None => out += "    (not instantiated)\n",
+        }
+        out += "\n";
+    }
+    out
+}
+
+// Print a dump of `--emit={kind}` to the standard output.
+pub fn print_dump(kind: &str, content: &str) {
+    println!("// --emit={}", kind);
+    print!("{}", content);
+}
+
+// Write a dump to a file.
+pub fn write_dump(path: &Path, content: &str) {
+    if let Err(e) = fs::write(path, content) {
+        error_exit(&format!(
+            "Failed to write `{}`: {}",
+            path.to_string_lossy(),
+            e
+        ));
+    }
+}
+
+fn sorted_global_value_names<'a>(
+    program: &'a Program,
+    modules: &HashSet<Name>,
+) -> Vec<&'a FullName> {
+    let mut names = program
+        .global_values
+        .keys()
+        .filter(|name| modules.contains(&name.module()))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+fn dump_type_defn(defn: &TypeDefn) -> String {
+    let mut head = format!("type {}", defn.name.to_string());
+    for tv in &defn.tyvars {
+        head += &format!(" {}", tv);
+    }
+    let (kind, is_unbox, fields) = match &defn.value {
+        TypeDeclValue::Struct(s) => ("struct", s.is_unbox, &s.fields),
+        TypeDeclValue::Union(u) => ("union", u.is_unbox, &u.fields),
+        TypeDeclValue::Alias(a) => return format!("{} = {};\n", head, a.value.to_string()),
+    };
+    let mut out = format!(
+        "{} = {} {} {{\n",
+        head,
+        if is_unbox { "unbox" } else { "box" },
+        kind
+    );
+    for field in fields {
+        out += &format!("    {} : {},\n", field.name, field.ty.to_string());
+    }
+    out += "};\n";
+    out
+}
+
+// Dump an expression as a tree.
+// If `resolver` is given, the type of each node is substituted by it.
+fn dump_expr(
+    expr: &Rc<ExprNode>,
+    resolver: Option<&TypeResolver>,
+    indent: usize,
+    label: &str,
+    out: &mut String,
+) {
+    let node = match &*expr.expr {
+        Expr::Var(v) => v.name.to_string(),
+        Expr::LLVM(l) => format!("LLVM {}", l.name),
+        Expr::App(_, _) => "App".to_string(),
+        Expr::Lam(params, _) => format!(
+            "Lam |{}|",
+            params
+                .iter()
+                .map(|v| v.name.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Expr::Let(pat, _, _) => format!("Let {}", pat.pattern.to_string()),
+        Expr::If(_, _, _) => "If".to_string(),
+        Expr::TyAnno(_, ty) => format!("TyAnno {}", ty.to_string()),
+        Expr::ArrayLit(_) => "Array".to_string(),
+        Expr::MakeStruct(tc, _) => format!("MakeStruct {}", tc.to_string()),
+        Expr::CallC(name, _, _, _, _) => format!("CALL_C {}", name),
+    };
+    out.push_str(&"    ".repeat(indent));
+    out.push_str(label);
+    out.push_str(&node);
+    if let Some(ty) = &expr.ty {
+        let ty = match resolver {
+            Some(resolver) => resolver.substitute_type(ty),
+            None => ty.clone(),
+        };
+        out.push_str(" : ");
+        out.push_str(&ty.to_string());
+    }
+    out.push('\n');
+
+    let indent = indent + 1;
+    match &*expr.expr {
+        Expr::Var(_) | Expr::LLVM(_) => {}
+        Expr::App(fun, args) => {
+            dump_expr(fun, resolver, indent, "", out);
+            for arg in args {
+                dump_expr(arg, resolver, indent, "", out);
+            }
+        }
+        Expr::Lam(_, body) => dump_expr(body, resolver, indent, "", out),
+        Expr::Let(_, bound, val) => {
+            dump_expr(bound, resolver, indent, "= ", out);
+            dump_expr(val, resolver, indent, "in ", out);
+        }
+        Expr::If(cond, then_expr, else_expr) => {
+            dump_expr(cond, resolver, indent, "", out);
+            dump_expr(then_expr, resolver, indent, "then ", out);
+            dump_expr(else_expr, resolver, indent, "else ", out);
+        }
+        Expr::TyAnno(e, _) => dump_expr(e, resolver, indent, "", out),
+        Expr::ArrayLit(elems) => {
+            for elem in elems {
+                dump_expr(elem, resolver, indent, "", out);
+            }
+        }
+        Expr::MakeStruct(_, fields) => {
+            for (name, field) in fields {
+                dump_expr(field, resolver, indent, &format!("{} = ", name), out);
+            }
+        }
+        Expr::CallC(_, _, _, _, args) => {
+            for arg in args {
+                dump_expr(arg, resolver, indent, "", out);
+            }
+        }
+    }
+}
@@ -0,0 +1,600 @@
+// Source code formatter used by `fix fmt`.
+// Since comments are silent rules in the grammar, they are recovered from the source text between nodes of the parse tree.
+
+use super::*;
+
+const INDENT: &str = "    ";
+const MAX_WIDTH: usize = 100;
+
+// Newlines in literals and block comments are replaced with this character while formatting,
+// so that they are not affected by indentation.
+const VERBATIM_NEWLINE: char = '\u{E000}';
+
+struct Comment {
+    text: String,
+    is_line_comment: bool,
+    // Number of newlines between the previous token (or comment) and this comment.
+    newlines_before: usize,
+}
+
+struct Formatter<'a> {
+    src: &'a str,
+    // Position up to which comments are already collected.
+    pos: usize,
+}
+
+// Format the content of a source file.
+pub fn format_source(source: &SourceFile) -> String {
+    let source_code = source.string();
+    let module = parse_to_pairs(source, &source_code).next().unwrap();
+    // Keep the shebang line as it is.
+    let shebang_len = if source_code.starts_with("#!") {
+        source_code.find('\n').unwrap_or(source_code.len())
+    } else {
+        0
+    };
+    let mut fmt = Formatter {
+        src: &source_code,
+        pos: shebang_len,
+    };
+    let mut out = String::default();
+    let items = module.into_inner().collect::<Vec<_>>();
+    fmt.fmt_seq(items, source_code.len(), 0, &mut out);
+    out += "\n";
+    let out = out.trim_start_matches('\n').replace(VERBATIM_NEWLINE, "\n");
+    if shebang_len > 0 {
+        source_code[..shebang_len].to_string() + "\n" + &out
+    } else {
+        out
+    }
+}
+
+// A function implementing `fix fmt` command.
+// If `check` is true, files are not modified and the process exits with a non-zero status if some file is not formatted.
+pub fn format_files(files: &[PathBuf], check: bool) {
+    let mut unformatted = vec![];
+    for file_path in files {
+        let source = SourceFile::from_file_path(file_path.clone());
+        let formatted = format_source(&source);
+        if formatted == source.string() {
+            continue;
+        }
+        if check {
+            unformatted.push(file_path.clone());
+            continue;
+        }
+        if let Err(e) = std::fs::write(file_path, formatted) {
+            error_exit(&format!(
+                "Failed to write file \"{}\": {}",
+                file_path.to_string_lossy(),
+                e
+            ));
+        }
+    }
+    if !unformatted.is_empty() {
+        for file_path in &unformatted {
+            eprintln!("\"{}\" is not formatted.", file_path.to_string_lossy());
+        }
+        std::process::exit(1);
+    }
+}
+
+fn is_flat(s: &str) -> bool {
+    !s.contains('\n')
+}
+
+fn fits(s: &str, indent: usize) -> bool {
+    is_flat(s) && s.chars().count() + indent * INDENT.len() <= MAX_WIDTH
+}
+
+// Check if a multi-line code ends with a closing bracket, so that it can follow other code on the same line, as `|x| (` or `f(a, |x| (`.
+fn hangs(s: &str) -> bool {
+    !is_flat(s)
+        && s.lines()
+            .last()
+            .map_or(false, |line| line.starts_with(&[')', ']', '}'][..]))
+}
+
+// Indent the lines of a code except the first line.
+fn shift(s: &str) -> String {
+    let mut res = String::default();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        res.push(c);
+        if c == '\n' && chars.peek().map_or(false, |c| *c != '\n') {
+            res += INDENT;
+        }
+    }
+    res
+}
+
+// Remove comments from a code between tokens.
+fn strip_comments(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut res = String::default();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"//") {
+            i = s[i..].find('\n').map_or(s.len(), |e| i + e);
+        } else if bytes[i..].starts_with(b"/*") {
+            i = s[i + 2..].find("*/").map_or(s.len(), |e| i + 2 + e + 2);
+        } else {
+            let c = s[i..].chars().next().unwrap();
+            res.push(c);
+            i += c.len_utf8();
+        }
+    }
+    res
+}
+
+impl<'a> Formatter<'a> {
+    // Collect comments between the current position and `until`.
+    // Returns the comments and the number of newlines between the last token (or comment) and `until`.
+    fn take_comments(&mut self, until: usize) -> (Vec<Comment>, usize) {
+        if until <= self.pos {
+            return (vec![], 0);
+        }
+        let text = &self.src[self.pos..until];
+        let bytes = text.as_bytes();
+        self.pos = until;
+        let mut comments = vec![];
+        let mut newlines = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            let end = if bytes[i..].starts_with(b"//") {
+                text[i..].find('\n').map_or(text.len(), |e| i + e)
+            } else if bytes[i..].starts_with(b"/*") {
+                text[i + 2..]
+                    .find("*/")
+                    .map_or(text.len(), |e| i + 2 + e + 2)
+            } else {
+                if bytes[i] == b'\n' {
+                    newlines += 1;
+                }
+                i += 1;
+                continue;
+            };
+            comments.push(Comment {
+                text: text[i..end]
+                    .trim_end()
+                    .replace('\n', &VERBATIM_NEWLINE.to_string()),
+                is_line_comment: bytes[i..].starts_with(b"//"),
+                newlines_before: newlines,
+            });
+            newlines = 0;
+            i = end;
+        }
+        (comments, newlines)
+    }
+
+    // Format comments before `until` so that they are placed between tokens.
+    fn inline_comments(&mut self, until: usize) -> String {
+        let (comments, _) = self.take_comments(until);
+        let mut res = String::default();
+        for comment in comments {
+            res += &comment.text;
+            res += if comment.is_line_comment { "\n" } else { " " };
+        }
+        res
+    }
+
+    // Format comments before `until`, each on its own line.
+    // A comment at the same line as the previous token is kept there.
+    // Returns the number of newlines between the last comment (or token) and `until`.
+    fn comment_lines(&mut self, until: usize, out: &mut String, first: &mut bool) -> usize {
+        let has_previous_token = self.pos > 0;
+        let (comments, newlines) = self.take_comments(until);
+        for comment in comments {
+            if comment.newlines_before == 0 && has_previous_token {
+                *out += " ";
+            } else {
+                *out += "\n";
+                if comment.newlines_before >= 2 && !*first {
+                    *out += "\n";
+                }
+                *first = false;
+            }
+            *out += &comment.text;
+        }
+        newlines
+    }
+
+    // Format items (e.g., global definitions) each placed on its own line, keeping blank lines between them.
+    fn fmt_seq(&mut self, items: Vec<Pair<'a, Rule>>, end: usize, indent: usize, out: &mut String) {
+        let mut first = true;
+        for item in items {
+            let newlines = self.comment_lines(item.as_span().start(), out, &mut first);
+            *out += "\n";
+            if newlines >= 2 && !first {
+                *out += "\n";
+            }
+            first = false;
+            *out += &self.fmt_node(item, indent);
+        }
+        self.comment_lines(end, out, &mut first);
+    }
+
+    // Format a block such as `impl` or `namespace` containing items.
+    fn fmt_block(&mut self, items: Vec<Pair<'a, Rule>>, end: usize, indent: usize) -> String {
+        let mut body = String::default();
+        self.fmt_seq(items, end, indent + 1, &mut body);
+        if body.is_empty() {
+            "{}".to_string()
+        } else {
+            "{".to_string() + &shift(&body) + "\n}"
+        }
+    }
+
+    // Format a comma-separated list of items enclosed by brackets.
+    // Each item consists of one node or a pair of nodes joined by " : ", such as a field of a struct.
+    // If the list does not fit in a line, each item is placed on its own line,
+    // except that a multi-line item at the last may follow other items, as `f(x, |y| (`.
+    fn fmt_list(
+        &mut self,
+        (open, close): (&str, &str),
+        padding: bool,
+        items: Vec<Vec<Pair<'a, Rule>>>,
+        end: usize,
+        indent: usize,
+    ) -> String {
+        // Render items separately to place comments before them on their own lines in case the list is broken.
+        let mut leading_comments = vec![];
+        let mut rendered = vec![];
+        for item in items {
+            let mut comments = String::default();
+            let mut first = true;
+            self.comment_lines(item[0].as_span().start(), &mut comments, &mut first);
+            leading_comments.push(comments);
+            let strs = item
+                .into_iter()
+                .map(|pair| self.child(pair, indent + 1))
+                .collect::<Vec<_>>();
+            rendered.push(strs.join(" : "));
+        }
+        let mut trailing_comments = String::default();
+        let mut first = true;
+        self.comment_lines(end, &mut trailing_comments, &mut first);
+
+        let has_comments =
+            !trailing_comments.is_empty() || leading_comments.iter().any(|c| !c.is_empty());
+        if rendered.is_empty() && !has_comments {
+            return open.to_string() + close;
+        }
+        let padding = if padding { " " } else { "" };
+        let joined = rendered.join(", ");
+        if !has_comments {
+            let flat = open.to_string() + padding + &joined + padding + close;
+            if fits(&flat, indent) {
+                return flat;
+            }
+            let (last, init) = rendered.split_last().unwrap();
+            if init.iter().all(|s| is_flat(s)) && hangs(last) {
+                let first_line = joined.lines().next().unwrap();
+                if fits(first_line, indent) {
+                    return open.to_string() + padding + &joined + padding + close;
+                }
+            }
+        }
+        let mut res = open.to_string();
+        let n = rendered.len();
+        for (i, (item, comments)) in rendered.iter().zip(leading_comments).enumerate() {
+            res += &shift(&comments);
+            res += "\n";
+            res += INDENT;
+            res += &shift(item);
+            if i + 1 < n {
+                res += ",";
+            }
+        }
+        res += &shift(&trailing_comments);
+        res += "\n";
+        res += close;
+        res
+    }
+
+    // Format an expression following other code on the same line, as `x = expr` or `|x| expr`.
+    // A multi-line expression is placed on the next lines unless it ends with a closing bracket.
+    fn fmt_hanging(&mut self, pair: Pair<'a, Rule>, indent: usize) -> String {
+        let s = self.child(pair, indent);
+        if is_flat(&s) || hangs(&s) {
+            " ".to_string() + &s
+        } else {
+            "\n".to_string() + INDENT + &shift(&s)
+        }
+    }
+
+    // Format a node after comments preceding it.
+    fn child(&mut self, pair: Pair<'a, Rule>, indent: usize) -> String {
+        let comments = self.inline_comments(pair.as_span().start());
+        comments + &self.fmt_node(pair, indent)
+    }
+
+    // Format children of a node and join them.
+    fn join_children(&mut self, pair: Pair<'a, Rule>, sep: &str, indent: usize) -> String {
+        let strs = pair
+            .into_inner()
+            .map(|child| self.child(child, indent))
+            .collect::<Vec<_>>();
+        strs.join(sep)
+    }
+
+    // Output a node as it is written.
+    fn verbatim(&mut self, pair: Pair<'a, Rule>) -> String {
+        self.pos = self.pos.max(pair.as_span().end());
+        pair.as_str().replace('\n', &VERBATIM_NEWLINE.to_string())
+    }
+
+    // Format a node. Lines of the result except the first one are indented relative to the line where the node starts.
+    fn fmt_node(&mut self, pair: Pair<'a, Rule>, indent: usize) -> String {
+        let end = pair.as_span().end();
+        match pair.as_rule() {
+            // Module-level items.
+            Rule::module_defn => format!("module {};", self.join_children(pair, "", indent)),
+            Rule::import_statement => format!("import {};", self.join_children(pair, "", indent)),
+            Rule::export_statement => {
+                format!("FFI_EXPORT[{}];", self.join_children(pair, ", ", indent))
+            }
+            Rule::global_defns => self.join_children(pair, "", indent),
+            Rule::global_defns_in_namespace => {
+                let mut pairs = pair.into_inner();
+                let namespace = self.child(pairs.next().unwrap(), indent);
+                let block = self.fmt_block(pairs.collect(), end, indent);
+                format!("namespace {} {}", namespace, block)
+            }
+            Rule::global_name_type_sign | Rule::trait_member_defn => {
+                format!("{};", self.join_children(pair, " : ", indent))
+            }
+            Rule::global_name_defn | Rule::trait_member_impl => {
+                let mut pairs = pair.into_inner();
+                let name = self.child(pairs.next().unwrap(), indent);
+                let expr = self.fmt_hanging(pairs.next().unwrap(), indent);
+                format!("{} ={};", name, expr)
+            }
+            Rule::type_defn => {
+                let mut res = "type".to_string();
+                let mut pairs = pair.into_inner().peekable();
+                while pairs.peek().unwrap().as_rule() != Rule::struct_defn
+                    && pairs.peek().unwrap().as_rule() != Rule::union_defn
+                    && pairs.peek().unwrap().as_rule() != Rule::type_alias_defn
+                {
+                    res += " ";
+                    res += &self.child(pairs.next().unwrap(), indent);
+                }
+                res += " = ";
+                res += &self.child(pairs.next().unwrap(), indent);
+                res + ";"
+            }
+            Rule::struct_defn | Rule::union_defn => {
+                let keyword = if pair.as_rule() == Rule::struct_defn {
+                    "struct"
+                } else {
+                    "union"
+                };
+                let mut res = String::default();
+                let mut pairs = pair.into_inner().peekable();
+                if pairs.peek().unwrap().as_rule() == Rule::box_or_unbox {
+                    res += &self.child(pairs.next().unwrap(), indent);
+                    res += " ";
+                }
+                let fields = pairs.map(|field| vec![field]).collect();
+                res + keyword + " " + &self.fmt_list(("{", "}"), true, fields, end, indent)
+            }
+            Rule::type_field => self.join_children(pair, " : ", indent),
+            Rule::trait_defn => {
+                let mut res = "trait ".to_string();
+                let mut pairs = pair.into_inner().peekable();
+                if pairs.peek().unwrap().as_rule() == Rule::predicates {
+                    res += &self.child(pairs.next().unwrap(), indent);
+                    res += " ";
+                }
+                res += &self.child(pairs.next().unwrap(), indent);
+                res += " : ";
+                res += &self.child(pairs.next().unwrap(), indent);
+                res += " ";
+                res + &self.fmt_block(pairs.collect(), end, indent)
+            }
+            Rule::trait_impl => {
+                let mut pairs = pair.into_inner();
+                let predicate = self.child(pairs.next().unwrap(), indent);
+                let block = self.fmt_block(pairs.collect(), end, indent);
+                format!("impl {} {}", predicate, block)
+            }
+            Rule::trait_alias_defn => {
+                let mut pairs = pair.into_inner();
+                let name = self.child(pairs.next().unwrap(), indent);
+                let traits = pairs
+                    .map(|p| self.child(p, indent))
+                    .collect::<Vec<_>>()
+                    .join(" + ");
+                format!("trait {} = {};", name, traits)
+            }
+
+            // Types, kinds and patterns.
+            Rule::type_expr
+            | Rule::type_nlr
+            | Rule::type_alias_defn
+            | Rule::kind_nlr
+            | Rule::pattern
+            | Rule::expr
+            | Rule::expr_nlr
+            | Rule::expr_lit => self.join_children(pair, "", indent),
+            Rule::type_fun | Rule::kind => self.join_children(pair, " -> ", indent),
+            Rule::type_tyapp | Rule::type_qualified | Rule::predicate_qualified => {
+                self.join_children(pair, " ", indent)
+            }
+            Rule::predicate | Rule::predicate_kind | Rule::pattern_var => {
+                self.join_children(pair, " : ", indent)
+            }
+            Rule::kind_braced => format!("({})", self.join_children(pair, "", indent)),
+            Rule::type_tuple | Rule::pattern_tuple | Rule::expr_tuple | Rule::arg_list => {
+                let items = pair.into_inner().map(|p| vec![p]).collect();
+                self.fmt_list(("(", ")"), false, items, end, indent)
+            }
+            Rule::predicates | Rule::expr_array_lit => {
+                let items = pair.into_inner().map(|p| vec![p]).collect();
+                self.fmt_list(("[", "]"), false, items, end, indent)
+            }
+            Rule::pattern_struct | Rule::expr_make_struct => {
+                let mut pairs = pair.into_inner();
+                let tycon = self.child(pairs.next().unwrap(), indent);
+                let mut fields = vec![];
+                while let Some(field_name) = pairs.next() {
+                    fields.push(vec![field_name, pairs.next().unwrap()]);
+                }
+                tycon + " " + &self.fmt_list(("{", "}"), true, fields, end, indent)
+            }
+
+            // Expressions.
+            Rule::expr_type_annotation => self.join_children(pair, " : ", indent),
+            Rule::expr_rtl_app => self.join_children(pair, " $ ", indent),
+            Rule::expr_or => self.join_children(pair, " || ", indent),
+            Rule::expr_and => self.join_children(pair, " && ", indent),
+            Rule::expr_cmp | Rule::expr_plus | Rule::expr_mul | Rule::expr_composition => {
+                self.join_children(pair, " ", indent)
+            }
+            Rule::expr_unary | Rule::expr_bind | Rule::expr_app => {
+                self.join_children(pair, "", indent)
+            }
+            Rule::expr_ltr_app => {
+                let strs = pair
+                    .into_inner()
+                    .map(|p| self.child(p, indent + 1))
+                    .collect::<Vec<_>>();
+                let flat = strs.join(".");
+                if fits(&flat, indent) || !strs.iter().all(|s| is_flat(s)) {
+                    flat
+                } else {
+                    // Break a long chain of function applications.
+                    let sep = format!("\n{}.", INDENT);
+                    strs.join(sep.as_str())
+                }
+            }
+            Rule::expr_let => {
+                let mut pairs = pair.into_inner();
+                let pattern = self.child(pairs.next().unwrap(), indent);
+                let eq = self.child(pairs.next().unwrap(), indent);
+                let bound = self.fmt_hanging(pairs.next().unwrap(), indent);
+                let in_of_let = pairs.next().unwrap();
+                let is_semicolon = in_of_let.as_str() == ";";
+                let in_of_let = self.child(in_of_let, indent);
+                let mut res = format!("let {} {}{}", pattern, eq, bound);
+                if is_semicolon {
+                    res += &in_of_let;
+                    res += &self.fmt_following(pairs.next().unwrap(), indent);
+                } else {
+                    res += " ";
+                    res += &in_of_let;
+                    res += &self.fmt_hanging(pairs.next().unwrap(), indent);
+                }
+                res
+            }
+            Rule::expr_eval => {
+                let mut pairs = pair.into_inner();
+                let expr = self.child(pairs.next().unwrap(), indent);
+                let body = self.fmt_following(pairs.next().unwrap(), indent);
+                format!("eval {};{}", expr, body)
+            }
+            Rule::expr_if => self.fmt_if(pair, indent),
+            Rule::expr_do => {
+                let expr = self.join_children(pair, "", indent + 1);
+                if fits(&expr, indent) {
+                    format!("do {{ {} }}", expr)
+                } else {
+                    format!("do {{\n{}{}\n}}", INDENT, shift(&expr))
+                }
+            }
+            Rule::expr_lam => {
+                let mut pairs = pair.into_inner().collect::<Vec<_>>();
+                let body = pairs.pop().unwrap();
+                let params = pairs
+                    .into_iter()
+                    .map(|p| self.child(p, indent))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("|{}|{}", params, self.fmt_hanging(body, indent))
+            }
+            Rule::expr_call_c => {
+                let mut pairs = pair.into_inner().peekable();
+                let ret_ty = self.child(pairs.next().unwrap(), indent);
+                let fun_name = self.child(pairs.next().unwrap(), indent);
+                let mut params = self.join_children(pairs.next().unwrap(), ", ", indent);
+                if pairs.peek().map(|p| p.as_rule()) == Some(Rule::ffi_var_args) {
+                    params += ", ";
+                    params += &self.child(pairs.next().unwrap(), indent);
+                }
+                let mut res = format!("CALL_C[{} {}({})", ret_ty, fun_name, params);
+                for arg in pairs {
+                    res += ", ";
+                    res += &self.child(arg, indent);
+                }
+                res + "]"
+            }
+
+            // Names, literals and operators.
+            _ => self.verbatim(pair),
+        }
+    }
+
+    // Format the body of `let` or `eval` written after a semicolon, which is placed on the next line.
+    fn fmt_following(&mut self, pair: Pair<'a, Rule>, indent: usize) -> String {
+        let mut res = String::default();
+        let mut first = false;
+        let newlines = self.comment_lines(pair.as_span().start(), &mut res, &mut first);
+        res += "\n";
+        if newlines >= 2 {
+            res += "\n";
+        }
+        res + &self.fmt_node(pair, indent)
+    }
+
+    fn fmt_if(&mut self, pair: Pair<'a, Rule>, indent: usize) -> String {
+        let mut pairs = pair.into_inner();
+        let (cond, then_expr, else_expr) = (
+            pairs.next().unwrap(),
+            pairs.next().unwrap(),
+            pairs.next().unwrap(),
+        );
+
+        // Detect the syntax of else-part: `else { e }`, `else e`, `; { e }` or `; e`.
+        let between =
+            strip_comments(&self.src[then_expr.as_span().end()..else_expr.as_span().start()]);
+        let between = between.trim_start().trim_start_matches('}').trim_start();
+        let is_semicolon = between.starts_with(';');
+        let else_keyword = if is_semicolon { ";" } else { " else" };
+        let is_braced = between
+            .trim_start_matches(';')
+            .trim_start_matches("else")
+            .trim_start()
+            .starts_with('{');
+
+        let cond = self.child(cond, indent);
+        let then_expr = self.child(then_expr, indent + 1);
+        let then_flat = format!("if {} {{ {} }}", cond, then_expr);
+        let then_part = if fits(&then_flat, indent) {
+            then_flat
+        } else {
+            format!("if {} {{\n{}{}\n}}", cond, INDENT, shift(&then_expr))
+        };
+
+        if is_semicolon && !is_braced {
+            return then_part + ";" + &self.fmt_following(else_expr, indent);
+        }
+        if !is_braced {
+            let else_expr = self.child(else_expr, indent);
+            return then_part + else_keyword + " " + &else_expr;
+        }
+        let else_expr = self.child(else_expr, indent + 1);
+        let flat = format!("{}{} {{ {} }}", then_part, else_keyword, else_expr);
+        if fits(&flat, indent) {
+            flat
+        } else {
+            format!(
+                "{}{} {{\n{}{}\n}}",
+                then_part,
+                else_keyword,
+                INDENT,
+                shift(&else_expr)
+            )
+        }
+    }
+}
@@ -18,7 +18,7 @@ use inkwell::{
     intrinsics::Intrinsic,
     module::Linkage,
     targets::{TargetData, TargetMachine},
-    types::{AnyType, BasicMetadataTypeEnum, BasicType},
+    types::{AnyType, BasicType},
     values::{BasicMetadataValueEnum, CallSiteValue, StructValue},
 };
 
@@ -431,6 +431,23 @@ impl<'c, 'm> GenerationContext<'c, 'm> {
         ret
     }
 
+    // The target triple of the generated module, or the host's one if it is not set (e.g., in JIT).
+    pub fn target_triple(&self) -> String {
+        let triple = self
+            .module
+            .get_triple()
+            .as_str()
+            .to_string_lossy()
+            .to_string();
+        if !triple.is_empty() {
+            return triple;
+        }
+        TargetMachine::get_default_triple()
+            .as_str()
+            .to_string_lossy()
+            .to_string()
+    }
+
     // Create debug info builders and compilation units.
     pub fn create_debug_info(&mut self) {
         let debug_metadata_version = self.context.i32_type().const_int(3, false);
@@ -1872,35 +1889,6 @@ impl<'c, 'm> GenerationContext<'c, 'm> {
             allocate_obj(ret_ty.clone(), &vec![], None, self, Some("allocate_CallC"))
         };
 
-        // Get c function
-        let c_fun = match self.module.get_function(&fun_name) {
-            Some(fun) => fun,
-            None => {
-                let ret_c_ty = ret_ty.get_c_type(self.context);
-                let parm_c_tys: Vec<BasicMetadataTypeEnum> = param_tys
-                    .iter()
-                    .map(|param_ty| {
-                        let c_type = param_ty.get_c_type(self.context);
-                        if c_type.is_none() {
-                            error_exit_with_src(
-                                "Cannot use `()` as a parameter type of C function.",
-                                &expr.source,
-                            )
-                        }
-                        c_type.unwrap().into()
-                    })
-                    .collect::<Vec<_>>();
-                let fn_ty = match ret_c_ty {
-                    None => {
-                        // Void case.
-                        self.context.void_type().fn_type(&parm_c_tys, is_va_args)
-                    }
-                    Some(ret_c_ty) => ret_c_ty.fn_type(&parm_c_tys, is_va_args),
-                };
-                self.module.add_function(&fun_name, fn_ty, None)
-            }
-        };
-
         // Evaluate arguments
         let mut arg_objs = vec![];
         for i in 0..args.len() {
@@ -1911,20 +1899,26 @@ impl<'c, 'm> GenerationContext<'c, 'm> {
             arg_objs.push(self.eval_expr(args[i].clone(), None));
         }
 
-        // Get argment values
-        let args_vals = arg_objs
-            .iter()
-            .map(|obj| obj.load_field_nocap(self, 0).into())
-            .collect::<Vec<_>>();
+        // Get c function
+        let abi = CFunctionAbi::new(ret_ty, param_tys, is_va_args, self, &expr.source);
+        let c_fun: CallableValue = if fun_name == CALL_C_INDIRECT_NAME {
+            // Call the function pointer given as the first argument.
+            let fun_ptr = arg_objs.remove(0).load_field_nocap(self, 0);
+            let fn_ty = abi.function_type(self);
+            let fun_ptr = self.cast_pointer(
+                fun_ptr.into_pointer_value(),
+                fn_ty.ptr_type(AddressSpace::from(0)),
+            );
+            CallableValue::try_from(fun_ptr).unwrap()
+        } else {
+            match self.module.get_function(&fun_name) {
+                Some(fun) => fun.into(),
+                None => abi.declare_function(&fun_name, self).into(),
+            }
+        };
 
         // Call c function
-        let ret_c_val =
-            self.builder()
-                .build_call(c_fun, &args_vals, &format!("CALL_C({})", fun_name));
-        match ret_c_val.try_as_basic_value() {
-            Either::Left(ret_c_val) => obj.store_field_nocap(self, 0, ret_c_val),
-            Either::Right(_) => {}
-        }
+        abi.build_call(c_fun, fun_name, &arg_objs, &obj, self);
 
         obj
     }
@@ -0,0 +1,116 @@
+// The Fix compiler as a library.
+// The stable API for tools written in Rust is defined in `api.rs` and re-exported from the crate root; other modules are internal.
+
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
+#[allow(unused_imports)]
+#[macro_use]
+extern crate serial_test;
+extern crate build_time;
+extern crate chrono;
+extern crate num_bigint;
+extern crate rand;
+extern crate regex;
+extern crate serde;
+extern crate serde_json;
+extern crate serde_pickle;
+
+mod api;
+mod ast;
+mod bindgen;
+mod borrowing_optimization;
+mod builtin;
+mod c_abi;
+mod cli;
+mod codegen_units;
+mod configuration;
+mod constants;
+mod docgen;
+mod dump;
+mod formatter;
+mod generator;
+mod graph;
+mod llvm_passes;
+mod lsp;
+mod misc;
+mod object;
+mod parser;
+mod pgo;
+mod repl;
+mod runner;
+mod runtime;
+mod sourcefile;
+mod stdlib;
+mod test_runner;
+#[cfg(test)]
+mod tests;
+mod time_passes;
+mod toolchain;
+mod type_check_cache;
+mod typecheck;
+mod uncurry_optimization;
+
+pub use api::*;
+use ast::export::*;
+use ast::expr::*;
+use ast::import::*;
+use ast::inline_llvm::*;
+use ast::name::*;
+use ast::pattern::*;
+use ast::program::*;
+use ast::traits::*;
+use ast::typedecl::*;
+use ast::types::*;
+use bindgen::*;
+use borrowing_optimization::*;
+use builtin::*;
+use c_abi::*;
+use codegen_units::*;
+use configuration::*;
+use constants::*;
+use docgen::*;
+use dump::*;
+use formatter::*;
+use generator::*;
+use graph::*;
+use inkwell::builder::Builder;
+use inkwell::context::Context;
+use inkwell::module::Module;
+use inkwell::passes::PassManager;
+use inkwell::support::load_library_permanently;
+use inkwell::types::{BasicTypeEnum, FunctionType, IntType, PointerType, StructType};
+use inkwell::values::{
+    BasicValue, BasicValueEnum, CallableValue, FunctionValue, IntValue, PointerValue,
+};
+use inkwell::{AddressSpace, IntPredicate, OptimizationLevel};
+use llvm_passes::*;
+use lsp::*;
+use misc::*;
+use object::*;
+use parser::*;
+use pest::iterators::{Pair, Pairs};
+use pest::Parser;
+use pgo::*;
+use repl::*;
+use runner::*;
+use runtime::*;
+use sourcefile::*;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::vec::Vec;
+use stdlib::*;
+use test_runner::*;
+use time_passes::*;
+use toolchain::*;
+use type_check_cache::*;
+use typecheck::*;
+use uncurry_optimization::*;
+
+// The entry point of the command line interface `fix`.
+#[doc(hidden)]
+pub use cli::cli_main;
@@ -1,9 +1,15 @@
 // This source file is generated by by passes_optimizer.py.
 
 use super::*;
-use inkwell::passes::PassManagerSubType;
 
-pub fn add_passes<T: PassManagerSubType>(passmgr: &PassManager<T>) {
+// The passes are run in groups in this order, and `--time-passes` reports the duration of each group.
+pub const PASS_GROUPS: [(&str, fn(&PassManager<Module>)); 3] = [
+    ("llvm_passes_early", add_passes_early),
+    ("llvm_passes_middle", add_passes_middle),
+    ("llvm_passes_late", add_passes_late),
+];
+
+pub fn add_passes_early(passmgr: &PassManager<Module>) {
     passmgr.add_early_cse_pass();
     passmgr.add_scalar_repl_aggregates_pass_ssa();
     passmgr.add_global_optimizer_pass();
@@ -32,6 +38,9 @@ pub fn add_passes<T: PassManagerSubType>(passmgr: &PassManager<T>) {
     passmgr.add_always_inliner_pass();
     passmgr.add_strip_dead_prototypes_pass();
     passmgr.add_scoped_no_alias_aa_pass();
+}
+
+pub fn add_passes_middle(passmgr: &PassManager<Module>) {
     passmgr.add_slp_vectorize_pass();
     passmgr.add_sccp_pass();
     passmgr.add_dead_store_elimination_pass();
@@ -60,6 +69,9 @@ pub fn add_passes<T: PassManagerSubType>(passmgr: &PassManager<T>) {
     passmgr.add_strip_dead_prototypes_pass();
     passmgr.add_type_based_alias_analysis_pass();
     passmgr.add_ind_var_simplify_pass();
+}
+
+pub fn add_passes_late(passmgr: &PassManager<Module>) {
     passmgr.add_strip_dead_prototypes_pass();
     passmgr.add_global_optimizer_pass();
     passmgr.add_promote_memory_to_register_pass();
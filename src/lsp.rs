@@ -0,0 +1,743 @@
+// Language server of Fix.
+// It communicates with an editor by Language Server Protocol (JSON-RPC over stdio) and provides
+// diagnostics, hover, go-to-definition and completion.
+
+use super::*;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+
+// Values of `CompletionItemKind` in LSP.
+const COMPLETION_ITEM_KIND_FUNCTION: i64 = 3;
+const COMPLETION_ITEM_KIND_INTERFACE: i64 = 8;
+const COMPLETION_ITEM_KIND_MODULE: i64 = 9;
+const COMPLETION_ITEM_KIND_STRUCT: i64 = 22;
+
+// A document opened in the editor.
+struct Document {
+    path: PathBuf,
+    text: String,
+    // The result of parsing `text`, which is reused until the document is changed.
+    parsed: Result<Program, CompileError>,
+}
+
+impl Document {
+    fn new(path: PathBuf, text: String) -> Self {
+        let src = SourceFile::from_string(path.clone(), text.clone());
+        let parsed = catch_compile_error(|| parse_source_file(src));
+        Document { path, text, parsed }
+    }
+}
+
+// The result of the latest analysis, which is used to answer requests from the editor.
+struct Analysis {
+    program: Program,
+    typed_exprs: Vec<TypedExpr>,
+}
+
+struct LanguageServer<W: Write> {
+    // The stream of messages to the editor.
+    writer: W,
+    // Opened documents indexed by URI.
+    documents: HashMap<String, Document>,
+    // The standard library, which is built once and linked with the documents in each analysis.
+    // Its type check cache is kept in memory and shared by all analyses, so that global values in unchanged modules are not type-checked again.
+    std_mod: Program,
+    analysis: Option<Analysis>,
+    shutdown_requested: bool,
+}
+
+// A function implementing `fix lsp` command.
+pub fn lsp_command() {
+    // Compile errors are reported to the editor as diagnostics, so they should not terminate the server.
+    enable_catching_compile_errors();
+
+    let stdin = std::io::stdin();
+    let exit_code = run_language_server(&mut stdin.lock(), std::io::stdout());
+    std::process::exit(exit_code);
+}
+
+// Serve an editor which sends messages to `reader` and receives messages from `writer`.
+// Returns the exit code when the editor sends `exit` or closes the input.
+pub fn run_language_server(reader: &mut impl BufRead, writer: impl Write) -> i32 {
+    let mut server = LanguageServer::new(writer);
+    while let Some(msg) = read_message(reader) {
+        if let Some(exit_code) = server.handle_message(msg) {
+            return exit_code;
+        }
+    }
+    0
+}
+
+// Read a message from the editor. Returns None when the input is closed.
+pub(crate) fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    loop {
+        let mut content_length: Option<usize> = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                if key.trim().eq_ignore_ascii_case("Content-Length") {
+                    content_length = value.trim().parse().ok();
+                }
+            }
+        }
+        let content_length = match content_length {
+            Some(len) => len,
+            None => {
+                eprintln!("warning: Received a message without Content-Length header.");
+                continue;
+            }
+        };
+        let mut content = vec![0; content_length];
+        reader.read_exact(&mut content).ok()?;
+        match serde_json::from_slice(&content) {
+            Ok(msg) => return Some(msg),
+            Err(e) => {
+                eprintln!("warning: Failed to parse a message: {}.", e);
+            }
+        }
+    }
+}
+
+impl<W: Write> LanguageServer<W> {
+    fn new(writer: W) -> Self {
+        let mut std_mod = make_std_mod();
+        // The type check cache should not be written to the working directory of the editor.
+        std_mod.type_check_cache = Rc::new(RefCell::new(TypeCheckCache::in_memory()));
+        LanguageServer {
+            writer,
+            documents: Default::default(),
+            std_mod,
+            analysis: None,
+            shutdown_requested: false,
+        }
+    }
+
+    fn send_message(&mut self, msg: &Value) {
+        let content = msg.to_string();
+        let _ = write!(
+            self.writer,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        );
+        let _ = self.writer.flush();
+    }
+
+    fn send_response(&mut self, id: Value, result: Value) {
+        self.send_message(&json!({"jsonrpc": "2.0", "id": id, "result": result}));
+    }
+
+    fn send_error_response(&mut self, id: Value, code: i64, msg: &str) {
+        self.send_message(
+            &json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": msg}}),
+        );
+    }
+
+    fn send_notification(&mut self, method: &str, params: Value) {
+        self.send_message(&json!({"jsonrpc": "2.0", "method": method, "params": params}));
+    }
+
+    // Handle a message from the editor. Returns the exit code if the server should exit.
+    fn handle_message(&mut self, msg: Value) -> Option<i32> {
+        let method = match msg["method"].as_str() {
+            Some(method) => method,
+            None => return None, // Responses from the editor are not used.
+        };
+        let id = msg.get("id").cloned();
+        let params = &msg["params"];
+        match (method, id) {
+            ("initialize", Some(id)) => self.send_response(
+                id,
+                json!({
+                    "capabilities": {
+                        "textDocumentSync": 1, // Full synchronization.
+                        "hoverProvider": true,
+                        "definitionProvider": true,
+                        "completionProvider": { "triggerCharacters": [":", "."] },
+                    },
+                    "serverInfo": { "name": "fix" },
+                }),
+            ),
+            ("shutdown", Some(id)) => {
+                self.shutdown_requested = true;
+                self.send_response(id, Value::Null);
+            }
+            ("exit", _) => return Some(if self.shutdown_requested { 0 } else { 1 }),
+            ("textDocument/didOpen", _) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(
+                    uri.to_string(),
+                    Document::new(uri_to_path(uri), text.to_string()),
+                );
+                self.analyze();
+            }
+            ("textDocument/didChange", _) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                // Since we requested full synchronization, the last change contains the whole text.
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let (Some(doc), Some(text)) = (self.documents.get_mut(uri), text) {
+                    *doc = Document::new(doc.path.clone(), text.to_string());
+                    self.analyze();
+                }
+            }
+            ("textDocument/didClose", _) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                if self.documents.remove(uri).is_some() {
+                    self.send_notification(
+                        "textDocument/publishDiagnostics",
+                        json!({"uri": uri, "diagnostics": []}),
+                    );
+                    self.analyze();
+                }
+            }
+            ("textDocument/hover", Some(id)) => {
+                let result = self.hover(params);
+                self.send_response(id, result)
+            }
+            ("textDocument/definition", Some(id)) => {
+                let result = self.definition(params);
+                self.send_response(id, result)
+            }
+            ("textDocument/completion", Some(id)) => {
+                let result = self.completion(params);
+                self.send_response(id, result)
+            }
+            (_, Some(id)) => {
+                self.send_error_response(id, -32601, &format!("Method not found: {}", method))
+            }
+            (_, None) => {} // Ignore unknown notifications.
+        }
+        None
+    }
+
+    // Link all opened documents with the standard library, type-check them, and publish diagnostics.
+    // Only the changed document is parsed again, and global values whose dependent codes are unchanged are not type-checked again.
+    fn analyze(&mut self) {
+        let mut errors: Vec<CompileError> = vec![];
+
+        // Link the parsed documents.
+        let mut uris = self.documents.keys().cloned().collect::<Vec<_>>();
+        uris.sort();
+        let mut program = self.std_mod.clone();
+        let mut source_files = vec![];
+        for uri in &uris {
+            let doc = &self.documents[uri];
+            let parsed = match &doc.parsed {
+                Ok(parsed) => parsed.clone(),
+                Err(err) => {
+                    errors.push(err.clone());
+                    continue;
+                }
+            };
+            match catch_compile_error(|| program.link(parsed)) {
+                Ok(_) => source_files.push(doc.path.clone()),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        // Run front-end phases of the compiler.
+        let mut config = Configuration::release();
+        config.source_files = source_files;
+        let frontend = catch_compile_error(|| {
+            program.resolve_imports(&mut config);
+            run_frontend(&mut program)
+        });
+
+        // Type-check global values defined in the documents one by one, so that we can report as many errors as possible.
+        match frontend {
+            Ok(typechecker) => {
+                let modules = program.modules_of_files(&config.source_files);
+                let mut names = program.global_values.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                let mut typed_exprs = vec![];
+                for name in names {
+                    match catch_compile_error(|| {
+                        program.check_type_of_global_value(&name, &modules, &typechecker)
+                    }) {
+                        Ok(mut exprs) => typed_exprs.append(&mut exprs),
+                        Err(err) => errors.push(err),
+                    }
+                }
+                self.analysis = Some(Analysis {
+                    program,
+                    typed_exprs,
+                });
+            }
+            Err(err) => {
+                // Do not answer hovers or go-to-definition by the analysis of old documents.
+                self.analysis = None;
+                errors.push(err);
+            }
+        }
+
+        self.publish_diagnostics(&uris, errors);
+    }
+
+    fn publish_diagnostics(&mut self, uris: &[String], errors: Vec<CompileError>) {
+        let mut diagnostics: HashMap<&String, Vec<Value>> = Default::default();
+        for uri in uris {
+            diagnostics.insert(uri, vec![]);
+        }
+        for err in errors {
+            // Report the error in the document where it is located.
+            // Errors located outside the opened documents are reported at the beginning of the first document.
+            let located_uri = err
+                .srcs
+                .first()
+                .and_then(|src| self.uri_of_path(&src.input.file_path));
+            let uri = match located_uri.or(uris.first()) {
+                Some(uri) => uri,
+                None => continue,
+            };
+            let range = match err.srcs.first() {
+                Some(src) if located_uri.is_some() => span_to_range(src),
+                _ => {
+                    json!({"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 0}})
+                }
+            };
+            let mut msg = err.msg.clone();
+            let mut related = vec![];
+            for src in err.srcs.iter().skip(1) {
+                related.push(
+                    json!({"location": span_to_location(src), "message": "Related location."}),
+                );
+            }
+            for (note, note_src) in &err.notes {
+                match note_src {
+                    Some(src) => {
+                        related.push(json!({"location": span_to_location(src), "message": note}))
+                    }
+                    None => {
+                        msg += "\nnote: ";
+                        msg += note;
+                    }
+                }
+            }
+            diagnostics.get_mut(uri).unwrap().push(json!({
+                "range": range,
+                "severity": 1,
+                "source": "fix",
+                "message": msg,
+                "relatedInformation": related,
+            }));
+        }
+        let notifications = diagnostics
+            .into_iter()
+            .map(|(uri, diagnostics)| json!({"uri": uri, "diagnostics": diagnostics}))
+            .collect::<Vec<_>>();
+        for params in notifications {
+            self.send_notification("textDocument/publishDiagnostics", params);
+        }
+    }
+
+    fn uri_of_path(&self, path: &PathBuf) -> Option<&String> {
+        self.documents
+            .iter()
+            .find(|(_, doc)| &doc.path == path)
+            .map(|(uri, _)| uri)
+    }
+
+    // Get the document and the byte offset specified by `TextDocumentPositionParams`.
+    fn document_position(&self, params: &Value) -> Option<(&Document, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let doc = self.documents.get(uri)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        Some((doc, position_to_offset(&doc.text, line, character)))
+    }
+
+    // Find the smallest expression at the given position.
+    fn expr_at(&self, path: &PathBuf, offset: usize) -> Option<(Rc<ExprNode>, &TypedExpr)> {
+        let analysis = self.analysis.as_ref()?;
+        let mut found: Option<(Rc<ExprNode>, &TypedExpr)> = None;
+        for te in &analysis.typed_exprs {
+            visit_expr(&te.expr, &mut |expr| {
+                let src = match &expr.source {
+                    Some(src) => src,
+                    None => return,
+                };
+                if &src.input.file_path != path || offset < src.start || src.end < offset {
+                    return;
+                }
+                let is_smaller = match &found {
+                    None => true,
+                    Some((e, _)) => {
+                        let found_src = e.source.as_ref().unwrap();
+                        src.end - src.start < found_src.end - found_src.start
+                    }
+                };
+                if is_smaller {
+                    found = Some((expr.clone(), te));
+                }
+            });
+        }
+        found
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let (doc, offset) = match self.document_position(params) {
+            Some(res) => res,
+            None => return Value::Null,
+        };
+        let (expr, te) = match self.expr_at(&doc.path, offset) {
+            Some(res) => res,
+            None => return Value::Null,
+        };
+        let ty = match &expr.ty {
+            Some(ty) => te.type_resolver.substitute_type(ty).to_string_normalize(),
+            None => return Value::Null,
+        };
+        let content = match &*expr.expr {
+            Expr::Var(var) => format!("{} : {}", var.name.to_string(), ty),
+            _ => ty,
+        };
+        json!({
+            "contents": { "kind": "markdown", "value": format!("```\n{}\n```", content) },
+            "range": span_to_range(expr.source.as_ref().unwrap()),
+        })
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let (doc, offset) = match self.document_position(params) {
+            Some(res) => res,
+            None => return Value::Null,
+        };
+        let analysis = match &self.analysis {
+            Some(analysis) => analysis,
+            None => return Value::Null,
+        };
+
+        // If the cursor is on a global value in an expression, jump to its definition.
+        if let Some((expr, _)) = self.expr_at(&doc.path, offset) {
+            if let Expr::Var(var) = &*expr.expr {
+                if var.name.is_global() {
+                    return match analysis.definition_source(&var.name) {
+                        Some(src) => span_to_location(&src),
+                        None => Value::Null,
+                    };
+                }
+            }
+        }
+
+        // Otherwise, search a type, a trait or a global value by the name at the cursor.
+        let word = name_at(&doc.text, offset);
+        let name = match FullName::parse(&word) {
+            Some(name) if !name.name.is_empty() => name,
+            _ => return Value::Null,
+        };
+        let mut candidates = analysis.definition_candidates(&name);
+        candidates.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        match candidates.first() {
+            Some((_, src)) => span_to_location(src),
+            None => Value::Null,
+        }
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let (doc, offset) = match self.document_position(params) {
+            Some(res) => res,
+            None => return Value::Null,
+        };
+        let analysis = match &self.analysis {
+            Some(analysis) => analysis,
+            None => return Value::Null,
+        };
+
+        // Split the name before the cursor into the namespace and the prefix of the member.
+        let start = name_start(&doc.text, offset);
+        let word = &doc.text[start..offset];
+        let (namespace, prefix) = match word.rfind(NAMESPACE_SEPARATOR) {
+            Some(pos) => (
+                NameSpace::new(
+                    word[..pos]
+                        .split(NAMESPACE_SEPARATOR)
+                        .map(|s| s.to_string())
+                        .collect(),
+                ),
+                &word[pos + NAMESPACE_SEPARATOR.len()..],
+            ),
+            None => (NameSpace::local(), word),
+        };
+
+        let mut items: HashMap<String, Value> = Default::default();
+        let mut add_item = |label: &str, kind: i64, detail: String| {
+            if !label.starts_with(prefix) || items.contains_key(label) {
+                return;
+            }
+            items.insert(
+                label.to_string(),
+                json!({"label": label, "kind": kind, "detail": detail}),
+            );
+        };
+        let mut add_namespace_members = |name: &FullName, kind: i64, detail: String| {
+            if namespace.is_suffix(&name.namespace) {
+                add_item(&name.name, kind, detail);
+            }
+            // Namespaces nested in the given namespace are also members.
+            if namespace.is_local() {
+                return;
+            }
+            for i in 0..name.namespace.names.len() {
+                let parent = NameSpace::new(name.namespace.names[..i].to_vec());
+                if !parent.is_local() && namespace.is_suffix(&parent) {
+                    let child = &name.namespace.names[i];
+                    add_item(
+                        child,
+                        COMPLETION_ITEM_KIND_MODULE,
+                        parent.to_string() + NAMESPACE_SEPARATOR + child,
+                    );
+                }
+            }
+        };
+        let program = &analysis.program;
+        for (name, gv) in &program.global_values {
+            add_namespace_members(name, COMPLETION_ITEM_KIND_FUNCTION, gv.scm.to_string());
+        }
+        for (tycon, _) in program.type_env().tycons.iter() {
+            add_namespace_members(
+                &tycon.name,
+                COMPLETION_ITEM_KIND_STRUCT,
+                tycon.name.to_string(),
+            );
+        }
+        for (tycon, _) in program.type_env().aliases.iter() {
+            add_namespace_members(
+                &tycon.name,
+                COMPLETION_ITEM_KIND_STRUCT,
+                tycon.name.to_string(),
+            );
+        }
+        for (trait_id, _) in &program.trait_env.traits {
+            add_namespace_members(
+                &trait_id.name,
+                COMPLETION_ITEM_KIND_INTERFACE,
+                trait_id.name.to_string(),
+            );
+        }
+
+        let mut items = items.into_iter().collect::<Vec<_>>();
+        items.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        json!(items.into_iter().map(|(_, item)| item).collect::<Vec<_>>())
+    }
+}
+
+impl Analysis {
+    // Get the source location where a global value is defined.
+    fn definition_source(&self, name: &FullName) -> Option<Span> {
+        match &self.program.global_values.get(name)?.expr {
+            SymbolExpr::Simple(e) => e.expr.source.clone(),
+            SymbolExpr::Method(_) => {
+                // A method is defined in the trait definition.
+                let trait_name = FullName::parse(&name.namespace.to_string())?;
+                let trait_info = self
+                    .program
+                    .trait_env
+                    .traits
+                    .get(&TraitId::from_fullname(trait_name))?;
+                trait_info.source.clone()
+            }
+        }
+    }
+
+    // Find global values, types and traits which have the given name as a suffix, with their sources.
+    fn definition_candidates(&self, name: &FullName) -> Vec<(FullName, Span)> {
+        let mut candidates = vec![];
+        let program = &self.program;
+        for (fullname, _) in &program.global_values {
+            if name.is_suffix(fullname) {
+                if let Some(src) = self.definition_source(fullname) {
+                    candidates.push((fullname.clone(), src));
+                }
+            }
+        }
+        for (tycon, info) in program.type_env().tycons.iter() {
+            if name.is_suffix(&tycon.name) {
+                if let Some(src) = &info.source {
+                    candidates.push((tycon.name.clone(), src.clone()));
+                }
+            }
+        }
+        for (tycon, info) in program.type_env().aliases.iter() {
+            if name.is_suffix(&tycon.name) {
+                if let Some(src) = &info.source {
+                    candidates.push((tycon.name.clone(), src.clone()));
+                }
+            }
+        }
+        for (trait_id, info) in &program.trait_env.traits {
+            if name.is_suffix(&trait_id.name) {
+                if let Some(src) = &info.source {
+                    candidates.push((trait_id.name.clone(), src.clone()));
+                }
+            }
+        }
+        candidates
+    }
+}
+
+// Call `f` for all subexpressions of `expr`.
+fn visit_expr(expr: &Rc<ExprNode>, f: &mut impl FnMut(&Rc<ExprNode>)) {
+    f(expr);
+    match &*expr.expr {
+        Expr::Var(_) => {}
+        Expr::LLVM(_) => {}
+        Expr::App(fun, args) => {
+            visit_expr(fun, f);
+            for arg in args {
+                visit_expr(arg, f);
+            }
+        }
+        Expr::Lam(_, body) => visit_expr(body, f),
+        Expr::Let(_, bound, val) => {
+            visit_expr(bound, f);
+            visit_expr(val, f);
+        }
+        Expr::If(cond, then_expr, else_expr) => {
+            visit_expr(cond, f);
+            visit_expr(then_expr, f);
+            visit_expr(else_expr, f);
+        }
+        Expr::TyAnno(e, _) => visit_expr(e, f),
+        Expr::ArrayLit(elems) => {
+            for elem in elems {
+                visit_expr(elem, f);
+            }
+        }
+        Expr::MakeStruct(_, fields) => {
+            for (_, field) in fields {
+                visit_expr(field, f);
+            }
+        }
+        Expr::CallC(_, _, _, _, args) => {
+            for arg in args {
+                visit_expr(arg, f);
+            }
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == ':' || c == '.'
+}
+
+// Get the start offset of the (namespaced) name which ends at `offset`.
+fn name_start(text: &str, offset: usize) -> usize {
+    let mut start = offset;
+    for (i, c) in text[..offset].char_indices().rev() {
+        if !is_name_char(c) {
+            break;
+        }
+        start = i;
+    }
+    start
+}
+
+// Get the (namespaced) name at `offset`.
+fn name_at(text: &str, offset: usize) -> String {
+    let start = name_start(text, offset);
+    let end = text[offset..]
+        .char_indices()
+        .find(|(_, c)| !is_name_char(*c))
+        .map_or(text.len(), |(i, _)| offset + i);
+    text[start..end].trim_matches(':').to_string()
+}
+
+// Convert a byte offset to LSP's position, whose character is counted in UTF-16 code units.
+fn offset_to_position(text: &str, offset: usize) -> Value {
+    let mut line = 0;
+    let mut character = 0;
+    for (i, c) in text.char_indices() {
+        if i >= offset {
+            break;
+        }
+        if c == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += c.len_utf16();
+        }
+    }
+    json!({"line": line, "character": character})
+}
+
+// Convert LSP's position to a byte offset.
+fn position_to_offset(text: &str, line: usize, character: usize) -> usize {
+    let mut cur_line = 0;
+    let mut cur_character = 0;
+    for (i, c) in text.char_indices() {
+        if cur_line == line && (cur_character >= character || c == '\n') {
+            return i;
+        }
+        if c == '\n' {
+            cur_line += 1;
+            cur_character = 0;
+        } else {
+            cur_character += c.len_utf16();
+        }
+    }
+    text.len()
+}
+
+fn span_to_range(span: &Span) -> Value {
+    let text = span.input.string();
+    let end = span.end.min(text.len());
+    let start = span.start.min(end);
+    json!({
+        "start": offset_to_position(&text, start),
+        "end": offset_to_position(&text, end),
+    })
+}
+
+fn span_to_location(span: &Span) -> Value {
+    json!({"uri": path_to_uri(&span.input.file_path), "range": span_to_range(span)})
+}
+
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    // Decode percent-encoded characters.
+    let bytes = path.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).to_string())
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut uri = "file://".to_string();
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri += &format!("%{:02X}", b);
+        }
+    }
+    uri
+}
@@ -1,40 +1,155 @@
 use super::*;
-use std::{cell::Cell, fs, process, time::Instant};
+use std::{
+    cell::{Cell, RefCell},
+    fs, process,
+    time::Instant,
+};
 
-pub fn error_exit(msg: &str) -> ! {
-    eprintln!("error: {}", msg);
-    process::exit(1)
+// An error detected by the compiler.
+#[derive(Clone)]
+pub struct CompileError {
+    pub msg: String,
+    pub srcs: Vec<Span>,
+    pub notes: Vec<(String, Option<Span>)>,
 }
 
-pub fn error_exit_with_src(msg: &str, src: &Option<Span>) -> ! {
-    let mut str = String::default();
-    str += msg;
-    str += "\n";
-    match src {
-        None => {}
-        Some(v) => {
+impl CompileError {
+    pub fn to_string(&self) -> String {
+        let mut str = self.msg.clone();
+        if self.srcs.is_empty() && self.notes.is_empty() {
+            return str;
+        }
+        str += "\n";
+        for src in &self.srcs {
             str += "\n";
-            str += &v.to_string();
+            str += &src.to_string();
         }
-    };
-    error_exit(&str)
+        for (note, note_src) in &self.notes {
+            str += "\n";
+            str += "note: ";
+            str += note;
+            str += "\n";
+            match note_src {
+                None => {}
+                Some(v) => {
+                    str += &v.to_string();
+                }
+            }
+        }
+        str
+    }
 }
 
-pub fn error_exit_with_srcs(msg: &str, srcs: &[&Option<Span>]) -> ! {
-    let mut str = String::default();
-    str += msg;
-    str += "\n";
-    for src in srcs {
-        match src {
-            None => {}
-            Some(v) => {
-                str += "\n";
-                str += &v.to_string();
+thread_local! {
+    static PANIC_ON_ERROR: Cell<bool> = Cell::new(false);
+    static RAISED_ERROR: RefCell<Option<CompileError>> = RefCell::new(None);
+}
+
+// The payload of a panic raised by `error_exit` functions when `set_panic_on_error(true)`.
+// The error itself is stored in a thread-local variable since `CompileError` is not `Send`.
+pub struct CompileErrorPanic;
+
+// If set, `error_exit` functions panic instead of terminating the process on the current thread,
+// so that the error can be caught by `catch_compile_error`.
+pub fn set_panic_on_error(flag: bool) {
+    PANIC_ON_ERROR.with(|f| f.set(flag));
+}
+
+// Make compile errors on the current thread catchable by `catch_compile_error`, and suppress the panic messages for them.
+pub fn enable_catching_compile_errors() {
+    set_panic_on_error(true);
+    suppress_compile_error_panic_messages();
+}
+
+// Install a panic hook which does not print messages for panics raised by `error_exit` functions.
+fn suppress_compile_error_panic_messages() {
+    static INSTALL_HOOK: std::sync::Once = std::sync::Once::new();
+    INSTALL_HOOK.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !info.payload().is::<CompileErrorPanic>() {
+                default_hook(info);
             }
+        }));
+    });
+}
+
+// Run `f` with compile errors made catchable, and catch a compile error raised in it.
+// Unlike `catch_compile_error`, this does not require `set_panic_on_error(true)` to be called beforehand.
+pub fn catch_compile_error_in<T>(f: impl FnOnce() -> T) -> Result<T, CompileError> {
+    suppress_compile_error_panic_messages();
+    let prev = PANIC_ON_ERROR.with(|flag| flag.replace(true));
+    let res = catch_compile_error(f);
+    set_panic_on_error(prev);
+    res
+}
+
+// Run `f`, catching a compile error raised in it.
+// Other panics are also caught and reported as internal errors of the compiler.
+pub fn catch_compile_error<T>(f: impl FnOnce() -> T) -> Result<T, CompileError> {
+    let payload = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(res) => return Ok(res),
+        Err(payload) => payload,
+    };
+    if payload.is::<CompileErrorPanic>() {
+        if let Some(err) = RAISED_ERROR.with(|e| e.borrow_mut().take()) {
+            return Err(err);
         }
     }
+    let msg = if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else {
+        "unknown error".to_string()
+    };
+    Err(CompileError {
+        msg: format!("Internal error of the compiler: {}", msg),
+        srcs: vec![],
+        notes: vec![],
+    })
+}
+
+pub fn raise_error(err: CompileError) -> ! {
+    if PANIC_ON_ERROR.with(|f| f.get()) {
+        RAISED_ERROR.with(|e| *e.borrow_mut() = Some(err));
+        std::panic::panic_any(CompileErrorPanic)
+    }
+    eprintln!("error: {}", err.to_string());
+    process::exit(1)
+}
+
+pub fn error_exit(msg: &str) -> ! {
+    raise_error(CompileError {
+        msg: msg.to_string(),
+        srcs: vec![],
+        notes: vec![],
+    })
+}
+
+pub fn error_exit_with_src(msg: &str, src: &Option<Span>) -> ! {
+    error_exit_with_srcs(msg, &[src])
+}
+
+pub fn error_exit_with_srcs(msg: &str, srcs: &[&Option<Span>]) -> ! {
+    raise_error(CompileError {
+        msg: msg.to_string(),
+        srcs: srcs.iter().filter_map(|src| (*src).clone()).collect(),
+        notes: vec![],
+    })
+}
 
-    error_exit(&str)
+// Show an error message with the source, followed by notes which may also have sources.
+pub fn error_exit_with_src_notes(
+    msg: &str,
+    src: &Option<Span>,
+    notes: &[(String, Option<Span>)],
+) -> ! {
+    raise_error(CompileError {
+        msg: msg.to_string(),
+        srcs: src.iter().cloned().collect(),
+        notes: notes.to_vec(),
+    })
 }
 
 pub fn temporary_source_name(file_name: &str, hash: &str) -> String {
@@ -105,14 +105,44 @@ pub fn parse_and_save_to_temporary_file(source: &str, file_name: &str) -> Progra
 }
 
 pub fn parse_file_path(file_path: PathBuf) -> Program {
-    let source = SourceFile::from_file_path(file_path);
+    parse_source_file(SourceFile::from_file_path(file_path))
+}
+
+// Parse a source file whose content may not be saved to the file system, e.g., a document opened in an editor.
+pub fn parse_source_file(source: SourceFile) -> Program {
+    let _timer = time_pass("parse");
+    let source_code = source.string();
+    let file = parse_to_pairs(&source, &source_code);
+    parse_file(file, source)
+}
+
+// Parse the content of a source file into pest's parse tree.
+pub fn parse_to_pairs<'a>(source: &SourceFile, source_code: &'a str) -> Pairs<'a, Rule> {
+    match FixParser::parse(Rule::file, source_code) {
+        Ok(res) => res,
+        Err(e) => {
+            let (msg, span) = message_parse_error(e, source);
+            error_exit_with_src(&msg, &Some(span))
+        }
+    }
+}
+
+// Parse an expression which is written in the given module, e.g., an input of REPL.
+// Returns the expression and the sizes of tuples used in it.
+pub fn parse_expr_source(source: SourceFile, module_name: &Name) -> (Rc<ExprNode>, Vec<u32>) {
     let source_code = source.string();
-    let file = FixParser::parse(Rule::file, &source_code);
-    let file = match file {
+    let mut pairs = match FixParser::parse(Rule::expr_file, &source_code) {
         Ok(res) => res,
-        Err(e) => error_exit(&message_parse_error(e, &source)),
+        Err(e) => {
+            let (msg, span) = message_parse_error(e, &source);
+            error_exit_with_src(&msg, &Some(span))
+        }
     };
-    parse_file(file, source)
+    let mut ctx = ParseContext::from_source(source.clone());
+    ctx.module_name = module_name.clone();
+    ctx.namespace = NameSpace::new(vec![module_name.clone()]);
+    let expr = parse_expr_with_new_do(pairs.next().unwrap(), &mut ctx);
+    (expr, ctx.tuple_sizes)
 }
 
 fn parse_file(mut file: Pairs<Rule>, src: SourceFile) -> Program {
@@ -139,6 +169,7 @@ fn parse_module(pair: Pair<Rule>, src: SourceFile) -> Program {
     let mut trait_aliases: Vec<TraitAlias> = vec![];
     let mut trait_impls: Vec<TraitInstance> = vec![];
     let mut import_statements: Vec<ImportStatement> = vec![];
+    let mut export_statements: Vec<ExportStatement> = vec![];
 
     for pair in pairs {
         match pair.as_rule() {
@@ -157,6 +188,9 @@ fn parse_module(pair: Pair<Rule>, src: SourceFile) -> Program {
             Rule::import_statement => {
                 import_statements.push(parse_import_statement(pair, &mut ctx));
             }
+            Rule::export_statement => {
+                export_statements.push(parse_export_statement(pair, &mut ctx));
+            }
             _ => unreachable!(),
         }
     }
@@ -165,6 +199,7 @@ fn parse_module(pair: Pair<Rule>, src: SourceFile) -> Program {
     fix_mod.add_type_defns(type_defns);
     fix_mod.add_traits(trait_infos, trait_impls, trait_aliases);
     fix_mod.add_import_statements(import_statements);
+    fix_mod.export_statements.append(&mut export_statements);
     fix_mod.used_tuple_sizes.append(&mut ctx.tuple_sizes);
 
     fix_mod
@@ -1209,7 +1244,7 @@ fn parse_expr_call_c(pair: Pair<Rule>, ctx: &mut ParseContext) -> Rc<ExprNode> {
     let span = Span::from_pair(&ctx.source, &pair);
     let mut pairs = pair.into_inner();
     let ret_ty = parse_ffi_c_fun_ty(pairs.next().unwrap());
-    let fun_name = pairs.next().unwrap().as_str().to_string();
+    let fun_name = pairs.next().unwrap().as_str().trim().to_string();
     let param_tys = parse_ffi_param_tys(pairs.next().unwrap());
     let is_var_args =
         if pairs.peek().is_some() && pairs.peek().unwrap().as_rule() == Rule::ffi_var_args {
@@ -1221,7 +1256,13 @@ fn parse_expr_call_c(pair: Pair<Rule>, ctx: &mut ParseContext) -> Rc<ExprNode> {
     let args: Vec<_> = pairs.map(|pair| parse_expr(pair, ctx)).collect();
 
     // Validate number of arguments.
-    if args.len() < param_tys.len() || (!is_var_args && args.len() > param_tys.len()) {
+    // In `CALL_C[R *(A1, ..., An), fun_ptr, a1, ..., an]`, the function pointer is given as the first argument.
+    let param_count = if fun_name == CALL_C_INDIRECT_NAME {
+        param_tys.len() + 1
+    } else {
+        param_tys.len()
+    };
+    if args.len() < param_count || (!is_var_args && args.len() > param_count) {
         error_exit_with_src(
             "Wrong number of arguments in CALL_C expression.",
             &Some(span),
@@ -1236,7 +1277,13 @@ fn parse_ffi_c_fun_ty(pair: Pair<Rule>) -> Rc<TyCon> {
     let name = if pair.as_str() == "()" {
         make_tuple_name(0)
     } else {
-        FullName::from_strs(&[STD_NAME], pair.as_str())
+        match pair.clone().into_inner().next() {
+            // A struct name, which will be resolved in `resolve_namespace`.
+            Some(inner) if inner.as_rule() == Rule::capital_fullname => {
+                parse_capital_fullname(inner)
+            }
+            _ => FullName::from_strs(&[STD_NAME], pair.as_str()),
+        }
     };
     tycon(name)
 }
@@ -1680,6 +1727,23 @@ fn parse_import_statement(pair: Pair<Rule>, ctx: &mut ParseContext) -> ImportSta
     }
 }
 
+// Parse `FFI_EXPORT[value_name, c_function_name];`.
+// The value name is relative to the module, e.g., `FFI_EXPORT[Foo::bar, foo_bar];` exports `Main::Foo::bar` in module `Main`.
+fn parse_export_statement(pair: Pair<Rule>, ctx: &mut ParseContext) -> ExportStatement {
+    assert_eq!(pair.as_rule(), Rule::export_statement);
+    let span = Span::from_pair(&ctx.source, &pair);
+    let mut pairs = pair.into_inner();
+    let var = parse_expr_var(pairs.next().unwrap(), ctx);
+    let mut value_name = var.get_var().name.clone();
+    value_name.namespace = ctx.namespace.append(value_name.namespace);
+    let c_function_name = pairs.next().unwrap().as_str().to_string();
+    ExportStatement {
+        value_name,
+        c_function_name,
+        source: Some(span),
+    }
+}
+
 fn rule_to_string(r: &Rule) -> String {
     fn join_by_or(tokens: &[&str]) -> String {
         tokens
@@ -1707,11 +1771,12 @@ fn rule_to_string(r: &Rule) -> String {
         Rule::operator_cmp => join_by_or(&["==", "!=", "<=", ">=", "<", ">"]),
         Rule::trait_impl => "`impl`".to_string(),
         Rule::import_statement => "`import`".to_string(),
+        Rule::export_statement => "`FFI_EXPORT`".to_string(),
         _ => format!("{:?}", r),
     }
 }
 
-fn message_parse_error(e: Error<Rule>, src: &SourceFile) -> String {
+fn message_parse_error(e: Error<Rule>, src: &SourceFile) -> (String, Span) {
     let mut msg: String = Default::default();
 
     #[allow(unused)]
@@ -1750,7 +1815,6 @@ fn message_parse_error(e: Error<Rule>, src: &SourceFile) -> String {
         msg += "\n";
         msg += &suggestion.unwrap();
     }
-    msg += "\n";
 
     // Show line and column number.
     let span = match e.location {
@@ -1765,7 +1829,5 @@ fn message_parse_error(e: Error<Rule>, src: &SourceFile) -> String {
             end: e,
         },
     };
-    msg += "\n";
-    msg += &span.to_string();
-    msg
+    (msg, span)
 }
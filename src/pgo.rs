@@ -0,0 +1,214 @@
+// Profile-guided optimization (`fix build --profile-generate` and `fix build --profile-use={file}.profdata`).
+//
+// The workflow is:
+// 1. `fix build --profile-generate` builds an executable instrumented by LLVM's IR-level PGO instrumentation.
+//    Running it writes `default_*.profraw` to the current directory (or the directory given by `--profile-generate={dir}`).
+// 2. `llvm-profdata merge -o {file}.profdata default_*.profraw` merges the raw profiles.
+// 3. `fix build --profile-use={file}.profdata` builds the program optimized by the profile.
+//
+// Since the C API of LLVM does not provide the PGO passes, the generated LLVM module is written as bitcode and
+// instrumented (or optimized with the profile) and compiled to an object file by clang.
+// The profile is also read by `llvm-profdata` and used by Fix-level optimizations: symbols which are never executed (nor called) are not uncurried.
+//
+// LLVM matches the profile to functions by names and hashes of their control flow graphs,
+// so the profile works for a program built from the same sources by the same compiler.
+// The uncurrying optimization is changed only for symbols which are not called by any symbol,
+// so that the functions in the profile are generated in the same way and clang does not warn about a mismatch of the profile.
+
+use std::process::Command;
+
+use super::*;
+
+// The name of the LLVM module of a program built into a single object file.
+// LLVM prefixes the names of functions with internal linkage by it in the profile.
+pub const PGO_MODULE_NAME: &str = "Main";
+
+// Execution counts of functions read from a profile.
+#[derive(Default)]
+pub struct Profile {
+    // The sum of counters of each function.
+    counts: HashMap<String, u64>,
+}
+
+impl Profile {
+    // Read an indexed profile (`.profdata`) by converting it to the text format by `llvm-profdata`.
+    pub fn read(path: &Path, config: &Configuration) -> Self {
+        if !path.exists() {
+            error_exit(&format!(
+                "Profile `{}` does not exist.",
+                path.to_str().unwrap()
+            ));
+        }
+        let mut com = config.toolchain.profdata_command();
+        com.arg("merge")
+            .arg("--text")
+            .arg("-o")
+            .arg("-")
+            .arg(path.to_str().unwrap());
+        let output = match com.output() {
+            Ok(output) => output,
+            Err(e) => error_exit(&format!(
+                "Failed to run `{}`: {}",
+                com.get_program().to_string_lossy(),
+                e
+            )),
+        };
+        if !output.status.success() {
+            error_exit(&format!(
+                "Failed to read profile `{}`.\n{}",
+                path.to_str().unwrap(),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Self::parse_text(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    // Parse a profile in the text format of `llvm-profdata`, which consists of records of
+    // the function name, `# Func Hash:`, the hash, `# Num Counters:`, the number of counters, `# Counter Values:` and the counters.
+    pub fn parse_text(text: &str) -> Self {
+        let mut profile = Profile::default();
+        let mut lines = text.lines().map(|line| line.trim());
+        while let Some(line) = lines.next() {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(':') {
+                continue;
+            }
+            let name = strip_module_prefix(line).to_string();
+            // A record ends with an empty line. Values after the counters (e.g., value profiles) are ignored.
+            let values = lines
+                .by_ref()
+                .take_while(|line| !line.is_empty())
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<_>>();
+            let counter_count = values
+                .get(1)
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(0);
+            let sum = values
+                .iter()
+                .skip(2)
+                .take(counter_count)
+                .filter_map(|v| v.parse::<u64>().ok())
+                .sum::<u64>();
+            *profile.counts.entry(name).or_default() += sum;
+        }
+        profile
+    }
+
+    // Is any function generated for a global symbol executed?
+    // A symbol is implemented by the function of its name (for a function pointer), the accessor `Get#{name}`, and uncurried versions `{name}#funptr{n}`.
+    pub fn is_executed_symbol(&self, name: &FullName) -> bool {
+        let name = name.to_string();
+        let accessor = format!("Get#{}", name);
+        let funptr_prefix = format!("{}#funptr", name);
+        self.counts.iter().any(|(func, count)| {
+            *count > 0 && (*func == name || *func == accessor || func.starts_with(&funptr_prefix))
+        })
+    }
+
+    // The symbols which should not be uncurried: symbols which are never executed and never called by any symbol.
+    // Since uncurrying a symbol changes the calls to it, a symbol called by another one is uncurried as in the instrumented build,
+    // otherwise the caller would not match its profile.
+    pub fn symbols_not_to_uncurry(&self, fix_mod: &Program) -> HashSet<FullName> {
+        let mut called = HashSet::default();
+        for sym in fix_mod.instantiated_global_symbols.values() {
+            collect_called_globals(sym.expr.as_ref().unwrap(), &mut called);
+        }
+        fix_mod
+            .instantiated_global_symbols
+            .keys()
+            .filter(|name| !self.is_executed_symbol(name) && !called.contains(name))
+            .cloned()
+            .collect()
+    }
+}
+
+// Collect global symbols applied to arguments in an expression, i.e., the calls which the uncurrying optimization may replace.
+fn collect_called_globals(expr: &Rc<ExprNode>, called: &mut HashSet<FullName>) {
+    match &*expr.expr {
+        Expr::Var(_) => {}
+        Expr::LLVM(_) => {}
+        Expr::App(fun, args) => {
+            let (head, _) = collect_app(expr);
+            if let Expr::Var(v) = &*head.expr {
+                if !v.name.is_local() {
+                    called.insert(v.name.clone());
+                }
+            }
+            collect_called_globals(fun, called);
+            for arg in args {
+                collect_called_globals(arg, called);
+            }
+        }
+        Expr::Lam(_, val) => collect_called_globals(val, called),
+        Expr::Let(_, bound, val) => {
+            collect_called_globals(bound, called);
+            collect_called_globals(val, called);
+        }
+        Expr::If(c, t, e) => {
+            collect_called_globals(c, called);
+            collect_called_globals(t, called);
+            collect_called_globals(e, called);
+        }
+        Expr::TyAnno(e, _) => collect_called_globals(e, called),
+        Expr::MakeStruct(_, fields) => {
+            for (_, field_expr) in fields {
+                collect_called_globals(field_expr, called);
+            }
+        }
+        Expr::ArrayLit(elems) => {
+            for e in elems {
+                collect_called_globals(e, called);
+            }
+        }
+        Expr::CallC(_, _, _, _, args) => {
+            for arg in args {
+                collect_called_globals(arg, called);
+            }
+        }
+    }
+}
+
+// Functions with internal linkage are named `{module}:{name}` (or `{module};{name}` by newer LLVM) in a profile.
+fn strip_module_prefix(name: &str) -> &str {
+    for sep in [':', ';'] {
+        if let Some(rest) = name.strip_prefix(&format!("{}{}", PGO_MODULE_NAME, sep)) {
+            // `Main::main` is a name of an external function, not `main` prefixed by the module name.
+            if !rest.starts_with(':') {
+                return rest;
+            }
+        }
+    }
+    name
+}
+
+// Compile the bitcode of a program into an object file by clang, instrumenting it (`--profile-generate`) or optimizing it with the profile (`--profile-use`).
+// Returns the warnings of clang.
+pub fn compile_bitcode_with_pgo(bc_path: &Path, obj_path: &Path, config: &Configuration) -> String {
+    let _timer = time_pass("object_emission");
+    let mut com = config.toolchain.pgo_compile_command();
+    com.arg(format!("--target={}", config.get_target_triple()))
+        .arg("-O2")
+        .arg("-Wno-unused-command-line-argument");
+    if let Some(dir) = &config.profile_generate {
+        com.arg(format!("-fprofile-generate={}", dir.to_str().unwrap()));
+    }
+    if let Some(path) = &config.profile_use {
+        // Functions whose code changed since the profile was generated are optimized without the profile, and clang warns about them.
+        com.arg(format!("-fprofile-use={}", path.to_str().unwrap()));
+    }
+    if config.is_library() {
+        com.arg("-fPIC");
+    }
+    com.arg("-o")
+        .arg(obj_path.to_str().unwrap())
+        .arg("-c")
+        .arg(bc_path.to_str().unwrap());
+    run_toolchain_command(&mut com)
+}
+
+// Add options to link the profile runtime of LLVM to an instrumented program.
+pub fn add_profile_generate_link_options(com: &mut Command, config: &Configuration) {
+    if let Some(dir) = &config.profile_generate {
+        com.arg(format!("-fprofile-generate={}", dir.to_str().unwrap()));
+    }
+}
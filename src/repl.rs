@@ -0,0 +1,429 @@
+// An implementation of `fix repl` command.
+//
+// Each input is compiled into a new LLVM module, which is added to the execution engine shared in the session.
+// Global symbols compiled for previous inputs are only declared in the new module and linked by name,
+// so that the values of global values (including those defined by `let`) are evaluated only once.
+//
+// The program consisting of the standard library and the definitions entered so far is kept after front-end phases,
+// and an expression is type-checked and instantiated in a copy of it, so that the definitions are parsed and checked only when they are entered.
+
+use std::io::{BufRead, Write};
+
+use either::Either;
+use inkwell::{execution_engine::ExecutionEngine, module::Linkage};
+
+use super::*;
+
+const REPL_MODULE_NAME: &str = "Repl";
+const REPL_SOURCE_NAME: &str = "repl";
+
+const REPL_HELP: &str = "\
+Enter an expression to evaluate it. A value is printed by `ToString` if available, and an `IO` action is executed.
+Definitions of types, traits, global values and `import` statements are added to the module `Repl`.
+Commands:
+  let x = expr;   Define a global value `x` whose type is inferred.
+  :type expr      Show the type of an expression.
+  :help           Show this message.
+  :quit           Exit the REPL.";
+
+// The state of a REPL session.
+pub struct ReplSession<'c> {
+    context: &'c Context,
+    config: Configuration,
+    // The execution engine to which modules compiled for inputs are added.
+    ee: Option<ExecutionEngine<'c>>,
+    // Modules added to the execution engine, which are kept alive while the session.
+    #[allow(dead_code)]
+    modules: Vec<Module<'c>>,
+    // Source code of definitions and import statements entered so far.
+    definitions: String,
+    // The program of the definitions entered so far after front-end phases, and its type checker. `None` if not yet loaded.
+    checked: Option<(Program, TypeCheckContext)>,
+    // Instantiated global symbols which are already compiled into the execution engine.
+    compiled_symbols: HashSet<FullName>,
+    // The number of inputs evaluated so far, used to give unique names to entry points.
+    input_count: u32,
+    // Whether the runtime library is loaded.
+    runtime_loaded: bool,
+}
+
+// A function implementing `fix repl` command.
+pub fn repl_command(config: Configuration) {
+    // Compile errors are reported for each input, so they should not terminate the session.
+    enable_catching_compile_errors();
+
+    let context = Context::create();
+    let mut session = ReplSession::new(&context, config);
+
+    let stdin = std::io::stdin();
+    let mut reader = stdin.lock();
+    while let Some(input) = read_input(&mut reader) {
+        let input = input.trim();
+        if input.is_empty() {
+            continue;
+        }
+        if input == ":quit" || input == ":q" {
+            break;
+        }
+        if input == ":help" {
+            println!("{}", REPL_HELP);
+            continue;
+        }
+        if let Err(e) = catch_compile_error(|| session.eval_input(input)) {
+            eprintln!("error: {}", e.to_string());
+        }
+    }
+}
+
+// Read an input from the user. A line ending with `\` is continued to the next line.
+// Returns None when the input is closed.
+fn read_input(reader: &mut impl BufRead) -> Option<String> {
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { "fix> " } else { "...> " });
+        std::io::stdout().flush().ok();
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return if input.is_empty() { None } else { Some(input) };
+        }
+        let line = line.trim_end_matches(&['\n', '\r']);
+        match line.strip_suffix('\\') {
+            Some(line) => {
+                input += line;
+                input += "\n";
+            }
+            None => {
+                input += line;
+                return Some(input);
+            }
+        }
+    }
+}
+
+impl<'c> ReplSession<'c> {
+    pub fn new(context: &'c Context, config: Configuration) -> Self {
+        let module = context.create_module(REPL_SOURCE_NAME);
+        let ee = module
+            .create_jit_execution_engine(config.get_llvm_opt_level())
+            .unwrap();
+        ReplSession {
+            context,
+            config,
+            ee: Some(ee),
+            modules: vec![module],
+            definitions: String::new(),
+            checked: None,
+            compiled_symbols: Default::default(),
+            input_count: 0,
+            runtime_loaded: false,
+        }
+    }
+
+    fn repl_module_name() -> Name {
+        REPL_MODULE_NAME.to_string()
+    }
+
+    // Evaluate an input, which is an expression, a definition or a command.
+    pub fn eval_input(&mut self, input: &str) {
+        if let Some(expr) = input.strip_prefix(":type") {
+            self.show_type(expr.trim());
+        } else if input.starts_with(':') {
+            error_exit(&format!(
+                "Unknown command `{}`. Enter `:help` to see available commands.",
+                input
+            ));
+        } else if input.starts_with("let ") && input.ends_with(';') {
+            self.add_let_definition(&input["let ".len()..input.len() - 1]);
+        } else if is_definition(input) {
+            self.add_definitions(input);
+        } else {
+            self.eval_expr(input);
+        }
+    }
+
+    // Create a program consisting of the standard library and the module `Repl`,
+    // which contains the definitions entered so far followed by `source`.
+    fn load_program(&mut self, source: &str) -> Program {
+        let source = format!(
+            "module {};\n{}{}\n",
+            REPL_MODULE_NAME, self.definitions, source
+        );
+        let repl_mod = parse_and_save_to_temporary_file(&source, REPL_SOURCE_NAME);
+        let mut program = make_std_mod();
+        program.link(repl_mod);
+        program.resolve_imports(&mut self.config);
+        program
+    }
+
+    // Get the program of the definitions entered so far after front-end phases.
+    // It is loaded again only if it lacks tuple types of the given sizes.
+    fn checked_program(&mut self, tuple_sizes: &[u32]) -> &(Program, TypeCheckContext) {
+        let lacks_tuples = match &self.checked {
+            Some((program, _)) => tuple_sizes
+                .iter()
+                .any(|size| !program.used_tuple_sizes.contains(size)),
+            None => true,
+        };
+        if lacks_tuples {
+            let mut program = self.load_program("");
+            program.used_tuple_sizes.extend(tuple_sizes);
+            let tc = run_frontend(&mut program);
+            self.checked = Some((program, tc));
+        }
+        self.checked.as_ref().unwrap()
+    }
+
+    // Parse an expression written in the module `Repl`. Returns the expression and the sizes of tuples used in it.
+    fn parse_expr(expr: &str) -> (Rc<ExprNode>, Vec<u32>) {
+        let source = SourceFile::from_string(PathBuf::from(REPL_SOURCE_NAME), expr.to_string());
+        parse_expr_source(source, &Self::repl_module_name())
+    }
+
+    // Infer the type of an expression under the definitions entered so far.
+    fn infer_type(&mut self, expr: &str) -> Rc<Scheme> {
+        let (expr, tuple_sizes) = Self::parse_expr(expr);
+        let (program, tc) = self.checked_program(&tuple_sizes);
+        program.infer_type_of_expr(expr, &Self::repl_module_name(), tc)
+    }
+
+    fn show_type(&mut self, expr: &str) {
+        let scm = self.infer_type(expr);
+        println!("{} : {}", expr, scm.to_string());
+    }
+
+    // Add definitions to the module `Repl` after checking their types.
+    fn add_definitions(&mut self, source: &str) {
+        let mut program = self.load_program(source);
+        let tc = run_frontend(&mut program);
+
+        // Global values defined by previous inputs are already checked, but trait method implementations may be added.
+        let modules = HashSet::from([Self::repl_module_name()]);
+        let mut names = program
+            .global_values
+            .iter()
+            .filter(|(name, gv)| {
+                let checked = match &self.checked {
+                    Some((checked, _)) => checked.global_values.contains_key(name),
+                    None => false,
+                };
+                !checked || matches!(gv.expr, SymbolExpr::Method(_))
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        for name in &names {
+            program.check_type_of_global_value(name, &modules, &tc);
+        }
+
+        self.definitions += source;
+        self.definitions += "\n";
+        self.checked = Some((program, tc));
+    }
+
+    // Add a global value defined by `let name = expr;` or `let name : type = expr;`.
+    fn add_let_definition(&mut self, defn: &str) {
+        let (lhs, expr) = match split_let_definition(defn) {
+            Some(res) => res,
+            None => {
+                error_exit("Only `let name = expression;` is supported for definitions in REPL.")
+            }
+        };
+        let (name, ty) = match lhs.split_once(':') {
+            Some((name, ty)) => (name.trim(), ty.trim().to_string()),
+            None => (lhs.trim(), self.infer_type(expr).to_string()),
+        };
+        if !is_global_value_name(name) {
+            error_exit(&format!(
+                "`{}` is not a valid name of a global value.",
+                name
+            ));
+        }
+        self.add_definitions(&format!("{} : {};\n{} = {};", name, ty, name, expr));
+    }
+
+    // Evaluate an expression, printing its value or running it if it is an `IO` action.
+    fn eval_expr(&mut self, expr: &str) {
+        let scm = self.infer_type(expr);
+
+        // Candidates of the entry point, which are tried in order.
+        let expr = format!("(\n{}\n)", expr);
+        let candidates = if scm.ty.to_string() == make_io_unit_ty().to_string() {
+            vec![expr]
+        } else if is_io_type(&scm.ty) {
+            vec![
+                format!(
+                    "Std::Monad::bind(|v| Std::IO::println(Std::ToString::to_string(v)), {})",
+                    expr
+                ),
+                format!("Std::Functor::map(|_| (), {})", expr),
+            ]
+        } else {
+            vec![
+                format!("Std::IO::println(Std::ToString::to_string({}))", expr),
+                format!("Std::IO::println(\"<value of type {}>\")", scm.to_string()),
+            ]
+        };
+        self.input_count += 1;
+
+        // Add the candidates to a copy of the checked program as global values of type `IO ()`.
+        // Results of type-checking are cached by names and the definitions, so the name of an entry contains the hash of the expression.
+        let mut entries = vec![];
+        for expr in &candidates {
+            let name = format!("repl_entry_{}_{:x}", self.input_count, md5::compute(expr));
+            entries.push((
+                FullName::from_strs(&[REPL_MODULE_NAME], &name),
+                Self::parse_expr(expr),
+            ));
+        }
+        let tuple_sizes = entries
+            .iter()
+            .flat_map(|(_, (_, sizes))| sizes.iter().cloned())
+            .collect::<Vec<_>>();
+        let (program, tc) = self.checked_program(&tuple_sizes);
+        let (mut program, mut tc) = (program.clone(), tc.clone());
+        let io_unit_scm = Scheme::generalize(Default::default(), vec![], make_io_unit_ty());
+        for (name, (expr, _)) in &entries {
+            program.add_global_value(name.clone(), (expr.clone(), io_unit_scm.clone()));
+            tc.scope
+                .add_global(name.name.clone(), &name.namespace, &io_unit_scm);
+        }
+
+        // Choose the first candidate which passes type-checking.
+        let modules = HashSet::from([Self::repl_module_name()]);
+        let mut entry = None;
+        let mut first_err = None;
+        for (name, _) in entries {
+            match catch_compile_error(|| program.check_type_of_global_value(&name, &modules, &tc)) {
+                Ok(_) => {
+                    entry = Some(name);
+                    break;
+                }
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        match entry {
+            Some(entry) => self.run_io_action(program, &tc, &entry),
+            None => raise_error(first_err.unwrap()),
+        }
+    }
+
+    // Compile a global value of type `IO ()` into a new LLVM module and run it.
+    fn run_io_action(&mut self, mut program: Program, tc: &TypeCheckContext, entry: &FullName) {
+        let io_expr = program.instantiate_io_action(entry, tc);
+        if self.config.get_uncurry_optimization() {
+            uncurry_optimization(&mut program, &HashSet::default());
+        }
+        if self.config.get_borrowing_optimization() {
+            borrowing_optimization(&mut program);
+        }
+
+        let module = self
+            .context
+            .create_module(&format!("{}_{}", REPL_SOURCE_NAME, self.input_count));
+        let fn_name = format!("repl_main_{}", self.input_count);
+        let ee = {
+            let mut gc = GenerationContext::new(
+                self.context,
+                &module,
+                Either::Right(self.ee.take().unwrap()),
+                self.config.clone(),
+                program.type_env(),
+            );
+            build_runtime(&mut gc);
+            internalize_definitions(&module);
+            program.generate_code(&mut gc, &self.compiled_symbols, true);
+
+            // Add the function which runs the entry point.
+            let fn_ty = self.context.void_type().fn_type(&[], false);
+            let function = module.add_function(&fn_name, fn_ty, None);
+            let entry_bb = self.context.append_basic_block(function, "entry");
+            gc.builder().position_at_end(entry_bb);
+            build_store_run_function_ptr(&mut gc);
+            build_run_io_action(&mut gc, io_expr);
+            gc.builder().build_return(None);
+            optimize_module(&module, &self.config);
+            gc.target.unwrap_right()
+        };
+        ee.add_module(&module)
+            .expect("Failed to add a module to the execution engine.");
+        self.compiled_symbols
+            .extend(program.instantiated_global_symbols.into_keys());
+        self.modules.push(module);
+        let ee = self.ee.insert(ee);
+
+        if !self.runtime_loaded {
+            load_runtime_library(&self.config);
+            self.runtime_loaded = true;
+        }
+        unsafe {
+            let func = ee.get_function::<unsafe extern "C" fn()>(&fn_name).unwrap();
+            func.call();
+        }
+    }
+}
+
+// Give internal linkage to functions and variables defined in a module,
+// so that each module in the execution engine has its own copy of runtime functions.
+fn internalize_definitions(module: &Module) {
+    for func in module.get_functions() {
+        if func.count_basic_blocks() > 0 {
+            func.set_linkage(Linkage::Internal);
+        }
+    }
+    for gv in module.get_globals() {
+        if gv.get_initializer().is_some() {
+            gv.set_linkage(Linkage::Internal);
+        }
+    }
+}
+
+// Check if an input consists of definitions or import statements.
+fn is_definition(input: &str) -> bool {
+    let first_word = input
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or_default();
+    if ["type", "trait", "impl", "import", "namespace"].contains(&first_word) {
+        return true;
+    }
+    let source = SourceFile::from_string(
+        PathBuf::from(REPL_SOURCE_NAME),
+        format!("module {};\n{}", REPL_MODULE_NAME, input),
+    );
+    catch_compile_error(|| parse_source_file(source)).is_ok()
+}
+
+// Split `name = expr` (or `name : type = expr`) at the first `=` which is not a part of an operator.
+fn split_let_definition(defn: &str) -> Option<(&str, &str)> {
+    let bytes = defn.as_bytes();
+    for (i, c) in bytes.iter().enumerate() {
+        if *c != b'=' {
+            continue;
+        }
+        let prev = if i > 0 { bytes[i - 1] } else { b' ' };
+        let next = bytes.get(i + 1).cloned().unwrap_or(b' ');
+        if "=<>!".as_bytes().contains(&prev) || next == b'=' {
+            continue;
+        }
+        return Some((&defn[..i], defn[i + 1..].trim()));
+    }
+    None
+}
+
+fn is_global_value_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_lowercase() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_io_type(ty: &Rc<TypeNode>) -> bool {
+    match ty.toplevel_tycon() {
+        Some(tc) => tc.name == FullName::from_strs(&[STD_NAME], IO_NAME),
+        None => false,
+    }
+}
@@ -1,8 +1,10 @@
 use build_time::build_time_utc;
 use std::{
     env,
+    ffi::CString,
     fs::create_dir_all,
     fs::{self, remove_dir_all},
+    os::unix::process::CommandExt,
     path::PathBuf,
     process::Command,
     ptr::null,
@@ -10,15 +12,60 @@ use std::{
 
 use either::Either;
 use inkwell::{
-    execution_engine::ExecutionEngine,
+    execution_engine::{ExecutionEngine, JitFunction},
     module::Linkage,
     passes::PassManager,
-    targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine},
+    targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple},
 };
 
 use super::*;
 
+// The type of `main` function generated by `build_main_function`.
+pub type MainFunction = unsafe extern "C" fn(i32, *const *const i8) -> i32;
+
 fn execute_main_module<'c>(ee: &ExecutionEngine<'c>, config: &Configuration) -> i32 {
+    let main = compile_main_function(ee, config);
+    report_time_passes(config);
+    call_main_function(&main, config)
+}
+
+// Load the runtime library and compile `main` function of a module added to the execution engine.
+pub fn compile_main_function<'c>(
+    ee: &ExecutionEngine<'c>,
+    config: &Configuration,
+) -> JitFunction<'c, MainFunction> {
+    load_runtime_library(config);
+    let _timer = time_pass("jit_compilation");
+    unsafe { ee.get_function::<MainFunction>("main").unwrap() }
+}
+
+// Call `main` function compiled by `compile_main_function`, and returns its exit code.
+pub fn call_main_function(main: &JitFunction<MainFunction>, config: &Configuration) -> i32 {
+    // Pass the program name and the arguments given by `fix run -- args...` as `argc` and `argv`.
+    let program_name = config
+        .source_files
+        .first()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let args = std::iter::once(&program_name)
+        .chain(config.program_args.iter())
+        .map(|arg| {
+            CString::new(arg.as_str()).unwrap_or_else(|_| {
+                error_exit(&format!(
+                    "Program argument contains a null character: {}",
+                    arg
+                ))
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut argv = args.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+    argv.push(null());
+
+    unsafe { main.call(args.len() as i32, argv.as_ptr()) }
+}
+
+// Load runtime library (and sanitizer) to the process so that the execution engine can call runtime functions.
+pub fn load_runtime_library(config: &Configuration) {
     // If sanitize_memory, load `libfixsanitizer.so`.
     if config.sanitize_memory {
         let path = "./sanitizer/libfixsanitizer.so";
@@ -40,15 +87,24 @@ fn execute_main_module<'c>(ee: &ExecutionEngine<'c>, config: &Configuration) ->
         .join("_");
     hash_source += &linked_libs_list;
     hash_source += &config.runtime_c_macro.join("_");
+    hash_source += &config.toolchain.fingerprint();
+    hash_source += &config
+        .toolchain
+        .library_paths
+        .iter()
+        .map(|p| p.to_str().unwrap())
+        .collect::<Vec<_>>()
+        .join("_");
     let runtime_so_path = PathBuf::from(INTERMEDIATE_PATH)
         .join(format!("libfixruntime.{:x}.so", md5::compute(hash_source)));
     if !runtime_so_path.exists() {
+        let _timer = time_pass("runtime_build");
         let runtime_c_path = PathBuf::from(INTERMEDIATE_PATH).join("fixruntime.c");
         fs::create_dir_all(INTERMEDIATE_PATH).expect("Failed to create intermediate directory.");
         fs::write(&runtime_c_path, include_str!("runtime.c"))
             .expect(&format!("Failed to generate runtime.c"));
         // Create library binary file.
-        let mut com = Command::new("gcc");
+        let mut com = config.toolchain.compile_and_link_command();
         com.arg("-shared")
             .arg("-fpic")
             .arg("-o")
@@ -64,32 +120,14 @@ fn execute_main_module<'c>(ee: &ExecutionEngine<'c>, config: &Configuration) ->
             }
             com.arg(format!("-l{}", lib_name));
         }
-        let output = com.output().expect("Failed to run gcc.");
-        if output.stderr.len() > 0 {
-            eprintln!(
-                "{}",
-                String::from_utf8(output.stderr)
-                    .unwrap_or("(failed to stringify error message of gcc.)".to_string())
-            );
-        }
+        run_toolchain_command(&mut com);
     }
     load_library_permanently(runtime_so_path.to_str().unwrap());
-
-    unsafe {
-        let func = ee
-            .get_function::<unsafe extern "C" fn(i32, *const *const i8) -> i32>("main")
-            .unwrap();
-        func.call(0, null())
-    }
 }
 
-fn build_module<'c>(
-    context: &'c Context,
-    module: &Module<'c>,
-    target: Either<TargetMachine, ExecutionEngine<'c>>,
-    mut fix_mod: Program,
-    config: Configuration,
-) -> Either<TargetMachine, ExecutionEngine<'c>> {
+// Run the phases before type-checking (calculating type environment, resolving namespaces, validation, etc.),
+// and create a typechecker to which declarations of global symbols are registered.
+pub fn run_frontend(fix_mod: &mut Program) -> TypeCheckContext {
     // Add tuple types used in this program.
     let mut used_tuple_sizes = fix_mod.used_tuple_sizes.clone();
     // Make elements of used_tuple_sizes unique.
@@ -100,6 +138,7 @@ fn build_module<'c>(
     }
 
     // Calculate list of type constructors.
+    let type_env_timer = time_pass("type_env");
     fix_mod.calculate_type_env();
 
     // Infer namespaces of traits and types that appear in declarations (not in expressions).
@@ -113,15 +152,20 @@ fn build_module<'c>(
 
     // Add struct / union methods
     fix_mod.add_methods();
+    drop(type_env_timer);
 
     // Validate trait env.
+    let trait_validation_timer = time_pass("trait_validation");
     fix_mod.validate_trait_env();
 
     // Create symbols.
     fix_mod.create_trait_method_symbols();
+    drop(trait_validation_timer);
 
     // Set and check kinds that appear in the module.
+    let kind_check_timer = time_pass("kind_check");
     fix_mod.set_kinds();
+    drop(kind_check_timer);
 
     // Create typeckecker.
     let mut typechecker = TypeCheckContext::new(
@@ -137,20 +181,121 @@ fn build_module<'c>(
             .add_global(name.name.clone(), &name.namespace, &defn.scm);
     }
 
-    // Instantiate main function and all called functions.
-    let main_expr = fix_mod.instantiate_main_function(&typechecker);
+    typechecker
+}
+
+// The values from which the entry points of the output are generated.
+pub enum ProgramEntry {
+    // The `IO ()` action run by `main`.
+    Main(Rc<ExprNode>),
+    // The values exported to C by `FFI_EXPORT`, for a library.
+    Exports(Vec<(ExportStatement, ExportedFunctionType, Rc<ExprNode>)>),
+}
+
+// Run the frontend, instantiate the entry point and all functions called from it, and optimize the instantiated symbols.
+pub fn prepare_program(fix_mod: &mut Program, config: &Configuration) -> ProgramEntry {
+    // Emit the parsed program.
+    let user_modules = fix_mod.modules_of_files(&config.source_files);
+    if config.emit_ast {
+        print_dump("ast", &dump_ast(fix_mod, &user_modules));
+    }
+
+    // Run phases before type-checking.
+    let typechecker = run_frontend(fix_mod);
+
+    // Instantiate main function (or exported values) and all called functions.
+    let instantiation_timer = time_pass("instantiation");
+    let entry = if config.is_library() {
+        if fix_mod.export_statements.is_empty() {
+            error_exit("No value is exported. Use `FFI_EXPORT[value_name, c_function_name];` to export a value from a library.");
+        }
+        let mut exported_values = vec![];
+        for export in fix_mod.export_statements.clone() {
+            let fun_ty = export.function_type(fix_mod);
+            let ty = fix_mod.global_values[&export.value_name].scm.ty.clone();
+            let expr = fix_mod.instantiate_global_value(&export.value_name, &ty, &typechecker);
+            exported_values.push((export, fun_ty, expr));
+        }
+        write_library_header(config, &exported_values);
+        ProgramEntry::Exports(exported_values)
+    } else {
+        let entry_point = FullName::parse(&config.entry_point)
+            .filter(|name| name.is_global())
+            .unwrap_or_else(|| {
+                error_exit(&format!(
+                    "Invalid entry point `{}`: it should be a full name such as `Main::main`.",
+                    config.entry_point
+                ))
+            });
+        ProgramEntry::Main(fix_mod.instantiate_io_action(&entry_point, &typechecker))
+    };
+    drop(instantiation_timer);
+
+    // Emit the type-annotated program.
+    // This is done after the instantiation, since only global values used by the program are type-checked.
+    if config.emit_typed {
+        print_dump("typed", &dump_typed(fix_mod, &user_modules, &typechecker));
+    }
+
+    // Emit the instantiated symbols.
+    if config.emit_instances {
+        print_dump("instances", &dump_instances(fix_mod));
+    }
 
     // Perform uncurrying optimization.
     if config.get_uncurry_optimization() {
-        uncurry_optimization(&mut fix_mod);
+        let _timer = time_pass("uncurry_optimization");
+        // With a profile, symbols which are not executed are not uncurried to save the code size.
+        let excluded = match &config.profile_use {
+            Some(path) => Profile::read(path, config).symbols_not_to_uncurry(fix_mod),
+            None => HashSet::default(),
+        };
+        uncurry_optimization(fix_mod, &excluded);
     }
 
     // Perform borrowing optimization.
     if config.get_borrowing_optimization() {
-        borrowing_optimization(&mut fix_mod);
+        let _timer = time_pass("borrowing_optimization");
+        borrowing_optimization(fix_mod);
+    }
+
+    set_time_passes_counter(
+        "instantiated_symbols",
+        fix_mod.instantiated_global_symbols.len(),
+    );
+    entry
+}
+
+// Build the entry points of the output: `main` function, or functions exported to C and the function initializing the runtime.
+pub fn build_program_entry<'c, 'm>(
+    gc: &mut GenerationContext<'c, 'm>,
+    entry: ProgramEntry,
+    config: &Configuration,
+) {
+    match entry {
+        ProgramEntry::Exports(exported_values) => {
+            for (export, fun_ty, expr) in exported_values {
+                build_exported_function(gc, &export, &fun_ty, expr);
+            }
+            build_library_initializer(gc);
+        }
+        ProgramEntry::Main(main_expr) => {
+            build_main_function(gc, main_expr, config);
+        }
     }
+}
+
+pub fn build_module<'c>(
+    context: &'c Context,
+    module: &Module<'c>,
+    target: Either<TargetMachine, ExecutionEngine<'c>>,
+    mut fix_mod: Program,
+    config: Configuration,
+) -> Either<TargetMachine, ExecutionEngine<'c>> {
+    let entry = prepare_program(&mut fix_mod, &config);
 
     // Create GenerationContext.
+    let ir_generation_timer = time_pass("llvm_ir_generation");
     let mut gc = GenerationContext::new(
         &context,
         &module,
@@ -168,13 +313,54 @@ fn build_module<'c>(
     build_runtime(&mut gc);
 
     // Generate codes.
-    fix_mod.generate_code(&mut gc);
+    fix_mod.generate_code(&mut gc, &HashSet::default(), false);
+
+    // Add `main` function, or functions exported to C.
+    build_program_entry(&mut gc, entry, &config);
+
+    // If debug inf generated, finalize it.
+    gc.finalize_di();
+    drop(ir_generation_timer);
+
+    // Link the runtime into the module so that its functions can be inlined into Fix code.
+    // In JIT, the runtime is loaded as a shared library instead.
+    if gc.target.is_left() && config.get_runtime_inlining() {
+        link_runtime_bitcode(context, module, &config);
+    }
+
+    // Print LLVM bitcode to file
+    if config.emit_llvm {
+        let path = config.get_output_llvm_ir_path(true);
+        if let Err(e) = module.print_to_file(path) {
+            error_exit(&format!("Failed to emit llvm: {}", e.to_string()));
+        }
+    }
+
+    // Run optimization and verify LLVM module.
+    optimize_module(module, &config);
+
+    // Print LLVM bitcode to file
+    if config.emit_llvm {
+        let path = config.get_output_llvm_ir_path(false);
+        if let Err(e) = module.print_to_file(path) {
+            error_exit(&format!("Failed to emit llvm: {}", e.to_string()));
+        }
+    }
 
+    gc.target
+}
+
+// Add `main` function which runs `main_expr`.
+fn build_main_function<'c, 'm>(
+    gc: &mut GenerationContext<'c, 'm>,
+    main_expr: Rc<ExprNode>,
+    config: &Configuration,
+) {
     // Add main function.
-    let main_fn_type = context.i32_type().fn_type(
+    let main_fn_type = gc.context.i32_type().fn_type(
         &[
-            context.i32_type().into(), // argc
-            context
+            gc.context.i32_type().into(), // argc
+            gc.context
                 .i8_type()
                 .ptr_type(AddressSpace::from(0))
                 .ptr_type(AddressSpace::from(0))
@@ -182,8 +368,8 @@ fn build_module<'c>(
         ],
         false,
     );
-    let main_function = module.add_function("main", main_fn_type, None);
-    let entry_bb = context.append_basic_block(main_function, "entry");
+    let main_function = gc.module.add_function("main", main_fn_type, None);
+    let entry_bb = gc.context.append_basic_block(main_function, "entry");
     gc.builder().position_at_end(entry_bb);
 
     // Save argc and argv to global variables.
@@ -201,7 +387,247 @@ fn build_module<'c>(
         gc.builder().build_store(gv_ptr, arg_val);
     }
 
-    // Store the pointer to `fixruntime_run_function` function defined in LLVM module to the `ptr_fixruntime_run_function` global variable defined in runtime.c.
+    build_store_run_function_ptr(gc);
+
+    // If both of `AsyncTask` and sanitizer are used, prepare for terminating threads.
+    if config.async_task && config.sanitize_memory {
+        gc.call_runtime(RuntimeFunctions::ThreadPrepareTermination, &[]);
+    }
+
+    // Run main object.
+    build_run_io_action(gc, main_expr);
+
+    // Perform leak check
+    gc.check_leak();
+
+    // Return main function.
+    gc.builder()
+        .build_return(Some(&gc.context.i32_type().const_int(0, false)));
+}
+
+// Write the C header file declaring the functions exported from a library.
+fn write_library_header(
+    config: &Configuration,
+    exported_values: &[(ExportStatement, ExportedFunctionType, Rc<ExprNode>)],
+) {
+    let header_path = config.get_output_file_path().with_extension("h");
+    let guard = format!(
+        "FIX_{}_H",
+        header_path
+            .file_stem()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_uppercase()
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    );
+    let mut header = String::new();
+    header += "// This file is generated by `fix build --lib`.\n";
+    header += &format!("#ifndef {}\n#define {}\n\n", guard, guard);
+    header += "#include <stdint.h>\n\n";
+    header += "#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n";
+    header += "// Initializes the runtime of Fix with command line arguments.\n";
+    header += "// This is called automatically when the library is loaded on Linux and macOS.\n";
+    header += &format!(
+        "void {}(int argc, char **argv);\n\n",
+        LIBRARY_INITIALIZER_NAME
+    );
+    for (export, fun_ty, _) in exported_values {
+        let params = if fun_ty.params.is_empty() {
+            "void".to_string()
+        } else {
+            fun_ty
+                .params
+                .iter()
+                .map(|param| param.get_c_type_name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        header += &format!("// `{}`\n", export.value_name.to_string());
+        header += &format!(
+            "{} {}({});\n\n",
+            fun_ty.ret.get_c_type_name(),
+            export.c_function_name,
+            params
+        );
+    }
+    header += "#ifdef __cplusplus\n}\n#endif\n\n";
+    header += &format!("#endif // {}\n", guard);
+    if let Err(e) = fs::write(&header_path, header) {
+        error_exit(&format!(
+            "Failed to write `{}`: {}",
+            header_path.to_str().unwrap(),
+            e
+        ));
+    }
+}
+
+// Add a C function which calls the value exported by `FFI_EXPORT`.
+fn build_exported_function<'c, 'm>(
+    gc: &mut GenerationContext<'c, 'm>,
+    export: &ExportStatement,
+    fun_ty: &ExportedFunctionType,
+    value: Rc<ExprNode>,
+) {
+    let fn_ty = c_function_type_of_fix_function(gc, fun_ty, None);
+    if gc.module.get_function(&export.c_function_name).is_some() {
+        error_exit_with_src(
+            &format!(
+                "Duplicate definition of C function `{}`.",
+                export.c_function_name
+            ),
+            &export.source,
+        );
+    }
+    let func = gc
+        .module
+        .add_function(&export.c_function_name, fn_ty, Some(Linkage::External));
+    let entry_bb = gc.context.append_basic_block(func, "entry");
+    gc.builder().position_at_end(entry_bb);
+
+    let obj = gc.eval_expr(value, None);
+    let c_args = func.get_params();
+    build_call_fix_function_from_c(gc, fun_ty, obj, c_args);
+}
+
+// Get the type of a C function which calls a Fix function.
+// If `context_idx` is given, a `void*` parameter is inserted at the index.
+pub fn c_function_type_of_fix_function<'c, 'm>(
+    gc: &mut GenerationContext<'c, 'm>,
+    fun_ty: &ExportedFunctionType,
+    context_idx: Option<usize>,
+) -> FunctionType<'c> {
+    let mut param_c_tys = fun_ty
+        .c_params()
+        .iter()
+        .map(|param| param.get_c_type(gc.context).unwrap().into())
+        .collect::<Vec<_>>();
+    if let Some(idx) = context_idx {
+        param_c_tys.insert(
+            idx,
+            gc.context.i8_type().ptr_type(AddressSpace::from(0)).into(),
+        );
+    }
+    match fun_ty.ret.get_c_type(gc.context) {
+        None => gc.context.void_type().fn_type(&param_c_tys, false),
+        Some(ret_c_ty) => ret_c_ty.fn_type(&param_c_tys, false),
+    }
+}
+
+// Build the body of a C function which applies the Fix function `obj` to the arguments `c_args` and returns the result.
+// `()` is given for parameters of type `()`.
+pub fn build_call_fix_function_from_c<'c, 'm>(
+    gc: &mut GenerationContext<'c, 'm>,
+    fun_ty: &ExportedFunctionType,
+    mut obj: Object<'c>,
+    c_args: Vec<BasicValueEnum<'c>>,
+) {
+    // Apply the value to the arguments.
+    let mut c_args = c_args.into_iter();
+    for param in &fun_ty.params {
+        let arg_ty = obj.ty.get_lambda_srcs()[0].clone();
+        let arg = allocate_obj(arg_ty, &vec![], None, gc, Some("arg_of_exported_function"));
+        if !param.is_unit() {
+            arg.store_field_nocap(gc, 0, c_args.next().unwrap());
+        }
+        obj = gc.apply_lambda(obj, vec![arg], None);
+    }
+
+    // Run the IO action.
+    if fun_ty.is_io {
+        let io_lambda_val = obj.load_field_nocap(gc, 0);
+        let io_lambda_ty = type_fun(make_unit_ty(), type_tycon(&fun_ty.ret));
+        let io_lambda = Object::create_from_value(io_lambda_val, io_lambda_ty, gc);
+        let unit = allocate_obj(
+            make_unit_ty(),
+            &vec![],
+            None,
+            gc,
+            Some("unit_for_exported_io"),
+        );
+        obj = gc.apply_lambda(io_lambda, vec![unit], None);
+    }
+
+    // Return the result.
+    if fun_ty.ret.is_unit() {
+        gc.release(obj);
+        gc.builder().build_return(None);
+    } else {
+        let ret = obj.load_field_nocap(gc, 0);
+        gc.release(obj);
+        gc.builder().build_return(Some(&ret));
+    }
+}
+
+// Add `fixruntime_init_library` function, which saves command line arguments and initializes the runtime, and register it as a constructor of the library.
+// Since the loader calls constructors with `argc` and `argv` (on Linux and macOS), `Std::IO::get_args` works also in a library.
+fn build_library_initializer<'c, 'm>(gc: &mut GenerationContext<'c, 'm>) {
+    let argv_ty = gc
+        .context
+        .i8_type()
+        .ptr_type(AddressSpace::from(0))
+        .ptr_type(AddressSpace::from(0));
+    let fn_ty = gc
+        .context
+        .void_type()
+        .fn_type(&[gc.context.i32_type().into(), argv_ty.into()], false);
+    let init_fn = gc
+        .module
+        .add_function(LIBRARY_INITIALIZER_NAME, fn_ty, Some(Linkage::External));
+    let entry_bb = gc.context.append_basic_block(init_fn, "entry");
+    gc.builder().position_at_end(entry_bb);
+
+    // Save argc and argv to global variables.
+    for (i, arg) in [GLOBAL_VAR_NAME_ARGC, GLOBAL_VAR_NAME_ARGV]
+        .iter()
+        .enumerate()
+    {
+        let arg_val = init_fn.get_nth_param(i as u32).unwrap();
+        let gv_ptr = gc
+            .module
+            .get_global(arg)
+            .unwrap()
+            .as_basic_value_enum()
+            .into_pointer_value();
+        gc.builder().build_store(gv_ptr, arg_val);
+    }
+    build_store_run_function_ptr(gc);
+    gc.builder().build_return(None);
+
+    // Register `init_fn` to `llvm.global_ctors`.
+    let ctor_fn_ptr_ty = gc
+        .context
+        .void_type()
+        .fn_type(&[], false)
+        .ptr_type(AddressSpace::from(0));
+    let i8_ptr_ty = gc.context.i8_type().ptr_type(AddressSpace::from(0));
+    let ctor_ty = gc.context.struct_type(
+        &[
+            gc.context.i32_type().into(),
+            ctor_fn_ptr_ty.into(),
+            i8_ptr_ty.into(),
+        ],
+        false,
+    );
+    let ctor = ctor_ty.const_named_struct(&[
+        gc.context.i32_type().const_int(65535, false).into(),
+        init_fn
+            .as_global_value()
+            .as_pointer_value()
+            .const_cast(ctor_fn_ptr_ty)
+            .into(),
+        i8_ptr_ty.const_null().into(),
+    ]);
+    let ctors = ctor_ty.const_array(&[ctor]);
+    let ctors_gv = gc
+        .module
+        .add_global(ctors.get_type(), None, "llvm.global_ctors");
+    ctors_gv.set_linkage(Linkage::Appending);
+    ctors_gv.set_initializer(&ctors);
+}
+
+// Store the pointer to `fixruntime_run_function` function defined in LLVM module to the `ptr_fixruntime_run_function` global variable defined in runtime.c.
+pub fn build_store_run_function_ptr<'c, 'm>(gc: &mut GenerationContext<'c, 'm>) {
     let run_function_func_ptr_ty = gc
         .context
         .i8_type()
@@ -223,78 +649,63 @@ fn build_module<'c>(
         run_task_func_ptr.as_pointer_value(),
         run_function_func.as_global_value().as_pointer_value(),
     );
+}
 
-    // If both of `AsyncTask` and sanitizer are used, prepare for terminating threads.
-    if config.async_task && config.sanitize_memory {
-        gc.call_runtime(RuntimeFunctions::ThreadPrepareTermination, &[]);
-    }
-
-    // Run main object.
-    let main_obj = gc.eval_expr(main_expr, None); // `IO ()`
-    let main_lambda_val = main_obj.load_field_nocap(&mut gc, 0);
-    let main_lambda_ty = type_fun(make_tuple_ty(vec![]), make_tuple_ty(vec![]));
-    let main_lambda = Object::create_from_value(main_lambda_val, main_lambda_ty, &mut gc);
+// Build the code which runs an `IO ()` object at the current position of the builder.
+pub fn build_run_io_action<'c, 'm>(gc: &mut GenerationContext<'c, 'm>, io_expr: Rc<ExprNode>) {
+    let io_obj = gc.eval_expr(io_expr, None); // `IO ()`
+    let io_lambda_val = io_obj.load_field_nocap(gc, 0);
+    let io_lambda_ty = type_fun(make_tuple_ty(vec![]), make_tuple_ty(vec![]));
+    let io_lambda = Object::create_from_value(io_lambda_val, io_lambda_ty, gc);
     let unit = allocate_obj(
         make_tuple_ty(vec![]),
         &vec![],
         None,
-        &mut gc,
+        gc,
         Some("unit_for_main_io"),
     );
-    let ret = gc.apply_lambda(main_lambda, vec![unit], None);
+    let ret = gc.apply_lambda(io_lambda, vec![unit], None);
     gc.release(ret);
+}
 
-    // Perform leak check
-    gc.check_leak();
-
-    // Return main function.
-    gc.builder()
-        .build_return(Some(&gc.context.i32_type().const_int(0, false)));
-
-    // If debug inf generated, finalize it.
-    gc.finalize_di();
-
-    // Print LLVM bitcode to file
-    if config.emit_llvm {
-        let path = config.get_output_llvm_ir_path(true);
-        if let Err(e) = module.print_to_file(path) {
-            error_exit(&format!("Failed to emit llvm: {}", e.to_string()));
-        }
-    }
-
-    // Run optimization
+// Run optimization passes on a LLVM module and verify it.
+pub fn optimize_module<'c>(module: &Module<'c>, config: &Configuration) {
+    let optimization_timer = time_pass("llvm_optimization");
     let passmgr = PassManager::create(());
-
     passmgr.add_verifier_pass();
-    match config.fix_opt_level {
-        FixOptimizationLevel::None => {}
-        FixOptimizationLevel::Minimum => {
-            passmgr.add_tail_call_elimination_pass();
-        }
-        FixOptimizationLevel::Default => {
-            add_passes(&passmgr);
-        }
+    if config.fix_opt_level == FixOptimizationLevel::Minimum {
+        passmgr.add_tail_call_elimination_pass();
     }
-
     passmgr.run_on(module);
+    if config.fix_opt_level == FixOptimizationLevel::Default {
+        // Each group of passes is run by its own pass manager, so that it can be timed separately.
+        for (group_name, add_group_passes) in PASS_GROUPS {
+            let _timer = time_pass(group_name);
+            let passmgr = PassManager::create(());
+            // An alias analysis is used only by the passes added after it to the same pass manager.
+            passmgr.add_type_based_alias_analysis_pass();
+            passmgr.add_scoped_no_alias_aa_pass();
+            passmgr.add_basic_alias_analysis_pass();
+            add_group_passes(&passmgr);
+            passmgr.run_on(module);
+        }
+
+        // Remove internal functions which are inlined everywhere or not used, e.g., those of the inlined runtime.
+        let _timer = time_pass("llvm_global_dce");
+        let passmgr = PassManager::create(());
+        passmgr.add_global_dce_pass();
+        passmgr.run_on(module);
+    }
+    drop(optimization_timer);
 
     // Verify LLVM module.
     // Maybe not needed at now?
+    let _timer = time_pass("llvm_verification");
     let verify = module.verify();
     if verify.is_err() {
         print!("{}", verify.unwrap_err().to_str().unwrap());
         panic!("LLVM verify failed!");
     }
-
-    // Print LLVM bitcode to file
-    if config.emit_llvm {
-        let path = config.get_output_llvm_ir_path(false);
-        if let Err(e) = module.print_to_file(path) {
-            error_exit(&format!("Failed to emit llvm: {}", e.to_string()));
-        }
-    }
-
-    gc.target
 }
 
 #[allow(dead_code)]
@@ -306,8 +717,9 @@ pub fn run_source(source: &str, mut config: Configuration) {
         save_temporary_source(source, MAIN_RUN, &source_hash);
         config.source_files = vec![temporary_source_path(MAIN_RUN, &source_hash)];
 
-        build_file(config);
+        build_file(config.clone());
         let output = Command::new("./a.out")
+            .args(&config.program_args)
             .output()
             .expect("Failed to run a.out.");
         if output.status.code().is_none() {
@@ -385,31 +797,157 @@ pub fn load_file(config: &mut Configuration) -> Program {
         let fix_mod = parse_file_path(file_path.clone());
         target_mod.link(fix_mod);
     }
+    let _timer = time_pass("resolve_imports");
     target_mod.resolve_imports(config);
     target_mod
 }
 
+// A function implementing `fix check` command.
+// Performs type-checking of all global values defined in the source files without generating codes.
+pub fn check_file(mut config: Configuration) {
+    let mut fix_mod = load_file(&mut config);
+    let typechecker = run_frontend(&mut fix_mod);
+    let modules = fix_mod.modules_of_files(&config.source_files);
+    fix_mod.check_type_of_global_values(&modules, &typechecker);
+    for export in &fix_mod.export_statements {
+        export.function_type(&fix_mod);
+    }
+}
+
 pub fn run_file(mut config: Configuration) -> i32 {
     run_module(load_file(&mut config), config)
 }
 
-fn get_target_machine(opt_level: OptimizationLevel) -> TargetMachine {
-    let _native = Target::initialize_native(&InitializationConfig::default())
-        .map_err(|e| error_exit(&format!("failed to initialize native: {}", e)))
-        .unwrap();
-    let triple = TargetMachine::get_default_triple();
+// A function implementing `fix script` command.
+// The executable built from the script is cached per user (see `script_cache_dir`), and reused while neither the script nor the modules it imports are changed.
+pub fn script_command(mut config: Configuration) -> ! {
+    let script_path = config.source_files[0].clone();
+    let exec_path = build_script(&mut config);
+    // Replace this process by the executable, so that stdio, signals and the exit status are those of the script.
+    let err = Command::new(&exec_path)
+        .arg0(&script_path)
+        .args(&config.program_args)
+        .exec();
+    error_exit(&format!(
+        "Failed to run `{}`: {}",
+        exec_path.to_string_lossy(),
+        err
+    ))
+}
+
+// Build the executable of a script unless it is cached, and return its path.
+pub fn build_script(config: &mut Configuration) -> PathBuf {
+    let program = load_file(config);
+    let exec_path = script_executable_path(&program, config);
+    if !exec_path.exists() {
+        // Build to a temporary file and rename it, so that another process running the same script does not see an incomplete executable.
+        let tmp_path = PathBuf::from(format!(
+            "{}.{}.tmp",
+            exec_path.to_string_lossy(),
+            std::process::id()
+        ));
+        let mut build_config = config.clone();
+        build_config.out_file_path = Some(tmp_path.clone());
+        build_program(program, build_config);
+        if let Err(e) = fs::rename(&tmp_path, &exec_path) {
+            error_exit(&format!(
+                "Failed to write `{}`: {}",
+                exec_path.to_string_lossy(),
+                e
+            ));
+        }
+    }
+    exec_path
+}
+
+// The directory where executables of scripts are cached: `$XDG_CACHE_HOME/fix/scripts`, or `~/.cache/fix/scripts`.
+// It does not depend on the working directory, so a script run from anywhere (e.g., found in `PATH`) shares one cache and leaves no files there.
+pub fn script_cache_dir() -> PathBuf {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|| std::env::temp_dir());
+    cache_home.join(SCRIPT_CACHE_PATH)
+}
+
+// Get the path of the cached executable of a script.
+// The file name is determined by the source codes of the script and its dependent modules, the configuration and the build time of the compiler.
+fn script_executable_path(program: &Program, config: &Configuration) -> PathBuf {
+    let mut module_hashes = program
+        .modules_of_files(&config.source_files)
+        .iter()
+        .map(|module| program.hash_of_dependent_codes(module))
+        .collect::<Vec<_>>();
+    module_hashes.sort();
+    let mut hash_source = "".to_string();
+    hash_source += build_time_utc!();
+    hash_source += &module_hashes.join("_");
+    hash_source += &config
+        .linked_libraries
+        .iter()
+        .map(|(s, _)| s.clone())
+        .collect::<Vec<_>>()
+        .join("_");
+    hash_source += &config.runtime_c_macro.join("_");
+    hash_source += &format!("{}", config.fix_opt_level as u8);
+    hash_source += &config.toolchain.fingerprint();
+    let script_name = config.source_files[0]
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let cache_dir = script_cache_dir();
+    if let Err(e) = fs::create_dir_all(&cache_dir) {
+        error_exit(&format!(
+            "Failed to create directory `{}`: {}",
+            cache_dir.to_string_lossy(),
+            e
+        ));
+    }
+    cache_dir.join(format!("{}.{:x}", script_name, md5::compute(hash_source)))
+}
+
+// Create the target machine for the output, following `--target` and `--target-cpu`.
+// For libraries, it generates position independent code, which is required for shared libraries.
+pub fn get_target_machine(config: &Configuration) -> TargetMachine {
+    let triple = match &config.target_triple {
+        None => {
+            Target::initialize_native(&InitializationConfig::default())
+                .map_err(|e| error_exit(&format!("failed to initialize native: {}", e)))
+                .unwrap();
+            TargetMachine::get_default_triple()
+        }
+        Some(triple) => {
+            Target::initialize_all(&InitializationConfig::default());
+            TargetTriple::create(triple)
+        }
+    };
     let target = Target::from_triple(&triple)
         .map_err(|e| {
-            error_exit(&format!("failed to create target: {}", e));
+            error_exit(&format!(
+                "failed to create target `{}`: {}",
+                triple.as_str().to_string_lossy(),
+                e
+            ));
         })
         .unwrap();
-    let cpu_name = TargetMachine::get_host_cpu_name();
+    // Use the host CPU and its features when compiling for the host (or `--target-cpu=native`).
+    let (cpu_name, cpu_features) = match config.target_cpu.as_deref() {
+        Some("native") => (host_cpu_name(), host_cpu_features()),
+        Some(cpu) => (cpu.to_string(), "".to_string()),
+        None if config.target_triple.is_none() => (host_cpu_name(), host_cpu_features()),
+        None => ("".to_string(), "".to_string()),
+    };
     let target_machine = target.create_target_machine(
         &triple,
-        cpu_name.to_str().unwrap(),
-        TargetMachine::get_host_cpu_features().to_str().unwrap(),
-        opt_level,
-        RelocMode::Default,
+        &cpu_name,
+        &cpu_features,
+        config.get_llvm_opt_level(),
+        if config.is_library() {
+            RelocMode::PIC
+        } else {
+            RelocMode::Default
+        },
         CodeModel::Default,
     );
     match target_machine {
@@ -418,30 +956,129 @@ fn get_target_machine(opt_level: OptimizationLevel) -> TargetMachine {
     }
 }
 
+fn host_cpu_name() -> String {
+    TargetMachine::get_host_cpu_name()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+fn host_cpu_features() -> String {
+    TargetMachine::get_host_cpu_features()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
 pub fn build_file(mut config: Configuration) {
-    let obj_path = PathBuf::from(INTERMEDIATE_PATH).join("a.o");
-    let exec_path = config.get_output_executable_file_path();
+    let fix_mod = load_file(&mut config);
+    build_program(fix_mod, config.clone());
+    report_time_passes(&config);
+}
+
+// Build an executable binary (or an object, assembly or bitcode file, following `config.output_file_type`) from a program whose imports are already resolved.
+pub fn build_program(fix_mod: Program, config: Configuration) {
+    let out_path = config.get_output_file_path();
 
     // Create intermediate directory.
     fs::create_dir_all(INTERMEDIATE_PATH).expect("Failed to create intermediate .");
 
-    let tm = get_target_machine(config.get_llvm_opt_level());
+    // Split the program into multiple LLVM modules for an incremental build or a parallel build.
+    // A parallel build is not used when the output is not linked or LLVM IR is emitted, which requires a single LLVM module.
+    let split_modules = config.incremental
+        || (config.codegen_jobs > 1 && config.output_file_type.is_linked() && !config.emit_llvm);
+    let obj_paths = if split_modules {
+        build_codegen_units(fix_mod, &config)
+    } else {
+        match build_single_object(fix_mod, &config, &out_path) {
+            Some(obj_path) => vec![obj_path],
+            None => return,
+        }
+    };
+    link_object_files(&obj_paths, &out_path, &config);
+}
 
-    let fix_mod = load_file(&mut config);
+// Generate the whole program into one LLVM module.
+// Returns the path of the object file to be linked, or `None` if the output is written without linking (`--emit=obj,asm,bc`).
+fn build_single_object(
+    fix_mod: Program,
+    config: &Configuration,
+    out_path: &Path,
+) -> Option<PathBuf> {
+    let obj_path = PathBuf::from(INTERMEDIATE_PATH).join("a.o");
+    let tm = get_target_machine(config);
 
     let ctx = Context::create();
-    let module = ctx.create_module("Main");
+    let module = ctx.create_module(PGO_MODULE_NAME);
     module.set_triple(&tm.get_triple());
     module.set_data_layout(&tm.get_target_data().get_data_layout());
 
     let tm = build_module(&ctx, &module, Either::Left(tm), fix_mod, config.clone()).unwrap_left();
+    match config.output_file_type {
+        OutputFileType::Executable
+        | OutputFileType::SharedLibrary
+        | OutputFileType::StaticLibrary => {}
+        OutputFileType::Object | OutputFileType::Assembly => {
+            let file_type = if config.output_file_type == OutputFileType::Object {
+                inkwell::targets::FileType::Object
+            } else {
+                inkwell::targets::FileType::Assembly
+            };
+            let emission_timer = time_pass("object_emission");
+            tm.write_to_file(&module, file_type, out_path)
+                .map_err(|e| error_exit(&format!("Failed to write to file: {}", e)))
+                .unwrap();
+            drop(emission_timer);
+            // The runtime is also needed to link the object file.
+            if config.output_file_type == OutputFileType::Object {
+                let runtime_obj_path = build_runtime_object(config);
+                let runtime_out_path = out_path.with_file_name(RUNTIME_OBJECT_FILE_NAME);
+                fs::copy(&runtime_obj_path, &runtime_out_path)
+                    .map_err(|e| {
+                        error_exit(&format!(
+                            "Failed to write `{}`: {}",
+                            runtime_out_path.to_str().unwrap(),
+                            e
+                        ))
+                    })
+                    .unwrap();
+            }
+            return None;
+        }
+        OutputFileType::Bitcode => {
+            if !module.write_bitcode_to_path(&out_path) {
+                error_exit(&format!(
+                    "Failed to write to file: `{}`",
+                    out_path.to_str().unwrap()
+                ));
+            }
+            return None;
+        }
+    }
+    // With profile-guided optimization, the module is compiled by clang.
+    if config.profile_generate.is_some() || config.profile_use.is_some() {
+        let bc_path = PathBuf::from(INTERMEDIATE_PATH).join("a.bc");
+        if !module.write_bitcode_to_path(&bc_path) {
+            error_exit(&format!(
+                "Failed to write to file: `{}`",
+                bc_path.to_str().unwrap()
+            ));
+        }
+        compile_bitcode_with_pgo(&bc_path, &obj_path, config);
+        return Some(obj_path);
+    }
+    let _timer = time_pass("object_emission");
     tm.write_to_file(&module, inkwell::targets::FileType::Object, &obj_path)
         .map_err(|e| error_exit(&format!("Failed to write to file: {}", e)))
         .unwrap();
+    Some(obj_path)
+}
 
+// Link object files generated from a Fix program with the runtime and libraries into the output file.
+fn link_object_files(obj_paths: &[PathBuf], out_path: &Path, config: &Configuration) {
     let mut libs_opts = vec![];
     for (lib_name, link_type) in &config.linked_libraries {
-        if std::env::consts::OS != "macos" {
+        if !config.is_target_macos() {
             match link_type {
                 LinkType::Static => libs_opts.push("-Wl,-Bstatic".to_string()),
                 LinkType::Dynamic => libs_opts.push("-Wl,-Bdynamic".to_string()),
@@ -455,66 +1092,238 @@ pub fn build_file(mut config: Configuration) {
         libs_opts.push(format!("-l{}", lib_name));
     }
 
-    // Build runtime.c to object file.
+    let runtime_obj_path = build_runtime_object(config);
+
+    let _timer = time_pass("link");
+    // Create a static library by archiving object files.
+    if config.output_file_type == OutputFileType::StaticLibrary {
+        if out_path.exists() {
+            fs::remove_file(&out_path).expect("Failed to remove old library.");
+        }
+        run_toolchain_command(
+            config
+                .toolchain
+                .archive_command()
+                .arg("rcs")
+                .arg(out_path.to_str().unwrap())
+                .args(obj_paths)
+                .arg(runtime_obj_path.to_str().unwrap()),
+        );
+        return;
+    }
+
+    let mut com = if config.profile_generate.is_some() {
+        config.toolchain.pgo_link_command()
+    } else {
+        config.toolchain.link_command()
+    };
+    add_profile_generate_link_options(&mut com, config);
+    com.arg("-Wno-unused-command-line-argument");
+    if config.output_file_type == OutputFileType::SharedLibrary {
+        com.arg("-shared");
+    } else {
+        com.arg("-no-pie");
+    }
+    if config.is_target_macos() {
+        com.arg("-Wl,-dead_strip");
+    } else {
+        com.arg("-Wl,--gc-sections");
+    }
+    com.arg("-o")
+        .arg(out_path.to_str().unwrap())
+        .args(obj_paths)
+        .arg(runtime_obj_path.to_str().unwrap())
+        .args(libs_opts);
+    run_toolchain_command(&mut com);
+}
+
+// Build runtime.c to an object file (if it is not cached) and return its path.
+fn build_runtime_object(config: &Configuration) -> PathBuf {
     let mut runtime_obj_hash_source = "".to_string();
     runtime_obj_hash_source += build_time_utc!();
     runtime_obj_hash_source += &config.runtime_c_macro.join("_");
+    runtime_obj_hash_source += &config.toolchain.fingerprint();
     let runtime_obj_path = PathBuf::from(INTERMEDIATE_PATH).join(format!(
         "fixruntime.{:x}.o",
         md5::compute(runtime_obj_hash_source)
     ));
     if !runtime_obj_path.exists() {
+        let _timer = time_pass("runtime_build");
         let runtime_c_path = PathBuf::from(INTERMEDIATE_PATH).join("fixruntime.c");
         fs::create_dir_all(INTERMEDIATE_PATH).expect("Failed to create intermediate directory.");
         fs::write(&runtime_c_path, include_str!("runtime.c"))
             .expect(&format!("Failed to generate runtime.c"));
         // Create library object file.
-        let mut com = Command::new("gcc");
-        let mut com = com
-            .arg("-ffunction-sections")
+        let mut com = config.toolchain.compile_command();
+        com.arg("-ffunction-sections")
             .arg("-fdata-sections")
+            .arg("-fPIC")
             .arg("-o")
             .arg(runtime_obj_path.to_str().unwrap())
             .arg("-c")
             .arg(runtime_c_path.to_str().unwrap());
         for m in &config.runtime_c_macro {
-            com = com.arg(format!("-D{}", m));
+            com.arg(format!("-D{}", m));
         }
-        let output = com.output().expect("Failed to run gcc.");
+        run_toolchain_command(&mut com);
+    }
+    runtime_obj_path
+}
 
-        if output.stderr.len() > 0 {
+// Get the path of the runtime compiled to LLVM bitcode for a configuration.
+// The file is empty if the runtime bitcode is not available for the configuration.
+pub(crate) fn runtime_bitcode_path(config: &Configuration) -> PathBuf {
+    let mut runtime_bc_hash_source = "".to_string();
+    runtime_bc_hash_source += build_time_utc!();
+    runtime_bc_hash_source += &config.runtime_c_macro.join("_");
+    runtime_bc_hash_source += &config.toolchain.fingerprint();
+    runtime_bc_hash_source += &config.get_target_triple();
+    PathBuf::from(INTERMEDIATE_PATH).join(format!(
+        "fixruntime.{:x}.bc",
+        md5::compute(runtime_bc_hash_source)
+    ))
+}
+
+// Build runtime.c to LLVM bitcode by clang (if it is not cached) and return its path.
+// Returns `None` if clang is not available or fails; then the runtime is only linked as an object file.
+fn build_runtime_bitcode(config: &Configuration) -> Option<PathBuf> {
+    let runtime_bc_path = runtime_bitcode_path(config);
+    if !runtime_bc_path.exists() {
+        let _timer = time_pass("runtime_build");
+        let runtime_c_path = PathBuf::from(INTERMEDIATE_PATH).join("fixruntime.c");
+        fs::create_dir_all(INTERMEDIATE_PATH).expect("Failed to create intermediate directory.");
+        fs::write(&runtime_c_path, include_str!("runtime.c"))
+            .expect(&format!("Failed to generate runtime.c"));
+        // Functions compiled by `-O0` are marked `optnone` and never inlined, so compile with `-O2`.
+        let mut com = config.toolchain.bitcode_compile_command();
+        com.arg(format!("--target={}", config.get_target_triple()))
+            .arg("-O2")
+            .arg("-fPIC")
+            .arg("-emit-llvm")
+            .arg("-o")
+            .arg(runtime_bc_path.to_str().unwrap())
+            .arg("-c")
+            .arg(runtime_c_path.to_str().unwrap());
+        for m in &config.runtime_c_macro {
+            com.arg(format!("-D{}", m));
+        }
+        let succeeded = match com.output() {
+            Err(e) => {
+                eprintln!(
+                    "warning: Failed to run `{}` to compile the runtime to LLVM bitcode, so runtime functions will not be inlined: {}",
+                    config.toolchain.clang(),
+                    e
+                );
+                false
+            }
+            Ok(output) if output.status.success() => true,
+            Ok(output) => {
+                eprintln!(
+                    "warning: Failed to compile the runtime to LLVM bitcode, so runtime functions will not be inlined:\n{}",
+                    String::from_utf8_lossy(&output.stderr).trim_end()
+                );
+                false
+            }
+        };
+        if !succeeded {
+            // Leave an empty file so that clang is not tried again for the same configuration.
+            fs::write(&runtime_bc_path, "").expect("Failed to write runtime bitcode.");
+        }
+    }
+    if fs::metadata(&runtime_bc_path).map(|m| m.len()).unwrap_or(0) == 0 {
+        return None;
+    }
+    Some(runtime_bc_path)
+}
+
+// Link the runtime compiled to LLVM bitcode into a module.
+// Functions of the runtime are made internal to the module, so they do not conflict with the runtime object file linked to the output.
+// Mutable global variables (the runtime state) are made `available_externally`, so that they are not emitted and the functions share them with the runtime object file.
+// If the runtime bitcode is not available (e.g., clang is not installed, or its LLVM is newer than the one of the compiler), does nothing.
+fn link_runtime_bitcode<'c>(context: &'c Context, module: &Module<'c>, config: &Configuration) {
+    let runtime_bc_path = match build_runtime_bitcode(config) {
+        Some(path) => path,
+        None => return,
+    };
+    let _timer = time_pass("runtime_inlining");
+    let runtime = match Module::parse_bitcode_from_path(&runtime_bc_path, context) {
+        Ok(runtime) => runtime,
+        Err(e) => {
             eprintln!(
-                "{}",
-                String::from_utf8(output.stderr)
-                    .unwrap_or("(failed to parse stderr from gcc as UTF8.)".to_string())
+                "warning: The runtime bitcode built by `{}` cannot be read by LLVM 12 of the compiler (an older clang is required), so runtime functions will not be inlined: {}",
+                config.toolchain.clang(),
+                e.to_string().trim_end()
+            );
+            // Empty the cached bitcode so that the warning is shown only once for the configuration.
+            fs::write(&runtime_bc_path, "").expect("Failed to write runtime bitcode.");
+            return;
+        }
+    };
+    runtime.set_triple(&module.get_triple());
+    runtime.set_data_layout(&module.get_data_layout());
+    let function_names = runtime
+        .get_functions()
+        .filter(|f| f.count_basic_blocks() > 0)
+        .map(|f| f.get_name().to_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    let mut constant_names = vec![];
+    let mut variable_names = vec![];
+    for gv in runtime.get_globals() {
+        if gv.get_initializer().is_none() {
+            continue;
+        }
+        let name = gv.get_name().to_str().unwrap().to_string();
+        if gv.is_constant() {
+            constant_names.push(name);
+        } else if matches!(gv.get_linkage(), Linkage::Internal | Linkage::Private) {
+            // A `static` variable cannot be shared with the runtime object file, and copying it would duplicate the runtime state.
+            eprintln!(
+                "warning: The runtime has a static variable `{}`, so runtime functions will not be inlined.",
+                name
             );
+            fs::write(&runtime_bc_path, "").expect("Failed to write runtime bitcode.");
+            return;
+        } else {
+            variable_names.push(name);
         }
     }
-
-    let mut com = Command::new("gcc");
-    com.arg("-Wno-unused-command-line-argument").arg("-no-pie");
-    if std::env::consts::OS == "macos" {
-        com.arg("-Wl,-dead_strip");
-    } else {
-        com.arg("-Wl,--gc-sections");
+    module
+        .link_in_module(runtime)
+        .map_err(|e| error_exit(&format!("Failed to link the runtime: {}", e.to_string())))
+        .unwrap();
+    for name in function_names {
+        if let Some(func) = module.get_function(&name) {
+            func.set_linkage(Linkage::Internal);
+        }
     }
-    com.arg("-o")
-        .arg(exec_path.to_str().unwrap())
-        .arg(obj_path.to_str().unwrap())
-        .arg(runtime_obj_path.to_str().unwrap())
-        .args(libs_opts);
-    let output = com.output().expect("Failed to run gcc.");
-    if output.stderr.len() > 0 {
-        eprintln!(
-            "{}",
-            String::from_utf8(output.stderr)
-                .unwrap_or("(failed to parse stderr from gcc as UTF8.)".to_string())
-        );
+    for name in constant_names {
+        if let Some(gv) = module.get_global(&name) {
+            gv.set_linkage(Linkage::Internal);
+        }
+    }
+    for name in variable_names {
+        if let Some(gv) = module.get_global(&name) {
+            gv.set_linkage(Linkage::AvailableExternally);
+        }
     }
 }
 
 // A function implementing `fix clean` command.
-pub fn clean_command() {
+pub fn clean_command(cache_only: bool) {
+    if cache_only {
+        clear_type_check_cache();
+        if Path::new(CODEGEN_UNITS_CACHE_PATH).exists() {
+            remove_dir_all(CODEGEN_UNITS_CACHE_PATH)
+                .map_err(|e| {
+                    error_exit(&format!(
+                        "Failed to remove `{}`: {}",
+                        CODEGEN_UNITS_CACHE_PATH, e
+                    ))
+                })
+                .unwrap();
+        }
+        return;
+    }
     // Delete `.fixlang` directory.
     remove_dir_all(DOT_FIXLANG).expect(&format!("Failed to remove `{}` directory.", DOT_FIXLANG));
 }
@@ -2,6 +2,8 @@
 C functions / values for implementing Fix standard library.
 - When running program by `fix run`, then this source file will be compiled into shared library and loaded to the JIT environment.
 - When running program by `fix build`, then this source file will be compiled into object file and linked to the binary.
+  With the default optimization level, it is also compiled into LLVM bitcode by clang (if available) and linked into the generated LLVM module, so that these functions can be inlined.
+  The object file is still linked, and global variables are shared with it, so this file must not have `static` variables.
 */
 
 #include <ctype.h>
@@ -48,7 +50,7 @@ void fixruntime_eprint(const char *msg)
     fflush(stderr);
 }
 
-// NOTE: Maybe should we define following functions by LLVM to better optimization opportunity?
+// NOTE: The standard library uses the built-in functions `_unsafe_store_unaligned` and `_unsafe_load_unaligned` instead of the following functions, so that they are inlined even without clang.
 void fixruntime_u8_to_bytes(uint8_t *buf, uint8_t v)
 {
     *buf = v;
@@ -521,6 +523,12 @@ void *fixruntime_run_function(void *function)
     return (*ptr_fixruntime_run_function)(function);
 }
 
+// Release the context of a C callback made by `Std::FFI::unsafe_make_c_callback`.
+void fixruntime_release_c_callback(void (*release)(void *), void *context)
+{
+    (*release)(context);
+}
+
 #ifdef THREAD
 
 typedef int *TaskFunction;
@@ -36,6 +36,17 @@ impl SourceFile {
         src_file
     }
 
+    // Create a source file from a string which may differ from the content of the file.
+    pub fn from_string(file_path: PathBuf, string: String) -> Self {
+        let mut src_file = Self {
+            string: Some(Rc::new(string)),
+            hash: None,
+            file_path,
+        };
+        src_file.set_hash();
+        src_file
+    }
+
     // Set the values of uninitialized fields.
     pub fn read_file(&mut self) {
         if self.string.is_none() {
@@ -108,6 +119,13 @@ impl Span {
         }
     }
 
+    // Check if two spans point the same range of the same file.
+    pub fn is_same_range(&self, other: &Self) -> bool {
+        self.start == other.start
+            && self.end == other.end
+            && self.input.file_path == other.input.file_path
+    }
+
     pub fn unite(&self, other: &Self) -> Self {
         Self {
             input: self.input.clone(),
@@ -2,27 +2,226 @@ use super::*;
 
 pub const FIX_NAME: &str = "fix";
 
-const STD_SOURCE: &str = include_str!("fix/std.fix");
+pub const STD_SOURCE: &str = include_str!("fix/std.fix");
 
 pub fn make_std_mod() -> Program {
     let mut fix_module = parse_and_save_to_temporary_file(STD_SOURCE, "std");
 
+    // Types implemented in the compiler (see `bulitin_tycons`).
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], BOOL_NAME),
+        r#"
+`Bool` is the type of boolean values, represented by 8-bit integer `1` (`true`) and `0` (`false`).
+
+Boolean literals are `true` and `false`.
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], I8_NAME),
+        r#"
+`I8` is the type of 8-bit signed integers.
+
+Literals:
+
+- `{number}_I8`
+    - Example: `42_I8`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], U8_NAME),
+        r#"
+`U8` is the type of 8-bit unsigned integers.
+
+Literals:
+
+- `{number}_U8`
+    - Example: `42_U8`
+- `'{character}'`
+  - Example:
+    - `'A'` for `65_U8`
+    - `'\0'` for `0_U8`
+    - `'\t'` for `9_U8`
+    - `'\r'` for `13_U8`
+    - `'\n'` for `10_U8`
+    - `'\\'` for `92_U8`
+    - `'\''` for `39_U8`
+    - `'\x7f'` for `127_U8`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], I16_NAME),
+        r#"
+`I16` is the type of 16-bit signed integers.
+
+Literals:
+
+- `{number}_I16`
+    - Example: `42_I16`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], U16_NAME),
+        r#"
+`U16` is the type of 16-bit unsigned integers.
+
+Literals:
+
+- `{number}_U16`
+    - Example: `42_U16`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], I32_NAME),
+        r#"
+`I32` is the type of 32-bit signed integers.
+
+Literals:
+
+- `{number}_I32`
+    - Example: `42_I32`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], U32_NAME),
+        r#"
+`U32` is the type of 32-bit unsigned integers.
+
+Literals:
+
+- `{number}_U32`
+    - Example: `42_U32`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], I64_NAME),
+        r#"
+`I64` is the type of 64-bit signed integers.
+
+Literals:
+
+- `{number}`
+    - Example: `42`
+- `{number}_I64`
+    - Example: `42_I64 == 42`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], U64_NAME),
+        r#"
+`U64` is the type of 64-bit unsigned integers.
+
+Literals:
+
+- `{number}_U64`
+    - Example: `42_U64`
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], F32_NAME),
+        r#"
+`F32` is the type of 32-bit floating numbers.
+
+For `F32` literals, you need to add a suffix "_F32" to explicitly specify the type. Example: `3.1416_F32`.
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], F64_NAME),
+        r#"
+`F64` is the type of 64-bit floating numbers.
+
+For `F64` literals, you can write or omit explicit type specifier suffix "_F64". Example `3.1416_F64 == 3.1416`.
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], ARRAY_NAME),
+        r#"
+`Array` is the type of variable-length arrays.
+
+Literals:
+
+- `[{elem_0}, {elem_1}, ...]`
+    - Example: `[1, 2, 3]` for integer array of length 3.
+"#,
+    );
+    fix_module.add_builtin_doc(
+        &FullName::from_strs(&[STD_NAME], PTR_NAME),
+        r#"
+`Ptr` is the type of pointers.
+
+Literals:
+
+- `nullptr`
+    - The null pointer.
+"#,
+    );
+
     // `LoopResult` type.
-    fix_module.type_defns.push(loop_result_defn());
+    let loop_result = loop_result_defn();
+    fix_module.add_builtin_doc(
+        &loop_result.name,
+        r#"
+`LoopResult` represents the result of loop body function and used with `loop` function. For example of `LoopResult`, see the section for `loop` function.
+
+```
+type LoopResult s b = unbox union { continue : s, break : b };
+```
+"#,
+    );
+    fix_module.type_defns.push(loop_result);
 
     // Traits
     fix_module.trait_env.add_trait(eq_trait());
+    fix_module.add_builtin_doc(
+        &eq_trait_id().name,
+        "`a : Eq` states that values of type `a` can be compared by `==` and `!=`.",
+    );
     fix_module.trait_env.add_trait(add_trait());
+    fix_module.add_builtin_doc(
+        &add_trait_id().name,
+        "`a : Add` states that values of type `a` can be added by `+`.",
+    );
     fix_module.trait_env.add_trait(subtract_trait());
+    fix_module.add_builtin_doc(
+        &subtract_trait_id().name,
+        "`a : Sub` states that values of type `a` can be subtracted by `-`.",
+    );
     fix_module.trait_env.add_trait(negate_trait());
+    fix_module.add_builtin_doc(
+        &negate_trait_id().name,
+        "`a : Neg` states that values of type `a` can be negated by the prefix operator `-`.",
+    );
     fix_module.trait_env.add_trait(not_trait());
+    fix_module.add_builtin_doc(
+        &not_trait_id().name,
+        "`a : Not` states that the logical negation of values of type `a` can be calculated by the prefix operator `!`.",
+    );
     fix_module.trait_env.add_trait(multiply_trait());
+    fix_module.add_builtin_doc(
+        &multiply_trait_id().name,
+        "`a : Mul` states that values of type `a` can be multiplied by `*`.",
+    );
     fix_module.trait_env.add_trait(divide_trait());
+    fix_module.add_builtin_doc(
+        &divide_trait_id().name,
+        "`a : Div` states that values of type `a` can be divided by `/`.",
+    );
     fix_module.trait_env.add_trait(remainder_trait());
+    fix_module.add_builtin_doc(
+        &remainder_trait_id().name,
+        "`a : Rem` states that the remainder of division of values of type `a` can be calculated by `%`.",
+    );
     fix_module.trait_env.add_trait(less_than_trait());
+    fix_module.add_builtin_doc(
+        &less_than_trait_id().name,
+        "`a : LessThan` states that values of type `a` can be compared by `<` and `>`.",
+    );
     fix_module
         .trait_env
         .add_trait(less_than_or_equal_to_trait());
+    fix_module.add_builtin_doc(
+        &less_than_or_equal_to_trait_id().name,
+        "`a : LessThanOrEq` states that values of type `a` can be compared by `<=` and `>=`.",
+    );
 
     // Trait instances
 
@@ -157,9 +356,10 @@ pub fn make_std_mod() -> Program {
         for to in integral_types {
             let to_name = to.toplevel_tycon().unwrap().name.name.clone();
             let from_namespace = from.toplevel_tycon().unwrap().name.to_namespace();
-            fix_module.add_global_value(
+            fix_module.add_builtin_value(
                 FullName::new(&from_namespace, &format!("to_{}", to_name)),
                 cast_between_integral_function(from.clone(), to.clone()),
+                &cast_doc(from, to),
             );
         }
     }
@@ -168,9 +368,10 @@ pub fn make_std_mod() -> Program {
         for to in float_types {
             let to_name = to.toplevel_tycon().unwrap().name.name.clone();
             let from_namespace = from.toplevel_tycon().unwrap().name.to_namespace();
-            fix_module.add_global_value(
+            fix_module.add_builtin_value(
                 FullName::new(&from_namespace, &format!("to_{}", to_name)),
                 cast_between_float_function(from.clone(), to.clone()),
+                &cast_doc(from, to),
             );
         }
     }
@@ -179,9 +380,10 @@ pub fn make_std_mod() -> Program {
         for to in float_types {
             let to_name = to.toplevel_tycon().unwrap().name.name.clone();
             let from_namespace = from.toplevel_tycon().unwrap().name.to_namespace();
-            fix_module.add_global_value(
+            fix_module.add_builtin_value(
                 FullName::new(&from_namespace, &format!("to_{}", to_name)),
                 cast_int_to_float_function(from.clone(), to.clone()),
+                &cast_doc(from, to),
             );
         }
     }
@@ -190,155 +392,349 @@ pub fn make_std_mod() -> Program {
         for to in integral_types {
             let to_name = to.toplevel_tycon().unwrap().name.name.clone();
             let from_namespace = from.toplevel_tycon().unwrap().name.to_namespace();
-            fix_module.add_global_value(
+            fix_module.add_builtin_value(
                 FullName::new(&from_namespace, &format!("to_{}", to_name)),
                 cast_float_to_int_function(from.clone(), to.clone()),
+                &cast_doc(from, to),
             );
         }
     }
     // Bit operations
     for int_ty in integral_types {
         let ty_name = int_ty.toplevel_tycon().unwrap().name.name.clone();
-        fix_module.add_global_value(
+        fix_module.add_builtin_value(
             FullName::from_strs(&[STD_NAME, &ty_name], "shift_left"),
             shift_function(int_ty.clone(), true),
+            "Shifts bits to the left.",
         );
-        fix_module.add_global_value(
+        fix_module.add_builtin_value(
             FullName::from_strs(&[STD_NAME, &ty_name], "shift_right"),
             shift_function(int_ty.clone(), false),
+            "Shifts bits to the right.",
         );
-        fix_module.add_global_value(
+        fix_module.add_builtin_value(
             FullName::from_strs(&[STD_NAME, &ty_name], "bit_xor"),
             bitwise_operation_function(int_ty.clone(), BitOperationType::Xor),
+            "Calculates bitwise XOR of two values.",
         );
-        fix_module.add_global_value(
+        fix_module.add_builtin_value(
             FullName::from_strs(&[STD_NAME, &ty_name], "bit_and"),
             bitwise_operation_function(int_ty.clone(), BitOperationType::And),
+            "Calculates bitwise AND of two values.",
         );
-        fix_module.add_global_value(
+        fix_module.add_builtin_value(
             FullName::from_strs(&[STD_NAME, &ty_name], "bit_or"),
             bitwise_operation_function(int_ty.clone(), BitOperationType::Or),
+            "Calculates bitwise OR of two values.",
+        );
+    }
+    // Unaligned memory access, used to implement `ToBytes` and `FromBytes`.
+    for ty in integral_types.iter().chain(float_types.iter()) {
+        let ty_name = ty.toplevel_tycon().unwrap().name.name.clone();
+        fix_module.add_builtin_value(
+            FullName::from_strs(&[STD_NAME, &ty_name], "_unsafe_store_unaligned"),
+            store_unaligned_function(ty.clone()),
+            "Stores a value to a pointer, which may not be aligned.",
+        );
+        fix_module.add_builtin_value(
+            FullName::from_strs(&[STD_NAME, &ty_name], "_unsafe_load_unaligned"),
+            load_unaligned_function(ty.clone()),
+            "Loads a value from a pointer, which may not be aligned.",
         );
     }
 
     // Basic functions
-    fix_module.add_global_value(FullName::from_strs(&[STD_NAME], FIX_NAME), fix());
-    fix_module.add_global_value(FullName::from_strs(&[STD_NAME], "loop"), state_loop());
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
+        FullName::from_strs(&[STD_NAME], FIX_NAME),
+        fix(),
+        r#"
+`fix` enables you to make a recursive function locally. The idiom is: `fix $ |loop, var| -> (expression calls loop)`.
+
+```
+module Main;
+
+main : IO ();
+main = (
+    let fact = fix $ |loop, n| if n == 0 { 1 } else { n * loop (n-1) };
+    println $ fact(5).to_string // evaluates to 5 * 4 * 3 * 2 * 1 = 120
+);
+```
+"#,
+    );
+    fix_module.add_builtin_value(
+        FullName::from_strs(&[STD_NAME], "loop"),
+        state_loop(),
+        r#"
+`loop` enables you to make a loop. `LoopResult` is a union type defined as follows:
+
+```
+type LoopResult s r = unbox union { s: continue, r: break };
+```
+
+`loop` takes two arguments: the initial state of the loop `s0` and the loop body function `body`. It first calls `body` on `s0`. If `body` returns `break r`, then the loop ends and returns `r` as the result. If `body` returns `continue s`, then the loop calls again `body` on `s`.
+
+```
+module Main;
+
+main : IO ();
+main = (
+    let sum = (
+        loop((0, 0), |(i, sum)|
+            if i == 100 {
+                break $ sum
+            } else {
+                continue $ (i+1, sum+i)
+            }
+        )
+    );
+    println $ sum.to_string
+); // evaluates to 0 + 1 + ... + 99
+```
+"#,
+    );
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME], "unsafe_is_unique"),
         is_unique_function(),
+        r#"
+This function checks if a value is uniquely refernced by a name, and returns the result paired with the given value itself. If `a` is unboxed, the 0th component of the returned value is always `true`.
+
+NOTE: Using the return value of this function to branch and change the return value of your function may break the referential transparency of the function. If you want to panic when a value is shared, consider using `Debug::assert_unique!` instead.
+
+Example:
+
+```
+module Main;
+
+import Debug;
+
+main : IO ();
+main = (
+    // For unboxed value, it returns true even if the value is used later.
+    let int_val = 42;
+    let (unique, _) = int_val.unsafe_is_unique;
+    let use = int_val + 1;
+    eval assert_eq(|_|"fail: int_val is shared", unique, true);
+
+    // For boxed value, it returns true if the value isn't used later.
+    let arr = Array::fill(10, 10);
+    let (unique, arr) = arr.unsafe_is_unique;
+    let use = arr.@(0); // This `arr` is not the one passed to `is_unique`, but the one returned by `is_unique`.
+    eval assert_eq(|_|"fail: arr is shared", unique, true);
+
+    // Fox boxed value, it returns false if the value will be used later.
+    let arr = Array::fill(10, 10);
+    let (unique, _) = arr.unsafe_is_unique;
+    let use = arr.@(0);
+    eval assert_eq(|_|"fail: arr is unique", unique, false);
+
+    pure()
+);
+```
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME], "mark_threaded"),
         mark_threaded_function(),
+        "Traverses all objects reachable from the given object, and changes them into multi-threaded mode so that the reference counting on them will be done atomically.",
     );
 
     // Array
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "fill"),
         fill_array(),
+        r#"
+Creates an array filled with the initial value.
+The capacity is set to the same value as the length.
+Example: `fill(n, x) == [x, x, x, ..., x]` (of length `n`).
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "_unsafe_set"),
         unsafe_set_array(),
+        "Sets a value into an array, without uniqueness checking, bounds checking and releasing the old value.",
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "_unsafe_set_size"),
         unsafe_set_size_array(),
+        "Updates the length of an array, without uniqueness checking or validation of the given length value.",
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "_unsafe_get"),
         unsafe_get_array(),
+        "Gets a value from an array, without bounds checking and retaining the returned value.",
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "force_unique!"),
         force_unique_array(true),
+        r#"
+Force the uniqueness of an array.
+If the given array is shared, this function panics.
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "force_unique"),
         force_unique_array(false),
+        r#"
+Force the uniqueness of an array.
+If the given array is shared, this function returns the cloned array.
+"#,
     );
-    fix_module.add_global_value(array_getter_function_name(), read_array());
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
+        array_getter_function_name(),
+        read_array(),
+        "Returns an element of an array at an index.",
+    );
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "set"),
         write_array(),
+        r#"
+Updates a value of an element at an index of an array.
+This function clones the given array if it is shared.
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "set!"),
         write_array_unique(),
+        r#"
+Updates a value of an element at an index of an array.
+This function never clones the given array. If the given array is shared, this function panics.
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "empty"),
         make_empty(),
+        "Creates an empty array with specified capacity.",
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "mod"),
         mod_array(false),
+        r#"
+Modifies an array value by acting on an element at an index.
+This function clones the given array if it is shared.
+What is special about this function is that if you call `arr.mod(i, f)` when both of `arr` and `arr.@(i)` are unique, it is assured that `f` receives the element value which is unique. So `arr.mod(i, f)` is NOT equivalent to `let v = arr.@(i); arr.set(i, f(v))`.
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "mod!"),
         mod_array(true),
+        r#"
+Modifies an array value by acting on an element at an index.
+This function never clones the given array. If the array is shared, this function panics.
+What is special about this function is that if you call `arr.mod(i, f)` when both of `arr` and `arr.@(i)` are unique, it is assured that `f` receives the element value which is unique. So `arr.mod(i, f)` is NOT equivalent to `let v = arr.@(i); arr.set(i, f(v))`.
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "get_capacity"),
         get_capacity_array(),
+        "Returns the capacity of an array.",
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "get_size"),
         get_size_array(),
+        "Returns the length of an array.",
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(&[STD_NAME, ARRAY_NAME], "_get_ptr"),
         get_ptr_array(),
+        r#"
+Get the pointer to the memory region where elements are stored.
+Note that in case the array is not used after call of this function, the returned pointer will be already released.
+"#,
     );
     // Debug
-    fix_module.add_global_value(FullName::from_strs(&[STD_NAME], "abort"), abort_function());
+    fix_module.add_builtin_value(
+        FullName::from_strs(&[STD_NAME], "abort"),
+        abort_function(),
+        "Evaluating this value stops the execution of the program.",
+    );
 
     // Numeric constants.
     for type_name in [F32_NAME, F64_NAME] {
-        fix_module.add_global_value(
+        fix_module.add_builtin_value(
             FullName::from_strs(&[STD_NAME, type_name], "infinity"),
             infinity_value(type_name),
+            &format!("The positive infinity of `{}`.", type_name),
         );
-        fix_module.add_global_value(
+        let bits = if type_name == F32_NAME { 32 } else { 64 };
+        fix_module.add_builtin_value(
             FullName::from_strs(&[STD_NAME, type_name], "quiet_nan"),
             quiet_nan_value(type_name),
+            &format!(
+                "A floating number represented by `0{}` in binary.",
+                "1".repeat(bits - 1)
+            ),
         );
     }
 
     // FFI.
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(
             &[STD_NAME, FFI_NAME],
             "unsafe_get_retained_ptr_of_boxed_value",
         ),
         get_retained_ptr_of_boxed_value_function(),
+        r#"
+Get a retained pointer to a boxed value.
+This function is intended to be used to share ownership of Fix's boxed objects with C program.
+To release / retain the object in C program, call it on the function pointer obtained by `unsafe_get_release_function_of_boxed_value` and `unsafe_get_retain_function_of_boxed_value`.
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(
             &[STD_NAME, FFI_NAME],
             "unsafe_get_boxed_value_from_retained_ptr",
         ),
         get_boxed_value_from_retained_ptr_function(),
+        "Get a boxed value from a retained pointer.",
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(
             &[STD_NAME, FFI_NAME],
             "unsafe_get_release_function_of_boxed_value",
         ),
         get_release_function_of_boxed_value(),
+        r#"
+Get a function pointer (of type `void (*)(void*)`) to release a boxed value.
+This function is intended to be used with `unsafe_get_retained_ptr_of_boxed_value`.
+"#,
     );
-    fix_module.add_global_value(
+    fix_module.add_builtin_value(
         FullName::from_strs(
             &[STD_NAME, FFI_NAME],
             "unsafe_get_retain_function_of_boxed_value",
         ),
         get_retain_function_of_boxed_value(),
+        r#"
+Get a function pointer (of type `void (*)(void*)`) to retain a boxed value.
+This function is intended to be used with `unsafe_get_retained_ptr_of_boxed_value`.
+"#,
+    );
+    fix_module.add_builtin_value(
+        FullName::from_strs(&[STD_NAME, FFI_NAME], "_unsafe_get_c_callback_function"),
+        get_c_callback_function(false),
+        "Get a pointer to a C function which calls the function in the given boxed value. The boxed value is passed to the C function as the last argument.",
+    );
+    fix_module.add_builtin_value(
+        FullName::from_strs(
+            &[STD_NAME, FFI_NAME],
+            "_unsafe_get_c_callback_function_context_first",
+        ),
+        get_c_callback_function(true),
+        "Get a pointer to a C function which calls the function in the given boxed value. The boxed value is passed to the C function as the first argument.",
     );
 
     fix_module
 }
 
+// The document of a cast function between numeric types.
+fn cast_doc(from: &Rc<TypeNode>, to: &Rc<TypeNode>) -> String {
+    format!(
+        "Casts a value of `{}` into a value of `{}`.",
+        from.toplevel_tycon().unwrap().name.name,
+        to.toplevel_tycon().unwrap().name.name
+    )
+}
+
 pub fn array_getter_function_name() -> FullName {
     FullName::from_strs(&[STD_NAME, ARRAY_NAME], ARRAY_GETTER_FUNCTION_NAME)
 }
@@ -0,0 +1,196 @@
+// An implementation of `fix test` command.
+//
+// Tests are global values of type `IO ()` or `IOFail ()` whose names are `test` or start with `test_`.
+// All tests are compiled into one executable, and each test is run by a separate process of it,
+// so that a test which aborts (e.g., by `Debug::assert_eq`) does not stop the other tests.
+
+use std::{
+    process::{Command, Output},
+    time::Instant,
+};
+
+use super::*;
+
+const TEST_RUNNER_MODULE_NAME: &str = "TestRunner";
+const TEST_RUNNER_NAME: &str = "test_runner";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TestKind {
+    IO,     // `IO ()`
+    IOFail, // `IOFail ()`
+}
+
+// The result of running a test.
+pub struct TestResult {
+    // The full name of the test.
+    pub name: String,
+    pub output: Output,
+    // The duration of the test in seconds.
+    pub duration: f64,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.output.status.success()
+    }
+}
+
+// A function implementing `fix test` command.
+// If `filter` is given, only tests whose names contain it are run.
+// Returns the exit status of the command, which is 1 if any test fails.
+pub fn test_command(config: Configuration, filter: Option<String>) -> i32 {
+    let start = Instant::now();
+    let results = run_tests(config, filter);
+    if results.is_empty() {
+        return 0;
+    }
+    let failures = results
+        .iter()
+        .filter(|res| !res.passed())
+        .collect::<Vec<_>>();
+
+    // Report failures.
+    if !failures.is_empty() {
+        println!();
+        println!("failures:");
+        for res in &failures {
+            println!();
+            println!("---- {} ----", res.name);
+            match res.output.status.code() {
+                Some(code) => println!("exit status: {}", code),
+                None => println!("terminated by a signal"),
+            }
+            for out in [&res.output.stdout, &res.output.stderr] {
+                if out.len() > 0 {
+                    print!("{}", String::from_utf8_lossy(out));
+                }
+            }
+        }
+    }
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed; finished in {:.3}s",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        results.len() - failures.len(),
+        failures.len(),
+        start.elapsed().as_secs_f64()
+    );
+    if failures.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+// Build the tests found in the source files and run each of them by a separate process.
+// Returns the results in the order of the names of tests.
+pub fn run_tests(mut config: Configuration, filter: Option<String>) -> Vec<TestResult> {
+    // Find tests in the modules of the source files.
+    let mut program = load_file(&mut config.clone());
+    run_frontend(&mut program);
+    let modules = program.modules_of_files(&config.source_files);
+    let mut tests = find_tests(&program, &modules);
+    if let Some(filter) = &filter {
+        tests.retain(|(name, _)| name.to_string().contains(filter));
+    }
+    if tests.is_empty() {
+        println!("No tests found.");
+        return vec![];
+    }
+
+    // Build an executable which runs the test specified by the command line argument.
+    let source = test_runner_source(&tests, &modules);
+    let source_hash = format!("{:x}", md5::compute(&source));
+    save_temporary_source(&source, TEST_RUNNER_NAME, &source_hash);
+    config
+        .source_files
+        .push(temporary_source_path(TEST_RUNNER_NAME, &source_hash));
+    config.entry_point =
+        FullName::from_strs(&[TEST_RUNNER_MODULE_NAME], MAIN_FUNCTION_NAME).to_string();
+    config.out_file_path = Some(PathBuf::from(INTERMEDIATE_PATH).join(TEST_RUNNER_NAME));
+    let exec_path = config.get_output_executable_file_path();
+    build_file(config);
+
+    // Run tests.
+    println!("running {} tests", tests.len());
+    let mut results = vec![];
+    for (name, _) in &tests {
+        let name = name.to_string();
+        let test_start = Instant::now();
+        let output = match Command::new(&exec_path).arg(&name).output() {
+            Ok(output) => output,
+            Err(e) => error_exit(&format!("Failed to run test `{}`: {}", name, e)),
+        };
+        let res = TestResult {
+            name,
+            output,
+            duration: test_start.elapsed().as_secs_f64(),
+        };
+        println!(
+            "test {} ... {} ({:.3}s)",
+            res.name,
+            if res.passed() { "ok" } else { "FAILED" },
+            res.duration
+        );
+        results.push(res);
+    }
+    results
+}
+
+// Find tests defined in the given modules, sorted by their names.
+fn find_tests(program: &Program, modules: &HashSet<Name>) -> Vec<(FullName, TestKind)> {
+    let io_ty = make_io_unit_ty().to_string();
+    let iofail_ty = type_tyapp(
+        type_tycon(&tycon(FullName::from_strs(
+            &[STD_NAME, IO_NAME],
+            IOFAIL_NAME,
+        ))),
+        make_unit_ty(),
+    )
+    .to_string();
+    let mut tests = vec![];
+    for (name, gv) in &program.global_values {
+        if !modules.contains(&name.module()) {
+            continue;
+        }
+        if name.name != "test" && !name.name.starts_with("test_") {
+            continue;
+        }
+        if !gv.scm.vars.is_empty() || !gv.scm.preds.is_empty() {
+            continue;
+        }
+        let ty = gv.scm.ty.to_string();
+        if ty == io_ty {
+            tests.push((name.clone(), TestKind::IO));
+        } else if ty == iofail_ty {
+            tests.push((name.clone(), TestKind::IOFail));
+        }
+    }
+    tests.sort_by(|(a, _), (b, _)| a.to_string().cmp(&b.to_string()));
+    tests
+}
+
+// Create the source code of the module whose `main` runs the test named by the first command line argument.
+// An `IOFail ()` test fails with exit status 1 when it throws an error.
+fn test_runner_source(tests: &[(FullName, TestKind)], modules: &HashSet<Name>) -> String {
+    let mut modules = modules.iter().collect::<Vec<_>>();
+    modules.sort();
+    let mut source = format!("module {};\n", TEST_RUNNER_MODULE_NAME);
+    for module in modules {
+        source += &format!("import {};\n", module);
+    }
+    source += "\nmain : IO ();\nmain = (\n";
+    source += "    let name = (*Std::IO::get_args).@(1);\n";
+    for (name, kind) in tests {
+        let name = name.to_string();
+        let run = match kind {
+            TestKind::IO => name.clone(),
+            TestKind::IOFail => {
+                format!("Std::IO::IOFail::try(Std::IO::exit_with_msg(1), {})", name)
+            }
+        };
+        source += &format!("    if name == \"{}\" {{ {} }};\n", name, run);
+    }
+    source += "    Std::IO::exit_with_msg(1, \"Unknown test: \" + name)\n);\n";
+    source
+}
@@ -2453,6 +2453,998 @@ pub fn test_call_c() {
     run_source(&source, Configuration::develop_compiler());
 }
 
+#[test]
+#[serial]
+pub fn test_call_c_struct_by_value() {
+    // Test passing structs by value, `Bool` and small integers in CALL_C.
+    let source = r##"
+    module Main;
+
+    type DivT = unbox struct { quot : I32, rem : I32 };
+    type Point = unbox struct { x : F32, y : F32 };
+    type Rect = unbox struct { origin : Point, size : Point };
+    type Vec3 = unbox struct { x : F64, y : F64, z : F64 };
+    type CBool = Bool;
+
+    main : IO ();
+    main = (
+        let d = CALL_C[DivT div(I32, I32), 17_I32, 5_I32];
+        let _ = *println(d.@quot.to_string + " " + d.@rem.to_string);
+        let rect = Rect { origin : Point { x : 1.0_F32, y : 2.0_F32 }, size : Point { x : 3.0_F32, y : 4.0_F32 } };
+        let rect = CALL_C[Rect rect_grow(Rect, F32), rect, 2.0_F32];
+        let _ = *println((rect.@origin.@x + rect.@size.@y).to_I64.to_string);
+        let v = CALL_C[Vec3 vec3_scale(Vec3, F64), Vec3 { x : 1.0, y : 2.0, z : 3.0 }, 2.0];
+        let _ = *println((v.@x + v.@y + v.@z).to_I64.to_string);
+        let even = CALL_C[CBool is_even(I64), 10];
+        let _ = *println(if even { "true" } else { "false" });
+        let _ = *println(CALL_C[I32 widen(I8), -3_I8].to_string);
+        pure()
+    );
+    "##;
+    let c_source = r##"
+    #include <stdbool.h>
+    #include <stdint.h>
+
+    typedef struct { float x, y; } point;
+    typedef struct { point origin, size; } rect;
+    typedef struct { double x, y, z; } vec3;
+
+    rect rect_grow(rect r, float k) {
+        r.size.x *= k;
+        r.size.y *= k;
+        return r;
+    }
+
+    vec3 vec3_scale(vec3 v, double k) {
+        vec3 res = { v.x * k, v.y * k, v.z * k };
+        return res;
+    }
+
+    bool is_even(int64_t n) { return n % 2 == 0; }
+
+    int32_t widen(int8_t n) { return n; }
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_call_c_struct", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_call_c_struct");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    std::fs::write(out_dir.join("structs.c"), c_source).unwrap();
+
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_call_c_struct", &source_hash)];
+    config.set_output_file_type(OutputFileType::Object);
+    config.out_file_path = Some(out_dir.join("main.o"));
+    build_file(config);
+    let exec_path = out_dir.join("main");
+    let output = std::process::Command::new("gcc")
+        .arg("-no-pie")
+        .arg("-o")
+        .arg(&exec_path)
+        .arg(out_dir.join("main.o"))
+        .arg(out_dir.join(RUNTIME_OBJECT_FILE_NAME))
+        .arg(out_dir.join("structs.c"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = std::process::Command::new(&exec_path).output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "3 2\n9\n12\ntrue\n-3\n"
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_call_c_struct_out_of_registers() {
+    // Test passing structs by value in CALL_C after the argument registers are used up by preceding arguments.
+    // On x86-64, a struct is passed in memory if the remaining registers cannot hold it, while the following arguments still use registers.
+    let source = r##"
+    module Main;
+
+    type TwoInts = unbox struct { a : I64, b : I64 };
+    type Mixed = unbox struct { n : I64, x : F64 };
+
+    main : IO ();
+    main = (
+        let t = TwoInts { a : 2, b : 3 };
+        let _ = *println(CALL_C[I64 after_ints(I64, I64, I64, I64, I64, TwoInts, I64), 1, 1, 1, 1, 1, t, 4].to_string);
+        let m = Mixed { n : 2, x : 0.5 };
+        let res = CALL_C[F64 after_doubles(F64, F64, F64, F64, F64, F64, F64, F64, Mixed, I64), 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, m, 3];
+        let _ = *println(res.to_I64.to_string);
+        let res = CALL_C[F64 mixed_in_registers(I64, I64, I64, I64, I64, Mixed, F64), 1, 1, 1, 1, 1, m, 4.0];
+        let _ = *println(res.to_I64.to_string);
+        pure()
+    );
+    "##;
+    let c_source = r##"
+    #include <stdint.h>
+
+    typedef struct { int64_t a, b; } two_ints;
+    typedef struct { int64_t n; double x; } mixed;
+
+    int64_t after_ints(int64_t a, int64_t b, int64_t c, int64_t d, int64_t e, two_ints t, int64_t f) {
+        return a + b + c + d + e + t.a * 100 + t.b * 1000 + f * 10000;
+    }
+
+    double after_doubles(double d1, double d2, double d3, double d4, double d5, double d6, double d7, double d8, mixed m, int64_t k) {
+        return d1 + d2 + d3 + d4 + d5 + d6 + d7 + d8 + m.n * 10 + m.x * 100 + k * 1000;
+    }
+
+    double mixed_in_registers(int64_t a, int64_t b, int64_t c, int64_t d, int64_t e, mixed m, double y) {
+        return a + b + c + d + e + m.n * 10 + m.x * 100 + y * 1000;
+    }
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_call_c_struct_regs", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_call_c_struct_regs");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    std::fs::write(out_dir.join("structs.c"), c_source).unwrap();
+
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path(
+        "test_call_c_struct_regs",
+        &source_hash,
+    )];
+    config.set_output_file_type(OutputFileType::Object);
+    config.out_file_path = Some(out_dir.join("main.o"));
+    build_file(config);
+    let exec_path = out_dir.join("main");
+    let output = std::process::Command::new("gcc")
+        .arg("-no-pie")
+        .arg("-o")
+        .arg(&exec_path)
+        .arg(out_dir.join("main.o"))
+        .arg(out_dir.join(RUNTIME_OBJECT_FILE_NAME))
+        .arg(out_dir.join("structs.c"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = std::process::Command::new(&exec_path).output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "43205\n3078\n4075\n"
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_c_callback() {
+    // Test Std::FFI::unsafe_make_c_callback.
+    let source = r#"
+            module Main;
+            import Debug;
+
+            main : IO ();
+            main = (
+                // Comparator of `qsort_r` in glibc, which takes the context as the last argument.
+                let descending = true;
+                let compare = |a, b| (
+                    let c = CALL_C[I32 memcmp(Ptr, Ptr, U64), a, b, 1_U64];
+                    if descending { 0_I32 - c } else { c }
+                );
+                let callback = unsafe_make_c_callback(compare);
+                let arr = [5_U8, 3_U8, 9_U8, 1_U8, 7_U8];
+                eval CALL_C[() qsort_r(Ptr, U64, U64, Ptr, Ptr), arr._get_ptr, 5_U64, 1_U64, callback.@function, callback.@context];
+                eval assert_eq(|_|"fail: qsort_r", arr, [9_U8, 7_U8, 5_U8, 3_U8, 1_U8]);
+                let _ = *release_c_callback(callback);
+
+                // A callback of type `void (*)(void*)`, which runs an IO action.
+                // `fixruntime_release_c_callback(f, ctx)` just calls `f(ctx)`.
+                let callback = unsafe_make_c_callback_context_first(|()| println("Hello from C!"));
+                eval CALL_C[() fixruntime_release_c_callback(Ptr, Ptr), callback.@function, callback.@context];
+                release_c_callback(callback)
+            );
+        "#;
+    run_source(&source, Configuration::develop_compiler());
+}
+
+#[test]
+#[serial]
+pub fn test_c_callback_unsupported_signature() {
+    // An unsupported signature of a C callback is reported at the call of `unsafe_make_c_callback`.
+    let source = r#"
+            module Main;
+
+            main : IO ();
+            main = (
+                let callback = unsafe_make_c_callback(|s : String| s.get_size);
+                release_c_callback(callback)
+            );
+        "#;
+    let err = catch_compile_error_in(|| run_source(&source, Configuration::develop_compiler()))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.msg,
+        "Cannot make a C callback from a function of type `Std::String -> Std::I64`: \
+        the parameter type `Std::String` cannot be passed from C."
+    );
+    let src = err.srcs.first().unwrap();
+    assert_eq!(&source[src.start..src.end], "unsafe_make_c_callback");
+
+    // A value which is not a function.
+    let source = r#"
+            module Main;
+
+            main : IO ();
+            main = (
+                let callback = unsafe_make_c_callback(42);
+                release_c_callback(callback)
+            );
+        "#;
+    let err = catch_compile_error_in(|| run_source(&source, Configuration::develop_compiler()))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.msg,
+        "Cannot make a C callback from a value of type `Std::I64`: it is not a function."
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_dynamic_library() {
+    // Test DynamicLibrary module and indirect CALL_C.
+    let source = r#"
+            module Main;
+            import Debug;
+            import DynamicLibrary;
+
+            type LDiv = unbox struct { quot : I64, rem : I64 };
+
+            main : IO ();
+            main = (
+                do {
+                    let libm = *DynamicLibrary::open("libm.so.6");
+                    let cos = *libm.get_symbol("cos");
+                    let x = libm.borrow(|_| CALL_C[F64 *(F64), cos, 0.0]);
+                    eval assert_eq(|_|"case 1", x, 1.0);
+
+                    let libc = *DynamicLibrary::open("libc.so.6");
+                    let abs = *libc.get_symbol("abs");
+                    let y = libc.borrow(|_| CALL_C[I32 *(I32), abs, -3_I32]);
+                    eval assert_eq(|_|"case 2", y, 3_I32);
+
+                    // A struct returned by value.
+                    let ldiv = *libc.get_symbol("ldiv");
+                    let LDiv { quot : q, rem : r } = libc.borrow(|_| CALL_C[LDiv *(I64, I64), ldiv, 17, 5]);
+                    eval assert_eq(|_|"case 3", (q, r), (3, 2));
+
+                    // Variadic function.
+                    let snprintf = *libc.get_symbol("snprintf");
+                    let buf : Array U8 = Array::fill(16, 0_U8);
+                    let len = "%d-%d".borrow_c_str(|fmt| libc.borrow(|_|
+                        CALL_C[I32 *(Ptr, U64, Ptr, ...), snprintf, buf._get_ptr, 16_U64, fmt, 4_I32, 2_I32]
+                    ));
+                    eval assert_eq(|_|"case 4", len, 3_I32);
+                    eval assert_eq(|_|"case 5", String::_unsafe_from_c_str(buf), "4-2");
+
+                    let res = *DynamicLibrary::open("libdoesnotexist.so").to_result.lift;
+                    eval assert_eq(|_|"case 6", res.is_err, true);
+                    let res = *libc.get_symbol("symbol_does_not_exist").to_result.lift;
+                    eval assert_eq(|_|"case 7", res.is_err, true);
+
+                    pure()
+                }.try(exit_with_msg(1))
+            );
+        "#;
+    run_source(&source, Configuration::develop_compiler());
+}
+
+#[test]
+#[serial]
+pub fn test_toolchain_options() {
+    // Test linking a library in a non-standard directory by `library_paths` and `rpaths`, with a C compiler other than `gcc`.
+    let source = r##"
+    module Main;
+
+    main : IO ();
+    main = println(CALL_C[I64 triple(I64), 14].to_string);
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_toolchain_options", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_toolchain_options");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let out_dir = out_dir.canonicalize().unwrap();
+    std::fs::write(
+        out_dir.join("triple.c"),
+        "#include <stdint.h>\nint64_t triple(int64_t x) { return 3 * x; }\n",
+    )
+    .unwrap();
+    let output = std::process::Command::new("cc")
+        .arg("-shared")
+        .arg("-fpic")
+        .arg("-o")
+        .arg(out_dir.join("libtriple.so"))
+        .arg(out_dir.join("triple.c"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path(
+        "test_toolchain_options",
+        &source_hash,
+    )];
+    config.out_file_path = Some(out_dir.join("main"));
+    config.add_dyanmic_library("triple");
+    config.toolchain.cc = "cc".to_string();
+    config.toolchain.cflags.push("-O2".to_string());
+    config.toolchain.library_paths.push(out_dir.clone());
+    config.toolchain.rpaths.push(out_dir.clone());
+    build_file(config);
+    let output = std::process::Command::new(out_dir.join("main"))
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "42\n");
+}
+
+#[test]
+#[serial]
+pub fn test_toolchain_failure() {
+    // A linker which exits with a non-zero status is reported as an error.
+    let source = r##"
+    module Main;
+
+    main : IO ();
+    main = println("Hello");
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_toolchain_failure", &source_hash);
+    let mut config = Configuration::develop_compiler();
+    config.source_files = vec![temporary_source_path(
+        "test_toolchain_failure",
+        &source_hash,
+    )];
+    config.toolchain.linker = Some("false".to_string());
+    let err = catch_compile_error_in(|| build_file(config.clone()))
+        .err()
+        .unwrap();
+    assert!(err.msg.starts_with("`false "));
+    assert!(err.msg.contains("failed (exit status 1)."));
+
+    // A command which cannot be started is also reported.
+    config.toolchain.linker = Some("fix-no-such-linker".to_string());
+    let err = catch_compile_error_in(|| build_file(config.clone()))
+        .err()
+        .unwrap();
+    assert!(err.msg.starts_with("Failed to run `fix-no-such-linker`"));
+}
+
+#[test]
+#[serial]
+pub fn test_toolchain_fingerprint() {
+    // Cached runtime binaries are invalidated when the linker or its options change.
+    let base = Toolchain::default();
+    let mut changed = vec![];
+    let mut toolchain = base.clone();
+    toolchain.linker = Some("clang".to_string());
+    changed.push(toolchain);
+    let mut toolchain = base.clone();
+    toolchain.ldflags.push("-fuse-ld=lld".to_string());
+    changed.push(toolchain);
+    let mut toolchain = base.clone();
+    toolchain.library_paths.push(PathBuf::from("/opt/lib"));
+    changed.push(toolchain);
+    let mut toolchain = base.clone();
+    toolchain.rpaths.push(PathBuf::from("/opt/lib"));
+    changed.push(toolchain);
+    for toolchain in changed {
+        assert_ne!(toolchain.fingerprint(), base.fingerprint());
+    }
+}
+
+#[test]
+#[serial]
+pub fn test_incremental_build() {
+    // Test that `--incremental` reuses object files of unchanged modules.
+    let make_source = |message: &str| {
+        format!(
+            r##"
+    module Main;
+
+    greeting : String;
+    greeting = "{}";
+
+    main : IO ();
+    main = (
+        let args = *get_args;
+        println(greeting + ", " + args.@(1) + "!")
+    );
+    "##,
+            message
+        )
+    };
+    let unit_objects = |prefix: &str| {
+        std::fs::read_dir(CODEGEN_UNITS_CACHE_PATH)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_str().unwrap().to_string())
+            .filter(|name| name.starts_with(prefix))
+            .collect::<HashSet<_>>()
+    };
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_incremental_build");
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    let mut outputs = vec![];
+    let mut std_objects_list = vec![];
+    let mut main_objects_list = vec![];
+    for message in ["Hello", "Hello", "Goodbye"] {
+        let source = make_source(message);
+        let source_hash = format!("{:x}", md5::compute(&source));
+        save_temporary_source(&source, "test_incremental_build", &source_hash);
+        let mut config = Configuration::release();
+        config.source_files = vec![temporary_source_path(
+            "test_incremental_build",
+            &source_hash,
+        )];
+        config.out_file_path = Some(out_dir.join("main"));
+        config.incremental = true;
+        build_file(config);
+        let output = std::process::Command::new(out_dir.join("main"))
+            .arg("Fix")
+            .output()
+            .unwrap();
+        outputs.push(String::from_utf8(output.stdout).unwrap());
+        std_objects_list.push(unit_objects("module.Std."));
+        main_objects_list.push(unit_objects("module.Main."));
+    }
+    assert_eq!(
+        outputs,
+        vec!["Hello, Fix!\n", "Hello, Fix!\n", "Goodbye, Fix!\n"]
+    );
+    // Changing `Main` module does not regenerate the object file of `Std` module.
+    assert_eq!(std_objects_list[0], std_objects_list[1]);
+    assert_eq!(std_objects_list[1], std_objects_list[2]);
+    // Only the latest object file of each module is kept.
+    assert_eq!(std_objects_list[2].len(), 1);
+    assert_eq!(main_objects_list[0], main_objects_list[1]);
+    assert_eq!(main_objects_list[2].len(), 1);
+    assert!(main_objects_list[2].is_disjoint(&main_objects_list[1]));
+
+    // `fix clean --cache-only` removes the cached object files.
+    clean_command(true);
+    assert!(!PathBuf::from(CODEGEN_UNITS_CACHE_PATH).exists());
+    assert!(PathBuf::from(INTERMEDIATE_PATH).exists());
+}
+
+#[test]
+#[serial]
+pub fn test_parallel_codegen_error() {
+    // An error on a worker thread of parallel code generation is raised on the calling thread, where it can be caught.
+    let jobs = (0..4)
+        .map(|i| CompileJob {
+            name: format!("part{}", i),
+            bitcode: b"not a bitcode".to_vec(),
+            obj_path: PathBuf::from(INTERMEDIATE_PATH).join(format!("broken.part{}.o", i)),
+        })
+        .collect::<Vec<_>>();
+    let mut config = Configuration::release();
+    config.codegen_jobs = 2;
+    let err = catch_compile_error_in(|| compile_jobs(&jobs, &config))
+        .err()
+        .unwrap();
+    assert!(err.msg.starts_with("Failed to load codegen unit `part0`"));
+}
+
+#[test]
+#[serial]
+pub fn test_parallel_codegen() {
+    // Test building a program split into multiple LLVM modules by `-j`.
+    let source = r##"
+    module Main;
+    import Debug;
+
+    fib : I64 -> I64;
+    fib = |n| if n <= 1 { n } else { fib(n - 1) + fib(n - 2) };
+
+    table : Array I64;
+    table = Iterator::range(0, 20).map(fib).to_array;
+
+    main : IO ();
+    main = (
+        let args = *get_args;
+        eval assert_eq(|_|"case 1", args.get_size, 2);
+        eval assert_eq(|_|"case 2", table.@(10), 55);
+        println(table.to_iter.map(to_string).join(","))
+    );
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_parallel_codegen", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_parallel_codegen");
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_parallel_codegen", &source_hash)];
+    config.out_file_path = Some(out_dir.join("main"));
+    config.codegen_jobs = 4;
+    build_file(config);
+    let output = std::process::Command::new(out_dir.join("main"))
+        .arg("arg")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "0,1,1,2,3,5,8,13,21,34,55,89,144,233,377,610,987,1597,2584,4181\n"
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_runtime_inlining() {
+    // Test that runtime functions are inlined into Fix code.
+    // Byte conversions are defined in LLVM IR by the compiler, so they are inlined even if the runtime bitcode is not available.
+    let source = r##"
+    module Main;
+
+    main : IO ();
+    main = (
+        let bytes = 258_U64.to_bytes;
+        eval *println(bytes.to_iter.map(to_string).join(","));
+        let n : I64 = "123".from_string.as_ok;
+        println(n.to_string)
+    );
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_runtime_inlining", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_runtime_inlining");
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_runtime_inlining", &source_hash)];
+    config.out_file_path = Some(out_dir.join("main"));
+    config.emit_llvm = true;
+    build_file(config.clone());
+    let output = std::process::Command::new(out_dir.join("main"))
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "2,1,0,0,0,0,0,0\n123\n"
+    );
+
+    let pre_opt_ir = std::fs::read_to_string(out_dir.join("pre_opt_main")).unwrap();
+    let post_opt_ir = std::fs::read_to_string(out_dir.join("post_opt_main")).unwrap();
+    // Conversions to bytes are built-in functions which do not call the runtime.
+    assert!(!pre_opt_ir.contains("@fixruntime_u64_to_bytes("));
+
+    // Functions written in C are linked only if the runtime bitcode for this configuration is built and readable.
+    // Otherwise the bitcode file is left empty, and a warning is shown.
+    let runtime_bitcode_linked = std::fs::metadata(runtime_bitcode_path(&config))
+        .map(|m| m.len() > 0)
+        .unwrap_or(false);
+    let strtoll_defined = pre_opt_ir
+        .lines()
+        .any(|line| line.starts_with("define") && line.contains("@fixruntime_strtoll_10("));
+    assert_eq!(strtoll_defined, runtime_bitcode_linked);
+    if runtime_bitcode_linked {
+        assert!(!post_opt_ir.contains("call i64 @fixruntime_strtoll_10("));
+    }
+}
+
+#[test]
+#[serial]
+pub fn test_pgo_profile() {
+    // Test reading a profile in the text format of `llvm-profdata`.
+    let text = r#"# IR level Instrumentation Flag
+:ir
+Main:Main::hot
+# Func Hash:
+1234
+# Num Counters:
+2
+# Counter Values:
+10
+3
+
+Main:Get#Main::table
+# Func Hash:
+5678
+# Num Counters:
+1
+# Counter Values:
+1
+
+Main::cold#funptr1
+# Func Hash:
+42
+# Num Counters:
+1
+# Counter Values:
+0
+# Num Value Kinds:
+1
+
+main
+# Func Hash:
+7
+# Num Counters:
+1
+# Counter Values:
+1
+"#;
+    let profile = Profile::parse_text(text);
+    let name = |s: &str| FullName::from_strs(&["Main"], s);
+    assert!(profile.is_executed_symbol(&name("hot")));
+    assert!(profile.is_executed_symbol(&name("table")));
+    assert!(!profile.is_executed_symbol(&name("cold")));
+    assert!(!profile.is_executed_symbol(&name("unknown")));
+}
+
+#[test]
+#[serial]
+pub fn test_pgo_build() {
+    // Test building with a profile collected by an instrumented build.
+    // Skipped if clang or llvm-profdata is not available.
+    for tool in ["clang", "llvm-profdata"] {
+        if std::process::Command::new(tool)
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+    }
+    let source = r##"
+    module Main;
+
+    fib : I64 -> I64;
+    fib = |n| if n <= 1 { n } else { fib(n - 1) + fib(n - 2) };
+
+    unused : I64 -> I64 -> I64;
+    unused = |x, y| x * y;
+
+    main : IO ();
+    main = (
+        let args = *get_args;
+        // `unused` is referenced only as a value, so it can be left curried without changing `main`.
+        let f = unused;
+        if args.get_size > 1 { println(f(2, 3).to_string) };
+        println(fib(20).to_string)
+    );
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_pgo_build", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_pgo_build");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    // Build an instrumented executable and run it.
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_pgo_build", &source_hash)];
+    config.out_file_path = Some(out_dir.join("instrumented"));
+    config.profile_generate = Some(out_dir.clone());
+    build_file(config);
+    let output = std::process::Command::new(out_dir.join("instrumented"))
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "6765\n");
+
+    // Merge the raw profiles.
+    let profraws = std::fs::read_dir(&out_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|e| e == "profraw").unwrap_or(false))
+        .collect::<Vec<_>>();
+    assert!(!profraws.is_empty());
+    let profdata = out_dir.join("fix.profdata");
+    let status = std::process::Command::new("llvm-profdata")
+        .arg("merge")
+        .arg("-o")
+        .arg(&profdata)
+        .args(&profraws)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // Build an optimized executable with the profile.
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_pgo_build", &source_hash)];
+    config.out_file_path = Some(out_dir.join("optimized"));
+    config.profile_use = Some(profdata.clone());
+    let profile = Profile::read(&profdata, &config);
+    assert!(profile.is_executed_symbol(&FullName::from_strs(&["Main"], "main")));
+    build_file(config.clone());
+    let output = std::process::Command::new(out_dir.join("optimized"))
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "6765\n");
+
+    // `unused` is not uncurried, and the functions generated from the same sources match the profile.
+    let mut program = load_file(&mut config.clone());
+    prepare_program(&mut program, &config);
+    let not_to_uncurry = profile.symbols_not_to_uncurry(&program);
+    let symbols = program
+        .instantiated_global_symbols
+        .keys()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    let is_unused = |name: &FullName| name.to_string().starts_with("Main::unused#");
+    assert!(not_to_uncurry.iter().any(is_unused));
+    assert!(!symbols
+        .iter()
+        .any(|name| name.starts_with("Main::unused#") && name.contains("#funptr")));
+    assert!(symbols
+        .iter()
+        .any(|name| name.starts_with("Main::fib#") && name.contains("#funptr")));
+    let warnings = compile_bitcode_with_pgo(
+        &PathBuf::from(INTERMEDIATE_PATH).join("a.bc"),
+        &out_dir.join("optimized.o"),
+        &config,
+    );
+    assert!(!warnings.contains("mismatch"), "{}", warnings);
+    assert!(!warnings.contains("out of date"), "{}", warnings);
+}
+
+#[test]
+#[serial]
+pub fn test_cross_compilation() {
+    // Test emitting object files for targets other than the host by `--target`.
+    let source = r##"
+    module Main;
+
+    main : IO ();
+    main = println(Iterator::range(0, 10).fold(0, Add::add).to_string);
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_cross_compilation", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_cross_compilation");
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    let make_config = |triple: &str| {
+        let mut config = Configuration::release();
+        config.source_files = vec![temporary_source_path(
+            "test_cross_compilation",
+            &source_hash,
+        )];
+        config.set_target_triple(triple);
+        config.set_threaded();
+        config.set_output_file_type(OutputFileType::Object);
+        config.out_file_path = Some(out_dir.join(format!("{}.o", triple)));
+        config
+    };
+
+    // The `e_machine` field of the ELF header of the object file and the runtime for each target.
+    for (triple, cpu, e_machine) in [
+        ("aarch64-unknown-linux-gnu", None, 183_u16),
+        ("aarch64-unknown-linux-gnu", Some("cortex-a72"), 183_u16),
+        ("riscv64-unknown-linux-gnu", None, 243_u16),
+    ] {
+        // The runtime is compiled by clang, which needs the C library headers of the target.
+        let header_check = std::process::Command::new("clang")
+            .arg(format!("--target={}", triple))
+            .args(["-fsyntax-only", "-x", "c", "-"])
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                use std::io::Write;
+                child
+                    .stdin
+                    .take()
+                    .unwrap()
+                    .write_all(b"#include <stdio.h>\n#include <sys/wait.h>\n")?;
+                child.wait()
+            });
+        if !header_check.map_or(false, |status| status.success()) {
+            eprintln!(
+                "skipped {}: clang cannot compile for it on this machine.",
+                triple
+            );
+            continue;
+        }
+        let mut config = make_config(triple);
+        config.target_cpu = cpu.map(|cpu| cpu.to_string());
+        config.toolchain.cc = "clang".to_string();
+        let obj_path = config.out_file_path.clone().unwrap();
+        build_file(config);
+
+        for path in [obj_path, out_dir.join(RUNTIME_OBJECT_FILE_NAME)] {
+            let obj = std::fs::read(&path).unwrap();
+            assert_eq!(&obj[0..4], b"\x7fELF");
+            assert_eq!(
+                u16::from_le_bytes([obj[18], obj[19]]),
+                e_machine,
+                "{}",
+                path.to_string_lossy()
+            );
+        }
+    }
+
+    // The host's C compiler cannot build the runtime for another target.
+    let mut config = make_config("aarch64-unknown-linux-gnu");
+    config.toolchain.cc = "gcc".to_string();
+    let err = catch_compile_error_in(|| build_file(config)).err().unwrap();
+    assert!(err
+        .msg
+        .starts_with("Cannot build for `aarch64-unknown-linux-gnu` by `gcc`"));
+}
+
+#[test]
+#[serial]
+pub fn test_type_check_cache() {
+    // Test that results of type-checking are saved to a single cache file and reused.
+    let source = r##"
+    module Main;
+
+    add_twice : I64 -> I64 -> I64;
+    add_twice = |x, y| x + y + y;
+
+    main : IO ();
+    main = println(add_twice(1, 2).to_string);
+    "##;
+    let cache_path = PathBuf::from(TYPE_CHECK_CACHE_PATH);
+    clear_type_check_cache();
+    assert!(!cache_path.exists());
+
+    run_source(&source, Configuration::develop_compiler());
+    let cache = fs::read(&cache_path).unwrap();
+    assert!(cache.starts_with(b"fix type check cache "));
+
+    // Nothing is added to the cache when the same program is compiled again.
+    run_source(&source, Configuration::develop_compiler());
+    assert_eq!(fs::read(&cache_path).unwrap(), cache);
+
+    // A cache file of an unknown format is ignored and overwritten.
+    fs::write(&cache_path, "unknown format").unwrap();
+    run_source(&source, Configuration::develop_compiler());
+    assert!(fs::read(&cache_path)
+        .unwrap()
+        .starts_with(b"fix type check cache "));
+
+    // `fix clean --cache-only` removes only the cache.
+    clean_command(true);
+    assert!(!cache_path.exists());
+    assert!(PathBuf::from(DOT_FIXLANG).exists());
+}
+
+#[test]
+#[serial]
+pub fn test_type_check_cache_concurrent_save() {
+    // Entries saved concurrently are all kept, since saving the cache is serialized by a lock.
+    clear_type_check_cache();
+    let keys = (0..8).map(|i| format!("{:032x}", i)).collect::<Vec<_>>();
+    std::thread::scope(|s| {
+        for key in &keys {
+            s.spawn(move || {
+                let mut cache = TypeCheckCache::default();
+                let te = TypedExpr::from_expr(expr_int_lit(0, make_i64_ty(), None));
+                cache.insert(key.clone(), &te);
+                cache.save();
+            });
+        }
+    });
+    let mut cache = TypeCheckCache::default();
+    for key in &keys {
+        assert!(cache.get(key).is_some());
+    }
+    clear_type_check_cache();
+}
+
+#[test]
+#[serial]
+pub fn test_time_passes() {
+    // Test that `--time-passes=json` reports durations of compilation phases.
+    let source = r##"
+    module Main;
+
+    main : IO ();
+    main = println("Hello");
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_time_passes", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_time_passes");
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_time_passes", &source_hash)];
+    config.out_file_path = Some(out_dir.join("main"));
+    config.time_passes = Some(TimePassesFormat::Json);
+    enable_time_passes();
+    build_file(config);
+
+    let report = fs::read_to_string(out_dir.join("time_passes_main.json")).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+    let names = report["passes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    for name in [
+        "parse",
+        "resolve_imports",
+        "type_env",
+        "trait_validation",
+        "instantiation",
+        "llvm_ir_generation",
+        "llvm_optimization",
+        "llvm_passes_early",
+        "llvm_passes_middle",
+        "llvm_passes_late",
+        "llvm_global_dce",
+        "object_emission",
+        "link",
+    ] {
+        assert!(
+            names.contains(&name.to_string()),
+            "{} is not reported",
+            name
+        );
+    }
+    assert!(report["counters"]["instantiated_symbols"].as_u64().unwrap() > 0);
+    assert!(report["total_seconds"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+#[serial]
+pub fn test_library_api() {
+    // Test the programmatic API of the compiler.
+    let source = r##"
+    module Main;
+
+    add_one : I64 -> I64;
+    add_one = |x| x + 1;
+
+    main : IO ();
+    main = (
+        eval *eprintln("to stderr");
+        println(add_one(41).to_string)
+    );
+    "##;
+    let path = PathBuf::from("test_library_api.fix");
+    let sources = [(path.clone(), source.to_string())];
+
+    // Type-check and query the types of global values.
+    let mut program = FixProgram::load_from_sources(&sources, Configuration::release()).unwrap();
+    assert!(program.check().is_empty());
+    let add_one_type = program.type_of("Main::add_one").unwrap().unwrap();
+    assert!(add_one_type.contains("I64"));
+    let types = program.global_value_types().unwrap();
+    assert!(types.iter().any(|(name, _)| name == "Main::main"));
+
+    // Run as a child process with captured outputs.
+    let output = program.run().unwrap();
+    assert_eq!(output.exit_code, Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "42\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "to stderr\n");
+
+    // Build an executable.
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_library_api");
+    fs::create_dir_all(&out_dir).unwrap();
+    let mut config = Configuration::release();
+    config.out_file_path = Some(out_dir.join("main"));
+    let program = FixProgram::load_from_sources(&sources, config).unwrap();
+    let exec_path = program.build().unwrap();
+    let output = std::process::Command::new(&exec_path).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "42\n");
+
+    // Type errors are reported as diagnostics.
+    let bad_source = source.replace("x + 1", "x + \"one\"");
+    let mut program =
+        FixProgram::load_from_sources(&[(path.clone(), bad_source)], Configuration::release())
+            .unwrap();
+    let errors = program.check();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].locations[0].file, path);
+    assert_eq!(errors[0].locations[0].line, 5);
+    assert!(program.run().is_err());
+
+    // Parse errors are reported when loading.
+    let res = FixProgram::load_from_sources(
+        &[(path, "module Main; main = ;".to_string())],
+        Configuration::release(),
+    );
+    assert!(res.is_err());
+}
+
 #[test]
 #[serial]
 pub fn test95() {
@@ -5144,6 +6136,28 @@ pub fn test_get_args() {
     run_source(&source, Configuration::develop_compiler());
 }
 
+#[test]
+#[serial]
+pub fn test_run_program_args() {
+    // Test that arguments of `fix run -- args...` are passed to the program.
+    let source = r##"
+    module Main;
+    import Debug;
+
+    main : IO ();
+    main = (
+        let args = *get_args;
+        eval assert_eq(|_|"case 1", args.get_size, 3);
+        eval assert_eq(|_|"case 2", args.@(1), "arg1");
+        eval assert_eq(|_|"case 3", args.@(2), "--arg2");
+        pure()
+    );
+    "##;
+    let mut config = Configuration::develop_compiler();
+    config.program_args = vec!["arg1".to_string(), "--arg2".to_string()];
+    run_source(&source, config);
+}
+
 #[test]
 #[serial]
 pub fn test_regression_issue_14() {
@@ -5471,3 +6485,1072 @@ pub fn test_regexp() {
     "##;
     run_source(&source, Configuration::develop_compiler());
 }
+
+#[test]
+#[serial]
+pub fn test_check_unused_global_values() {
+    // `fix check` type-checks global values which are not used from `main`, and does not require `main`.
+    let source = r##"
+    module Lib;
+
+    unused : I64 -> String;
+    unused = |x| (x + 1).to_string;
+
+    impl I64 : Zero {
+        zero = 0;
+    }
+
+    trait a : Zero {
+        zero : a;
+    }
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "check_lib", &source_hash);
+    let mut config = Configuration::develop_compiler();
+    config.source_files = vec![temporary_source_path("check_lib", &source_hash)];
+    check_file(config);
+}
+
+#[test]
+#[serial]
+pub fn test_check_unused_global_value_with_type_error() {
+    // `fix check` rejects a type error in a global value which is not used from `main`.
+    let source = r##"
+    module Main;
+
+    unused : I64 -> String;
+    unused = |x| x + 1;
+
+    main : IO ();
+    main = pure();
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "check_unused_error", &source_hash);
+    let mut config = Configuration::develop_compiler();
+    config.source_files = vec![temporary_source_path("check_unused_error", &source_hash)];
+    let err = catch_compile_error_in(|| check_file(config.clone()))
+        .err()
+        .unwrap();
+    assert!(err.msg.starts_with("Type mismatch."));
+    let src = err.srcs.first().unwrap();
+    let defn_start = source.find("unused = ").unwrap();
+    let defn_end = defn_start + source[defn_start..].find(';').unwrap();
+    assert!(defn_start <= src.start && src.end <= defn_end);
+
+    // The program itself can be run, since `unused` is not instantiated.
+    run_file(config);
+}
+
+#[test]
+#[serial]
+pub fn test_fix_test_command() {
+    // `fix test` runs global values of type `IO ()` or `IOFail ()` whose names start with `test_`.
+    let source = r##"
+    module Lib;
+    import Debug;
+
+    double : I64 -> I64;
+    double = |x| 2 * x;
+
+    test_double : IO ();
+    test_double = (
+        eval assert_eq(|_|"double(3)", double(3), 6);
+        pure()
+    );
+
+    test_double_iofail : IOFail ();
+    test_double_iofail = (
+        if double(0) != 0 { throw("double(0) is not zero.") };
+        pure()
+    );
+
+    not_a_test : IO ();
+    not_a_test = exit(1);
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_lib", &source_hash);
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_lib", &source_hash)];
+    assert_eq!(test_command(config.clone(), None), 0);
+    assert_eq!(test_command(config, Some("iofail".to_string())), 0);
+}
+
+#[test]
+#[serial]
+pub fn test_fix_test_command_failures() {
+    // A test which fails or panics is reported, and does not stop the other tests.
+    let source = r##"
+    module Lib;
+    import Debug;
+
+    test_assertion : IO ();
+    test_assertion = (
+        eval assert_eq(|_|"one is two", 1, 2);
+        pure()
+    );
+
+    test_index_out_of_range : IO ();
+    test_index_out_of_range = (
+        let x = [1, 2, 3].@(5);
+        println(x.to_string)
+    );
+
+    test_throw : IOFail ();
+    test_throw = throw("an error thrown");
+
+    test_pass : IO ();
+    test_pass = println("passed");
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_failures", &source_hash);
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_failures", &source_hash)];
+
+    let results = run_tests(config.clone(), None);
+    let names = results
+        .iter()
+        .map(|res| res.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            "Lib::test_assertion",
+            "Lib::test_index_out_of_range",
+            "Lib::test_pass",
+            "Lib::test_throw"
+        ]
+    );
+    let stderr = |i: usize| String::from_utf8_lossy(&results[i].output.stderr).to_string();
+    assert!(!results[0].passed());
+    assert!(stderr(0).contains("one is two"));
+    assert!(!results[1].passed());
+    assert!(stderr(1).contains("Index out of range."));
+    assert!(results[2].passed());
+    assert_eq!(
+        String::from_utf8_lossy(&results[2].output.stdout),
+        "passed\n"
+    );
+    assert!(!results[3].passed());
+    assert_eq!(results[3].output.status.code(), Some(1));
+    assert!(stderr(3).contains("an error thrown"));
+
+    // The command fails if any test fails.
+    assert_eq!(test_command(config.clone(), None), 1);
+    assert_eq!(test_command(config, Some("pass".to_string())), 0);
+}
+
+#[test]
+#[serial]
+pub fn test_doc_command() {
+    let source = r##"module Lib;
+// A library for testing `fix doc`.
+
+// A pair of values.
+type Pair a = struct { fst : a, snd : a };
+
+// Swap two values.
+//
+// Example: `swap(Pair { fst : 1, snd : 2 })`.
+swap : Pair a -> Pair a;
+swap = |p| Pair { fst : p.@snd, snd : p.@fst }; // Not documented.
+
+// A comment separated by a blank line, which does not document `unit`.
+
+unit : ();
+unit = ();
+
+namespace Pair {
+    // Get the first value.
+    first : Pair a -> a;
+    first = @fst;
+}
+"##;
+    let src = SourceFile::from_string(PathBuf::from("lib.fix"), source.to_string());
+    let out_path = PathBuf::from(INTERMEDIATE_PATH).join("test_doc.md");
+    fs::create_dir_all(INTERMEDIATE_PATH).unwrap();
+    doc_command(vec![src], &out_path);
+    let markdown = fs::read_to_string(&out_path).unwrap();
+    assert!(markdown.contains("- [Module `Lib`](#module-lib)\n"));
+    assert!(markdown.contains("# Module `Lib`\nA library for testing `fix doc`.\n\n"));
+    assert!(markdown.contains("## `type Pair a`\nA pair of values.\n\n"));
+    assert!(markdown.contains(
+        "## `swap : Pair a -> Pair a`\nSwap two values.\n\nExample: `swap(Pair { fst : 1, snd : 2 })`.\n\n"
+    ));
+    assert!(markdown
+        .contains("## `namespace Pair`\n\n### `first : Pair a -> a`\nGet the first value.\n"));
+    assert!(markdown.contains("## `unit : ()`\n\n"));
+    assert!(!markdown.contains("does not document"));
+    let html = fs::read_to_string(out_path.with_extension("html")).unwrap();
+    assert!(html.contains("<a href=\"#type-pair-a\">Pair</a>"));
+}
+
+#[test]
+#[serial]
+pub fn test_doc_command_std() {
+    // `fix doc --std` documents items implemented in the compiler, as well as those written in the source code of `Std`.
+    let out_path = PathBuf::from(INTERMEDIATE_PATH).join("test_doc_std.md");
+    fs::create_dir_all(INTERMEDIATE_PATH).unwrap();
+    doc_command(standard_library_sources(), &out_path);
+    let markdown = fs::read_to_string(&out_path).unwrap();
+    assert!(markdown.contains("# Module `Std`\nThe standard library"));
+    assert!(markdown.contains("## `type I64`\n`I64` is the type of 64-bit signed integers.\n"));
+    assert!(markdown.contains("## `type Array a`\n"));
+    assert!(markdown.contains("## `type LoopResult s b`\n"));
+    assert!(markdown.contains("## `trait a : Eq`\n"));
+    assert!(markdown.contains("### `eq : [a : Eq] a -> a -> Bool`\n"));
+    assert!(markdown.contains("## `loop : a -> (a -> LoopResult a b) -> b`\n"));
+    assert!(markdown.contains(
+        "### `fill : I64 -> a -> Array a`\nCreates an array filled with the initial value.\n"
+    ));
+    assert!(markdown
+        .contains("### `to_U8 : I64 -> U8`\nCasts a value of `I64` into a value of `U8`.\n"));
+    assert!(markdown.contains("### `impl I64 : Add`\n"));
+    assert!(!markdown.contains("#FunPtr"));
+    // The committed BuiltinLibraries.md is the output of `fix doc --std`.
+    let committed = fs::read_to_string("BuiltinLibraries.md").unwrap();
+    assert!(
+        markdown == committed,
+        "BuiltinLibraries.md is out of date. Regenerate it by `fix doc --std -o BuiltinLibraries.md`."
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_catch_compile_error() {
+    // The language server recovers from compile errors in a document which is not saved to a file.
+    let source = r##"
+    module Main;
+
+    main : IO ();
+    main = println(42);
+    "##;
+    set_panic_on_error(true);
+    let src = SourceFile::from_string(PathBuf::from("unsaved.fix"), source.to_string());
+    let fix_mod = catch_compile_error(|| parse_source_file(src)).ok().unwrap();
+    let mut program = make_std_mod();
+    program.link(fix_mod);
+    let mut config = Configuration::develop_compiler();
+    let typechecker = catch_compile_error(|| {
+        program.resolve_imports(&mut config);
+        run_frontend(&mut program)
+    })
+    .ok()
+    .unwrap();
+    let main_name = FullName::from_strs(&[MAIN_MODULE_NAME], MAIN_FUNCTION_NAME);
+    let modules = HashSet::from([MAIN_MODULE_NAME.to_string()]);
+    let err = catch_compile_error(|| {
+        program.check_type_of_global_value(&main_name, &modules, &typechecker)
+    })
+    .err()
+    .unwrap();
+    set_panic_on_error(false);
+    assert!(err.msg.starts_with("Type mismatch."));
+    let src = err.srcs.first().unwrap();
+    assert!(source[src.start..src.end].contains("42"));
+
+    let src = SourceFile::from_string(
+        PathBuf::from("unsaved.fix"),
+        "module Main; main =".to_string(),
+    );
+    set_panic_on_error(true);
+    let err = catch_compile_error(|| parse_source_file(src))
+        .err()
+        .unwrap();
+    set_panic_on_error(false);
+    assert!(err.msg.starts_with("Expected"));
+}
+
+#[test]
+#[serial]
+pub fn test_infer_type_of_expr() {
+    // REPL infers the type of an expression written in a module.
+    let source = r##"
+    module Main;
+
+    add : I64 -> I64 -> I64;
+    add = |x, y| x + y;
+    "##;
+    let main_mod = MAIN_MODULE_NAME.to_string();
+    let infer = |expr: &str| {
+        let mut program = make_std_mod();
+        program.link(parse_and_save_to_temporary_file(source, "infer_type"));
+        let mut config = Configuration::develop_compiler();
+        program.resolve_imports(&mut config);
+        let src = SourceFile::from_string(PathBuf::from("expr.fix"), expr.to_string());
+        let (expr, tuple_sizes) = parse_expr_source(src, &main_mod);
+        program.used_tuple_sizes.extend(tuple_sizes);
+        let typechecker = run_frontend(&mut program);
+        program.infer_type_of_expr(expr, &main_mod, &typechecker)
+    };
+    let scm = infer("add(1)");
+    assert!(scm.vars.is_empty() && scm.preds.is_empty());
+    assert_eq!(
+        scm.ty.to_string(),
+        type_fun(make_i64_ty(), make_i64_ty()).to_string()
+    );
+    let scm = infer("|x| (x.to_string, 0)");
+    assert_eq!(scm.vars.len(), 1);
+    assert_eq!(scm.preds.len(), 1);
+}
+
+#[test]
+#[serial]
+pub fn test_format_source() {
+    let source = r##"// Header comment.
+module Main;
+import Debug;
+
+type  Pair a = unbox struct {fst:a, snd : a};
+
+// Add two numbers.
+add : I64 -> I64 -> I64;
+add = |x,y| x+y; // trailing
+
+main : IO ();
+main = (
+    let x = add(1,2);   /* block */
+    // Print it.
+    eval *println(x.to_string);
+    pure()
+);
+"##;
+    let expected = r##"// Header comment.
+module Main;
+import Debug;
+
+type Pair a = unbox struct { fst : a, snd : a };
+
+// Add two numbers.
+add : I64 -> I64 -> I64;
+add = |x, y| x + y; // trailing
+
+main : IO ();
+main = (
+    let x = add(1, 2); /* block */
+    // Print it.
+    eval *println(x.to_string);
+    pure()
+);
+"##;
+    let src = SourceFile::from_string(PathBuf::from("format.fix"), source.to_string());
+    assert_eq!(format_source(&src), expected);
+}
+
+#[test]
+#[serial]
+pub fn test_shebang() {
+    // A source file can start with a shebang line, which is kept by the formatter.
+    let source = r##"#!/usr/bin/env fix
+module Main;
+
+main : IO ();
+main = println("Hello, script!");
+"##;
+    run_source(&source, Configuration::develop_compiler());
+    let src = SourceFile::from_string(PathBuf::from("script.fix"), source.to_string());
+    assert_eq!(format_source(&src), source);
+}
+
+#[test]
+#[serial]
+pub fn test_script_cache() {
+    // The executable of a script is reused while the script and the modules it imports are unchanged.
+    let dir = PathBuf::from(INTERMEDIATE_PATH).join("test_script_cache");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    std::env::set_var("XDG_CACHE_HOME", dir.join("cache"));
+    let script_path = dir.join("script.fix");
+    let lib_path = dir.join("lib.fix");
+    fs::write(
+        &script_path,
+        "#!/usr/bin/env fix\nmodule Main;\nimport Lib;\n\nmain : IO ();\nmain = println(greeting);\n",
+    )
+    .unwrap();
+    let write_lib = |greeting: &str| {
+        let source = format!(
+            "module Lib;\n\ngreeting : String;\ngreeting = \"{}\";\n",
+            greeting
+        );
+        fs::write(&lib_path, source).unwrap();
+    };
+    let build = || {
+        let mut config = Configuration::release();
+        config.source_files = vec![script_path.clone(), lib_path.clone()];
+        build_script(&mut config)
+    };
+    let run = |exec_path: &PathBuf| {
+        let output = std::process::Command::new(exec_path).output().unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    write_lib("Hello");
+    let exec_path = build();
+    assert!(exec_path.starts_with(dir.join("cache").join(SCRIPT_CACHE_PATH)));
+    assert_eq!(run(&exec_path), "Hello\n");
+    let modified = fs::metadata(&exec_path).unwrap().modified().unwrap();
+
+    // Running the script again reuses the executable.
+    assert_eq!(build(), exec_path);
+    assert_eq!(
+        fs::metadata(&exec_path).unwrap().modified().unwrap(),
+        modified
+    );
+
+    // Editing an imported module rebuilds it.
+    write_lib("Bye");
+    let rebuilt_path = build();
+    assert_ne!(rebuilt_path, exec_path);
+    assert_eq!(run(&rebuilt_path), "Bye\n");
+
+    std::env::remove_var("XDG_CACHE_HOME");
+    assert_eq!(
+        script_cache_dir(),
+        PathBuf::from(std::env::var("HOME").unwrap())
+            .join(".cache")
+            .join(SCRIPT_CACHE_PATH)
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_format_idempotent() {
+    // Formatting keeps the program parsable, and formatting a formatted source changes nothing.
+    for dir in ["examples", "src/fix"] {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map_or(true, |ext| ext != "fix") {
+                continue;
+            }
+            let formatted = format_source(&SourceFile::from_file_path(path.clone()));
+            let src = SourceFile::from_string(path.clone(), formatted.clone());
+            assert_eq!(format_source(&src), formatted);
+            parse_source_file(src);
+        }
+    }
+}
+
+#[test]
+#[serial]
+pub fn test_emit_ast_typed_instances() {
+    let source = r##"
+    module Main;
+
+    double : I64 -> I64;
+    double = |x| 2 * x;
+
+    // A global value which is not used is not type-checked by `fix build`, so it should not be dumped by `--emit=typed`.
+    unused : I64 -> String;
+    unused = |x| x + 1;
+
+    main : IO ();
+    main = println(double(3).to_string);
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_emit", &source_hash);
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_emit", &source_hash)];
+
+    let mut program = load_file(&mut config.clone());
+    let modules = program.modules_of_files(&config.source_files);
+    let ast = dump_ast(&program, &modules);
+    assert!(ast.contains("Main::double : I64 -> I64;"));
+    assert!(ast.contains("Lam |x|"));
+
+    let typechecker = run_frontend(&mut program);
+    program.instantiate_io_action(&FullName::from_strs(&["Main"], "main"), &typechecker);
+    let typed = dump_typed(&program, &modules, &typechecker);
+    assert!(typed.contains("Lam |x| : I64 -> I64"));
+    assert!(!typed.contains("Main::unused"));
+    let instances = dump_instances(&program);
+    assert!(instances.contains("(instance of Main::double)"));
+    assert!(instances.contains("(instance of Main::main)"));
+}
+
+#[test]
+#[serial]
+pub fn test_emit_obj_asm_bc() {
+    let source = r##"
+    module Main;
+
+    main : IO ();
+    main = println("Hello from an object file!");
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_emit_obj", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_emit_obj");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let mut config = Configuration::release();
+    config.source_files = vec![temporary_source_path("test_emit_obj", &source_hash)];
+
+    // Build an object file and link it by ourselves.
+    let mut obj_config = config.clone();
+    obj_config.set_output_file_type(OutputFileType::Object);
+    obj_config.out_file_path = Some(out_dir.join("main.o"));
+    build_file(obj_config);
+    let exec_path = out_dir.join("main");
+    let output = std::process::Command::new("gcc")
+        .arg("-no-pie")
+        .arg("-o")
+        .arg(&exec_path)
+        .arg(out_dir.join("main.o"))
+        .arg(out_dir.join(RUNTIME_OBJECT_FILE_NAME))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let output = std::process::Command::new(&exec_path).output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Hello from an object file!\n"
+    );
+
+    // Assembly and bitcode files.
+    for (file_type, file_name) in [
+        (OutputFileType::Assembly, "main.s"),
+        (OutputFileType::Bitcode, "main.bc"),
+    ] {
+        let mut config = config.clone();
+        config.set_output_file_type(file_type);
+        config.out_file_path = Some(out_dir.join(file_name));
+        build_file(config);
+        assert!(out_dir.join(file_name).exists());
+    }
+}
+
+#[test]
+#[serial]
+pub fn test_build_library() {
+    let source = r##"
+    module Lib;
+
+    FFI_EXPORT[add, fix_add];
+    FFI_EXPORT[Inner::print_twice, fix_print_twice];
+    FFI_EXPORT[arg_count, fix_arg_count];
+
+    add : I64 -> I64 -> I64;
+    add = |x, y| x + y;
+
+    namespace Inner {
+        print_twice : I32 -> IO F64;
+        print_twice = |x| (
+            let _ = *println((2 * x).to_string);
+            pure $ x.to_F64 * 0.5
+        );
+    }
+
+    arg_count : IO I64;
+    arg_count = (*get_args).get_size.pure;
+    "##;
+    let c_source = r##"
+    #include <stdio.h>
+    #include "lib.h"
+
+    int main(int argc, char **argv) {
+        printf("%ld\n", (long)fix_add(1, 2));
+        printf("%.1f\n", fix_print_twice(3));
+        printf("%ld\n", (long)fix_arg_count());
+        return 0;
+    }
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "test_lib_export", &source_hash);
+    let out_dir = PathBuf::from(INTERMEDIATE_PATH).join("test_build_library");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    std::fs::write(out_dir.join("main.c"), c_source).unwrap();
+
+    for (file_type, lib_name) in [
+        (OutputFileType::SharedLibrary, "lib.so"),
+        (OutputFileType::StaticLibrary, "lib.a"),
+    ] {
+        let mut config = Configuration::release();
+        config.source_files = vec![temporary_source_path("test_lib_export", &source_hash)];
+        config.set_output_file_type(file_type);
+        config.out_file_path = Some(out_dir.join(lib_name));
+        build_file(config);
+        assert!(out_dir.join("lib.h").exists());
+
+        let exec_path = out_dir.join("main");
+        let output = std::process::Command::new("gcc")
+            .arg("-o")
+            .arg(&exec_path)
+            .arg(out_dir.join("main.c"))
+            .arg(out_dir.join(lib_name))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let output = std::process::Command::new(&exec_path)
+            .arg("arg")
+            .env("LD_LIBRARY_PATH", &out_dir)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "3\n6\n1.5\n2\n");
+    }
+}
+
+#[test]
+#[serial]
+pub fn test_bindgen() {
+    let header = r##"
+    #ifndef MY_MATH_H
+    #define MY_MATH_H
+    #include <stddef.h>
+
+    #define ANSWER 42
+    #define HALF (0.5)
+    #define MASK 0xffUL
+
+    typedef int my_int; /* an integer */
+    typedef struct opaque opaque_t;
+    enum color { RED, GREEN = 5, BLUE };
+
+    #ifdef __cplusplus
+    extern "C" {
+    #endif
+
+    int abs(my_int x);
+    double sqrt(double);
+    size_t strlen(const char *s);
+    int printf(const char *format, ...);
+    opaque_t make_opaque(void);
+    static inline int twice(int x) { return 2 * x; }
+
+    #ifdef __cplusplus
+    }
+    #endif
+    #endif
+    "##;
+    let bindings = generate_bindings(header, "MyMath");
+    assert!(bindings.contains("answer : I64;\nanswer = 42;"));
+    assert!(bindings.contains("half : F64;\nhalf = 0.5;"));
+    assert!(bindings.contains("mask = 0xff_U64;"));
+    assert!(bindings.contains("green = 5_I32;"));
+    assert!(bindings.contains("blue = 6_I32;"));
+    assert!(bindings.contains("abs : I32 -> IO I32;"));
+    assert!(bindings.contains("strlen = |s| IO { _data : |_| CALL_C[U64 strlen(Ptr), s] };"));
+    assert!(bindings.contains("// Skipped `int printf(const char * format, ...)`"));
+    assert!(bindings.contains("// Skipped `opaque_t make_opaque(void)`"));
+    assert!(!bindings.contains("twice"));
+
+    let bindings_hash = format!("{:x}", md5::compute(&bindings));
+    save_temporary_source(&bindings, "my_math", &bindings_hash);
+    let main = r##"
+    module Main;
+    import Debug;
+    import MyMath;
+
+    main : IO ();
+    main = (
+        let x = *MyMath::abs(-3_I32);
+        eval assert_eq(|_|"abs", x, 3_I32);
+        let y = *MyMath::sqrt(16.0);
+        eval assert_eq(|_|"sqrt", y, 4.0);
+        let n = *"hello".borrow_c_str(|p| strlen(p)._unsafe_perform).pure;
+        eval assert_eq(|_|"strlen", n, 5_U64);
+        eval assert_eq(|_|"answer", answer + blue.to_I64, 48);
+        pure()
+    );
+    "##;
+    let main_hash = format!("{:x}", md5::compute(main));
+    save_temporary_source(main, "bindgen_main", &main_hash);
+    let mut config = Configuration::release();
+    config.add_libm();
+    config.source_files = vec![
+        temporary_source_path("my_math", &bindings_hash),
+        temporary_source_path("bindgen_main", &main_hash),
+    ];
+    run_file(config);
+}
+
+#[test]
+#[serial]
+pub fn test_find_mismatch() {
+    use TypeComponent::*;
+    let mut program = make_std_mod();
+    program.calculate_type_env();
+    let kinds = program.type_env().kinds();
+    let find = |ty1: &Rc<TypeNode>, ty2: &Rc<TypeNode>| {
+        Substitution::find_mismatch(&kinds, ty1, ty2, Substitution::unify)
+    };
+    let i64_ty = make_i64_ty();
+    let bool_ty = make_bool_ty();
+    let array = |ty: &Rc<TypeNode>| type_tyapp(make_array_ty(), ty.clone());
+
+    // Types which can be unified have no mismatch.
+    assert_eq!(find(&array(&i64_ty), &array(&type_tyvar_star("a"))), None);
+    // The argument of a type application differs.
+    assert_eq!(
+        find(&array(&i64_ty), &array(&bool_ty)),
+        Some(vec![TyAppArg])
+    );
+    // The result of a function type differs.
+    assert_eq!(
+        find(
+            &type_fun(i64_ty.clone(), bool_ty.clone()),
+            &type_fun(i64_ty.clone(), i64_ty.clone())
+        ),
+        Some(vec![FunTyDst])
+    );
+    // The path goes into nested components.
+    assert_eq!(
+        find(
+            &type_fun(array(&i64_ty), bool_ty.clone()),
+            &type_fun(array(&bool_ty), bool_ty.clone())
+        ),
+        Some(vec![FunTySrc, TyAppArg])
+    );
+    assert_eq!(
+        find(
+            &make_tuple_ty(vec![i64_ty.clone(), bool_ty.clone()]),
+            &make_tuple_ty(vec![i64_ty.clone(), i64_ty.clone()])
+        ),
+        Some(vec![TyAppArg])
+    );
+    // Type constructors differ, so the whole types are reported.
+    assert_eq!(find(&array(&i64_ty), &bool_ty), Some(vec![]));
+    assert_eq!(
+        find(
+            &make_tuple_ty(vec![i64_ty.clone(), bool_ty.clone()]),
+            &make_tuple_ty(vec![bool_ty.clone(), bool_ty.clone()])
+        ),
+        Some(vec![])
+    );
+    // Each component can be unified, but they conflict with each other.
+    let a = type_tyvar_star("a");
+    assert_eq!(
+        find(
+            &type_fun(a.clone(), a.clone()),
+            &type_fun(i64_ty.clone(), bool_ty.clone())
+        ),
+        Some(vec![])
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_to_string_normalize_highlight() {
+    use TypeComponent::*;
+    let i64_ty = make_i64_ty();
+    let bool_ty = make_bool_ty();
+    let array = |ty: &Rc<TypeNode>| type_tyapp(make_array_ty(), ty.clone());
+    let highlight = |ty: &Rc<TypeNode>, path: &[TypeComponent]| {
+        let (s, (start, end)) = ty.to_string_normalize_highlight(path);
+        let highlighted = s[start..end].to_string();
+        (s, highlighted, start)
+    };
+
+    // The whole type.
+    assert_eq!(
+        highlight(&array(&i64_ty), &[]),
+        (
+            "Std::Array Std::I64".to_string(),
+            "Std::Array Std::I64".to_string(),
+            0
+        )
+    );
+    // The argument of a type application.
+    assert_eq!(
+        highlight(&array(&i64_ty), &[TyAppArg]),
+        (
+            "Std::Array Std::I64".to_string(),
+            "Std::I64".to_string(),
+            11
+        )
+    );
+    // A braced argument is highlighted without braces.
+    assert_eq!(
+        highlight(&array(&array(&i64_ty)), &[TyAppArg]),
+        (
+            "Std::Array (Std::Array Std::I64)".to_string(),
+            "Std::Array Std::I64".to_string(),
+            12
+        )
+    );
+    // Components of function types.
+    let fun = type_fun(type_fun(i64_ty.clone(), bool_ty.clone()), bool_ty.clone());
+    assert_eq!(
+        highlight(&fun, &[FunTySrc]),
+        (
+            "(Std::I64 -> Std::Bool) -> Std::Bool".to_string(),
+            "Std::I64 -> Std::Bool".to_string(),
+            1
+        )
+    );
+    assert_eq!(
+        highlight(&fun, &[FunTySrc, FunTyDst]),
+        (
+            "(Std::I64 -> Std::Bool) -> Std::Bool".to_string(),
+            "Std::Bool".to_string(),
+            13
+        )
+    );
+    assert_eq!(
+        highlight(&fun, &[FunTyDst]),
+        (
+            "(Std::I64 -> Std::Bool) -> Std::Bool".to_string(),
+            "Std::Bool".to_string(),
+            27
+        )
+    );
+    // A field of a tuple.
+    assert_eq!(
+        highlight(
+            &make_tuple_ty(vec![i64_ty.clone(), bool_ty.clone()]),
+            &[TyAppArg]
+        ),
+        (
+            "(Std::I64, Std::Bool)".to_string(),
+            "Std::Bool".to_string(),
+            11
+        )
+    );
+    // Type variables are normalized, and a path which cannot be followed stops at the deepest component.
+    assert_eq!(
+        highlight(&array(&type_tyvar_star("elem")), &[TyAppArg, TyAppArg]),
+        ("Std::Array t0".to_string(), "t0".to_string(), 11)
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_type_mismatch_message() {
+    let source = "f : I64 -> Bool;\nf = |x| x;\n";
+    let file = SourceFile::from_string(PathBuf::from("mismatch.fix"), source.to_string());
+    let span = |text: &str| {
+        let start = source.find(text).unwrap();
+        Span {
+            input: file.clone(),
+            start,
+            end: start + text.len(),
+        }
+    };
+    let mut program = make_std_mod();
+    program.calculate_type_env();
+    let kinds = program.type_env().kinds();
+    let expected = type_fun(make_i64_ty(), make_bool_ty()).set_source(Some(span("I64 -> Bool")));
+    let found = type_fun(make_i64_ty(), make_i64_ty());
+    let expr_src = Some(span("|x| x"));
+    let err = catch_compile_error_in(|| {
+        error_exit_type_mismatch(&kinds, &expected, &found, Substitution::unify, &expr_src)
+    })
+    .err()
+    .unwrap();
+
+    // Carets are shown under the differing components.
+    let caret_indent = " ".repeat("  expected: Std::I64 -> ".len());
+    assert_eq!(
+        err.msg,
+        format!(
+            "Type mismatch. Expected `Std::I64 -> Std::Bool`, found `Std::I64 -> Std::I64`.\n\
+            \x20 expected: Std::I64 -> Std::Bool\n\
+            {}^^^^^^^^^\n\
+            \x20    found: Std::I64 -> Std::I64\n\
+            {}^^^^^^^^\n\
+            `Std::Bool` and `Std::I64` do not match.",
+            caret_indent, caret_indent
+        )
+    );
+    assert!(err.srcs[0].is_same_range(expr_src.as_ref().unwrap()));
+
+    // The origin of the expected type is noted, and the found type has no origin.
+    assert_eq!(err.notes.len(), 1);
+    let (note, note_src) = &err.notes[0];
+    assert_eq!(
+        note,
+        "The expected type `Std::I64 -> Std::Bool` is specified here."
+    );
+    assert!(note_src
+        .as_ref()
+        .unwrap()
+        .is_same_range(&span("I64 -> Bool")));
+
+    // No note is added if the type is specified at the expression itself.
+    let found = found.set_source(expr_src.clone());
+    let err = catch_compile_error_in(|| {
+        error_exit_type_mismatch(&kinds, &expected, &found, Substitution::unify, &expr_src)
+    })
+    .err()
+    .unwrap();
+    assert_eq!(err.notes.len(), 1);
+}
+
+#[test]
+#[serial]
+pub fn test_type_mismatch_more_general_signature() {
+    // A type signature which is more general than the definition is reported with the differing components.
+    let source = r##"
+    module Main;
+
+    f : a -> a;
+    f = |x| x + 1;
+
+    main : IO ();
+    main = pure();
+    "##;
+    let source_hash = format!("{:x}", md5::compute(source));
+    save_temporary_source(source, "more_general_signature", &source_hash);
+    let mut config = Configuration::develop_compiler();
+    config.source_files = vec![temporary_source_path(
+        "more_general_signature",
+        &source_hash,
+    )];
+    let err = catch_compile_error_in(|| check_file(config.clone()))
+        .err()
+        .unwrap();
+    let caret_indent = " ".repeat("  expected: ".len());
+    assert_eq!(
+        err.msg,
+        format!(
+            "Type mismatch. Expected `t0 -> t0`, found `Std::I64 -> Std::I64`.\n\
+            \x20 expected: t0 -> t0\n\
+            {}^^\n\
+            \x20    found: Std::I64 -> Std::I64\n\
+            {}^^^^^^^^\n\
+            `t0` and `Std::I64` do not match.",
+            caret_indent, caret_indent
+        )
+    );
+
+    // Type variables of both types are renamed by a common renaming.
+    let a = type_tyvar_star("a");
+    let b = type_tyvar_star("b");
+    let normalized = TypeNode::normalize_tyvars_of(&[type_fun(a.clone(), b.clone()), b.clone()]);
+    assert_eq!(
+        normalized[1].to_string(),
+        normalized[0].get_lambda_dst().to_string()
+    );
+    assert_ne!(
+        normalized[1].to_string(),
+        normalized[0].get_lambda_srcs()[0].to_string()
+    );
+}
+
+#[test]
+#[serial]
+pub fn test_repl_session() {
+    // Definitions and global values defined by `let` are kept across inputs of REPL.
+    let context = Context::create();
+    let mut session = ReplSession::new(&context, Configuration::develop_compiler());
+    let mut eval = |input: &str| catch_compile_error_in(|| session.eval_input(input));
+
+    eval("import Debug;").ok().unwrap();
+    eval("let x = 40;").ok().unwrap();
+    eval("add2 : I64 -> I64;\nadd2 = |n| n + 2;").ok().unwrap();
+    eval("(eval assert_eq(|_|\"add2(x)\", add2(x), 42); pure())")
+        .ok()
+        .unwrap();
+    eval("let y : I64 = add2(x) * 10;").ok().unwrap();
+    eval("(eval assert_eq(|_|\"y\", y, 420); pure())")
+        .ok()
+        .unwrap();
+
+    // Values are printed, and `IO` actions are run.
+    eval("x + y").ok().unwrap();
+    eval("println(\"hello\")").ok().unwrap();
+    eval("pure(x)").ok().unwrap();
+    eval(":type add2").ok().unwrap();
+
+    // A tuple type which is not used by previous inputs.
+    eval("let t = (1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11);")
+        .ok()
+        .unwrap();
+    eval("(eval assert_eq(|_|\"t\", t.@0 + t.@10, 12); pure())")
+        .ok()
+        .unwrap();
+
+    // Errors do not break the session, and erroneous definitions are not added.
+    let err = eval("add2(\"two\")").err().unwrap();
+    assert!(err.msg.starts_with("Type mismatch."));
+    assert!(eval("let z : Bool = x;").is_err());
+    assert!(eval("z").is_err());
+    assert!(eval("let x = 0;").is_err());
+    eval("(eval assert_eq(|_|\"x\", x, 40); pure())")
+        .ok()
+        .unwrap();
+    eval("let z = x + 2;").ok().unwrap();
+    eval("(eval assert_eq(|_|\"z\", z, add2(x)); pure())")
+        .ok()
+        .unwrap();
+}
+
+#[test]
+#[serial]
+pub fn test_language_server() {
+    // Test the language server by messages of Language Server Protocol.
+    let text = "module Main;\n\ntwice : I64 -> I64;\ntwice = |x| x + x;\n\nmain : IO ();\nmain = println(twice(21).to_string);\n";
+    // The URI ends with a percent-encoded character.
+    let uri = "file:///tmp/fix%20lsp/main.fi%78";
+    let normalized_uri = "file:///tmp/fix%20lsp/main.fix";
+    let position = |line: usize, character: usize| serde_json::json!({"textDocument": {"uri": uri}, "position": {"line": line, "character": character}});
+    let messages = vec![
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": uri, "languageId": "fix", "version": 1, "text": text}
+        }}),
+        // On `twice` in `main`.
+        serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": position(6, 16)}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": position(6, 16)}),
+        // After `twi` in `main`.
+        serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "textDocument/completion", "params": position(6, 18)}),
+        // Introduce a type error, which is reported as a diagnostic.
+        serde_json::json!({"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+            "textDocument": {"uri": uri, "version": 2},
+            "contentChanges": [{"text": text.replace("x + x", "x + \"x\"")}]
+        }}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 5, "method": "shutdown"}),
+        serde_json::json!({"jsonrpc": "2.0", "method": "exit"}),
+    ];
+    let mut input = vec![];
+    for msg in messages {
+        let content = msg.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{}", content.len(), content).bytes());
+    }
+
+    clear_type_check_cache();
+    let mut output = vec![];
+    let exit_code = catch_compile_error_in(|| {
+        run_language_server(&mut std::io::Cursor::new(input), &mut output)
+    })
+    .ok()
+    .unwrap();
+    assert_eq!(exit_code, 0);
+    // The type check cache is not written to the working directory.
+    assert!(!Path::new(TYPE_CHECK_CACHE_PATH).exists());
+
+    let mut reader = std::io::Cursor::new(output);
+    let mut responses: HashMap<i64, serde_json::Value> = Default::default();
+    let mut diagnostics = vec![];
+    while let Some(msg) = read_message(&mut reader) {
+        if let Some(id) = msg["id"].as_i64() {
+            responses.insert(id, msg["result"].clone());
+        } else if msg["method"] == "textDocument/publishDiagnostics" {
+            assert_eq!(msg["params"]["uri"], uri);
+            diagnostics.push(msg["params"]["diagnostics"].as_array().unwrap().clone());
+        }
+    }
+
+    assert_eq!(responses[&1]["capabilities"]["hoverProvider"], true);
+    assert_eq!(
+        responses[&2]["contents"]["value"],
+        "```\nMain::twice : Std::I64 -> Std::I64\n```"
+    );
+    assert_eq!(responses[&3]["uri"], normalized_uri);
+    assert_eq!(responses[&3]["range"]["start"]["line"], 3);
+    let labels = responses[&4]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert!(labels.contains(&"twice".to_string()));
+    assert!(labels.iter().all(|label| label.starts_with("twi")));
+    assert_eq!(responses[&5], serde_json::Value::Null);
+
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics[0].is_empty());
+    assert_eq!(diagnostics[1].len(), 1);
+    assert_eq!(diagnostics[1][0]["range"]["start"]["line"], 3);
+}
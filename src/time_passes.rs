@@ -0,0 +1,162 @@
+// Measuring durations of compilation phases (`--time-passes`).
+//
+// A phase is measured by holding a `PassTimer` returned by `time_pass` while it runs.
+// Durations of a phase run multiple times (e.g., type-checking of each global value, or compilation of each codegen unit) are summed up.
+// Phases may be nested; e.g., "instantiation" includes "type_check" since global values are type-checked when they are instantiated.
+// Timers can be used on any thread, so the durations of phases run in parallel are the sums over the threads.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+
+use super::*;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TimePassesFormat {
+    Text,
+    Json,
+}
+
+struct PassRecord {
+    name: &'static str,
+    first_start: Instant,
+    duration: Duration,
+    count: usize,
+}
+
+struct TimePasses {
+    start: Instant,
+    passes: Vec<PassRecord>,
+    counters: Vec<(&'static str, usize)>,
+}
+
+static TIME_PASSES: Mutex<Option<TimePasses>> = Mutex::new(None);
+
+// Start measuring phases. Before this is called, `time_pass` and `set_time_passes_counter` do nothing.
+pub fn enable_time_passes() {
+    *TIME_PASSES.lock().unwrap() = Some(TimePasses {
+        start: Instant::now(),
+        passes: vec![],
+        counters: vec![],
+    });
+}
+
+fn is_time_passes_enabled() -> bool {
+    TIME_PASSES.lock().unwrap().is_some()
+}
+
+pub struct PassTimer {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+// Start measuring a phase. The phase ends when the returned timer is dropped.
+pub fn time_pass(name: &'static str) -> PassTimer {
+    PassTimer {
+        name,
+        start: if is_time_passes_enabled() {
+            Some(Instant::now())
+        } else {
+            None
+        },
+    }
+}
+
+impl Drop for PassTimer {
+    fn drop(&mut self) {
+        let start = match self.start {
+            Some(start) => start,
+            None => return,
+        };
+        let duration = start.elapsed();
+        let mut time_passes = TIME_PASSES.lock().unwrap();
+        let time_passes = match time_passes.as_mut() {
+            Some(time_passes) => time_passes,
+            None => return,
+        };
+        match time_passes.passes.iter_mut().find(|p| p.name == self.name) {
+            Some(record) => {
+                record.first_start = record.first_start.min(start);
+                record.duration += duration;
+                record.count += 1;
+            }
+            None => time_passes.passes.push(PassRecord {
+                name: self.name,
+                first_start: start,
+                duration,
+                count: 1,
+            }),
+        }
+    }
+}
+
+// Record a statistic of the compilation, such as the number of instantiated symbols.
+pub fn set_time_passes_counter(name: &'static str, value: usize) {
+    if let Some(time_passes) = TIME_PASSES.lock().unwrap().as_mut() {
+        match time_passes.counters.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => time_passes.counters.push((name, value)),
+        }
+    }
+}
+
+// Report the measured durations in the format specified by `config.time_passes`:
+// a table written to stderr, or a JSON file (`time_passes.json`, or `time_passes_{output}.json` if `-o {output}` is given).
+pub fn report_time_passes(config: &Configuration) {
+    let format = match config.time_passes {
+        Some(format) => format,
+        None => return,
+    };
+    let time_passes = match TIME_PASSES.lock().unwrap().take() {
+        Some(time_passes) => time_passes,
+        None => return,
+    };
+    let total = time_passes.start.elapsed();
+    let mut passes = time_passes.passes;
+    passes.sort_by_key(|p| p.first_start);
+    match format {
+        TimePassesFormat::Text => {
+            let mut text = format!("{:<32} {:>12} {:>8}\n", "phase", "time (sec)", "count");
+            for pass in &passes {
+                text += &format!(
+                    "{:<32} {:>12.3} {:>8}\n",
+                    pass.name,
+                    pass.duration.as_secs_f64(),
+                    pass.count
+                );
+            }
+            text += &format!("{:<32} {:>12.3}\n", "total", total.as_secs_f64());
+            for (name, value) in &time_passes.counters {
+                text += &format!("{}: {}\n", name, value);
+            }
+            eprint!("{}", text);
+        }
+        TimePassesFormat::Json => {
+            let passes = passes
+                .iter()
+                .map(|p| {
+                    json!({
+                        "name": p.name,
+                        "seconds": p.duration.as_secs_f64(),
+                        "count": p.count,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let counters = time_passes
+                .counters
+                .iter()
+                .map(|(name, value)| (name.to_string(), json!(value)))
+                .collect::<serde_json::Map<_, _>>();
+            let report = json!({
+                "passes": passes,
+                "total_seconds": total.as_secs_f64(),
+                "counters": counters,
+            });
+            let path = config.get_output_emit_path_with_extension("time_passes", "json");
+            write_dump(&path, &serde_json::to_string_pretty(&report).unwrap());
+        }
+    }
+}
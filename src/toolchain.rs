@@ -0,0 +1,247 @@
+// The C toolchain used to compile the runtime (runtime.c) and to link executables and libraries.
+//
+// The C compiler is taken from `CC` environment variable (default: `gcc`), and the linker from `FIX_LINKER` (default: the C compiler).
+// Since the linker is invoked with options of a compiler driver (e.g., `-shared`, `-Wl,...`), `FIX_LINKER` should be a compiler driver such as `clang` or `gcc`.
+// `LD` is not used, since it usually names a plain linker such as `ld`; to change the linker used by the driver, pass e.g. `-fuse-ld=lld` in `LDFLAGS`.
+// Extra flags are taken from `CFLAGS` and `LDFLAGS`, and the archiver for static libraries from `AR` (default: `ar`).
+// For profile-guided optimization, clang (the C compiler if it is clang, or `clang`) and `llvm-profdata` (or `LLVM_PROFDATA`) are used.
+// Each of them can be overridden or extended by command line options.
+//
+// When cross-compiling (`--target`), `CC` should be a cross compiler (e.g., `aarch64-linux-gnu-gcc`),
+// or clang, to which the target triple is passed by `--target` option.
+// The host's compiler (`gcc` or `cc`, including the default) is rejected, since it would silently build the runtime for the host.
+
+use std::{path::PathBuf, process::Command};
+
+use inkwell::targets::TargetMachine;
+
+use super::*;
+
+#[derive(Clone)]
+pub struct Toolchain {
+    // The C compiler driver.
+    pub cc: String,
+    // The compiler driver used for linking. If `None`, `cc` is used.
+    pub linker: Option<String>,
+    // The archiver used to create static libraries.
+    pub ar: String,
+    // Extra flags passed to the C compiler.
+    pub cflags: Vec<String>,
+    // Extra flags passed to the linker.
+    pub ldflags: Vec<String>,
+    // Directories searched for linked libraries (`-L`).
+    pub library_paths: Vec<PathBuf>,
+    // Directories added to the runtime search path of the output (`-rpath`).
+    pub rpaths: Vec<PathBuf>,
+    // The target triple when cross-compiling, passed to the C compiler and the linker if they are clang.
+    pub target: Option<String>,
+    // The tool which converts profiles for profile-guided optimization.
+    pub profdata: String,
+}
+
+impl Default for Toolchain {
+    fn default() -> Self {
+        Toolchain {
+            cc: "gcc".to_string(),
+            linker: None,
+            ar: "ar".to_string(),
+            cflags: vec![],
+            ldflags: vec![],
+            library_paths: vec![],
+            rpaths: vec![],
+            target: None,
+            profdata: "llvm-profdata".to_string(),
+        }
+    }
+}
+
+impl Toolchain {
+    // Create a toolchain from environment variables `CC`, `FIX_LINKER`, `AR`, `CFLAGS` and `LDFLAGS`.
+    pub fn from_env() -> Self {
+        let mut toolchain = Self::default();
+        let get_env = |name: &str| std::env::var(name).ok().filter(|s| !s.trim().is_empty());
+        if let Some(cc) = get_env("CC") {
+            toolchain.cc = cc;
+        }
+        toolchain.linker = get_env("FIX_LINKER");
+        if let Some(ar) = get_env("AR") {
+            toolchain.ar = ar;
+        }
+        if let Some(flags) = get_env("CFLAGS") {
+            toolchain.cflags = split_flags(&flags);
+        }
+        if let Some(flags) = get_env("LDFLAGS") {
+            toolchain.ldflags = split_flags(&flags);
+        }
+        if let Some(profdata) = get_env("LLVM_PROFDATA") {
+            toolchain.profdata = profdata;
+        }
+        toolchain
+    }
+
+    // The command which links object files. Output path, inputs and libraries should be added by the caller.
+    pub fn link_command(&self) -> Command {
+        let linker = self.linker.as_ref().unwrap_or(&self.cc);
+        let mut com = Command::new(linker);
+        self.add_target_option(&mut com, linker);
+        self.add_link_options(&mut com);
+        com
+    }
+
+    // The command which compiles C source files. Output path and inputs should be added by the caller.
+    pub fn compile_command(&self) -> Command {
+        let mut com = Command::new(&self.cc);
+        self.add_target_option(&mut com, &self.cc);
+        com.args(&self.cflags);
+        com
+    }
+
+    // The command of clang which compiles C source files to LLVM bitcode: the C compiler if it is clang, or `clang` otherwise.
+    // Output path, inputs and the target should be added by the caller.
+    pub fn bitcode_compile_command(&self) -> Command {
+        let mut com = Command::new(self.clang());
+        com.args(&self.cflags);
+        com
+    }
+
+    // The command of clang which compiles LLVM bitcode with profile-guided optimization. Options should be added by the caller.
+    pub fn pgo_compile_command(&self) -> Command {
+        Command::new(self.clang())
+    }
+
+    // The command which links object files instrumented for profile-guided optimization.
+    // The linker has to be clang, which links the profile runtime of LLVM.
+    pub fn pgo_link_command(&self) -> Command {
+        let linker = match &self.linker {
+            Some(linker) if is_clang(linker) => linker.as_str(),
+            _ => self.clang(),
+        };
+        let mut com = Command::new(linker);
+        self.add_target_option(&mut com, linker);
+        self.add_link_options(&mut com);
+        com
+    }
+
+    // The command which converts profiles.
+    pub fn profdata_command(&self) -> Command {
+        Command::new(&self.profdata)
+    }
+
+    pub fn clang(&self) -> &str {
+        if is_clang(&self.cc) {
+            &self.cc
+        } else {
+            "clang"
+        }
+    }
+
+    // The command which compiles C source files and links them into a shared library at once.
+    pub fn compile_and_link_command(&self) -> Command {
+        let mut com = self.compile_command();
+        self.add_link_options(&mut com);
+        com
+    }
+
+    // The command which creates a static library.
+    pub fn archive_command(&self) -> Command {
+        Command::new(&self.ar)
+    }
+
+    // A string which identifies the toolchain, used to invalidate cached runtime binaries built by another toolchain.
+    pub fn fingerprint(&self) -> String {
+        let mut parts = vec![self.cc.clone()];
+        parts.extend(self.linker.iter().cloned());
+        parts.extend(self.target.iter().cloned());
+        parts.extend(self.cflags.iter().cloned());
+        parts.extend(self.ldflags.iter().cloned());
+        for path in &self.library_paths {
+            parts.push(format!("-L{}", path.to_string_lossy()));
+        }
+        for path in &self.rpaths {
+            parts.push(format!("-rpath={}", path.to_string_lossy()));
+        }
+        parts.join(" ")
+    }
+
+    fn add_target_option(&self, com: &mut Command, driver: &str) {
+        if let Some(target) = &self.target {
+            if is_clang(driver) {
+                com.arg(format!("--target={}", target));
+            } else if is_host_compiler(driver) && !is_host_triple(target) {
+                error_exit(&format!(
+                    "Cannot build for `{}` by `{}`, which compiles for the host. \
+                    Use clang (`--cc clang`) or a cross compiler for the target (e.g., `--cc aarch64-linux-gnu-gcc`).",
+                    target, driver
+                ));
+            }
+        }
+    }
+
+    fn add_link_options(&self, com: &mut Command) {
+        for path in &self.library_paths {
+            com.arg(format!("-L{}", path.to_str().unwrap()));
+        }
+        for path in &self.rpaths {
+            com.arg(format!("-Wl,-rpath,{}", path.to_str().unwrap()));
+        }
+        com.args(&self.ldflags);
+    }
+}
+
+// Is the compiler driver clang (e.g., `clang`, `clang-15` or `/usr/bin/clang`)?
+fn is_clang(driver: &str) -> bool {
+    PathBuf::from(driver)
+        .file_name()
+        .map(|name| name.to_string_lossy().contains("clang"))
+        .unwrap_or(false)
+}
+
+// Is the compiler driver the host's generic one (`gcc` or `cc`), which cannot compile for other targets?
+fn is_host_compiler(driver: &str) -> bool {
+    PathBuf::from(driver)
+        .file_name()
+        .map(|name| name == "gcc" || name == "cc")
+        .unwrap_or(false)
+}
+
+fn is_host_triple(triple: &str) -> bool {
+    TargetMachine::get_default_triple()
+        .as_str()
+        .to_string_lossy()
+        == triple
+}
+
+// Split flags given by an environment variable such as `CFLAGS` by whitespaces.
+fn split_flags(flags: &str) -> Vec<String> {
+    flags.split_whitespace().map(|s| s.to_string()).collect()
+}
+
+// Run a command of the toolchain.
+// Warnings written to stderr are printed (and returned), and the compiler exits with an error if the command fails.
+pub fn run_toolchain_command(com: &mut Command) -> String {
+    let program = com.get_program().to_string_lossy().to_string();
+    let output = match com.output() {
+        Ok(output) => output,
+        Err(e) => error_exit(&format!("Failed to run `{}`: {}", program, e)),
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() {
+        let status = match output.status.code() {
+            Some(code) => format!("exit status {}", code),
+            None => "terminated by signal".to_string(),
+        };
+        let args = com
+            .get_args()
+            .map(|s| s.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        error_exit(&format!(
+            "`{} {}` failed ({}).\n{}",
+            program, args, status, stderr
+        ));
+    }
+    if stderr.len() > 0 {
+        eprintln!("{}", stderr);
+    }
+    stderr
+}
@@ -0,0 +1,255 @@
+// A cache of the results of type-checking global values, stored in a single file `.fixlang/type_check_cache.db`.
+//
+// The file consists of
+// - a header line which identifies the format and the build of the compiler; a file with another header is ignored, and
+// - entries, each of which is the key (32 bytes of md5 hex digest), the last used time (u64, seconds since UNIX epoch),
+//   the length of the data (u64) and the data (a `TypedExpr` serialized by `serde_pickle`). Integers are little endian.
+//
+// All entries are loaded at the first lookup, and new or used entries are saved when the cache is dropped,
+// merged with entries saved by other processes in the meantime.
+// The merge holds an advisory lock on `.fixlang/type_check_cache.lock`, so that entries saved by concurrent processes are not lost.
+// Entries not used for `MAX_AGE_SECS` are evicted, and least recently used entries are evicted while the file exceeds `MAX_TOTAL_SIZE`.
+
+use build_time::build_time_utc;
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::*;
+
+const FORMAT_VERSION: u32 = 1;
+const KEY_LEN: usize = 32;
+const MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+const MAX_TOTAL_SIZE: usize = 256 * 1024 * 1024;
+// The last used time of an entry is updated at most once in this interval, to avoid rewriting the file in every build.
+const TOUCH_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+struct CacheEntry {
+    last_used: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct TypeCheckCache {
+    // `None` until the cache file is loaded.
+    entries: Option<HashMap<String, CacheEntry>>,
+    // Keys of the entries added or used in this process, which should be saved.
+    dirty: HashSet<String>,
+    // If true, the cache file is neither loaded nor saved.
+    in_memory: bool,
+}
+
+impl TypeCheckCache {
+    // A cache which is kept only in memory, e.g., by the language server, which should not write files to the working directory of the editor.
+    pub fn in_memory() -> Self {
+        TypeCheckCache {
+            entries: Some(Default::default()),
+            dirty: Default::default(),
+            in_memory: true,
+        }
+    }
+
+    // The key of the result of type-checking a global value against a type scheme.
+    pub fn key(name: &FullName, hash_of_dependent_codes: &str, scheme: &Rc<Scheme>) -> String {
+        let data = format!(
+            "{}_{}_{}",
+            name.to_string(),
+            hash_of_dependent_codes,
+            scheme.to_string()
+        );
+        format!("{:x}", md5::compute(data))
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<TypedExpr> {
+        let now = now();
+        let entries = self.entries.get_or_insert_with(read_cache_file);
+        let entry = entries.get_mut(key)?;
+        let expr: TypedExpr = match serde_pickle::from_slice(&entry.data, Default::default()) {
+            Ok(res) => res,
+            Err(why) => {
+                eprintln!(
+                    "warning: Failed to parse an entry of type check cache: {}.",
+                    why
+                );
+                return None;
+            }
+        };
+        if entry.last_used + TOUCH_INTERVAL_SECS < now {
+            entry.last_used = now;
+            self.dirty.insert(key.to_string());
+        }
+        Some(expr)
+    }
+
+    pub fn insert(&mut self, key: String, te: &TypedExpr) {
+        let data = serde_pickle::to_vec(te, Default::default()).unwrap();
+        let entries = self.entries.get_or_insert_with(read_cache_file);
+        entries.insert(
+            key.clone(),
+            CacheEntry {
+                last_used: now(),
+                data,
+            },
+        );
+        self.dirty.insert(key);
+    }
+
+    // Save new or used entries to the cache file.
+    pub fn save(&mut self) {
+        if self.in_memory || self.dirty.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.take().unwrap_or_default();
+        let _lock = lock_cache_file();
+        let mut merged = read_cache_file();
+        for key in std::mem::take(&mut self.dirty) {
+            if let Some(entry) = entries.remove(&key) {
+                merged.insert(key, entry);
+            }
+        }
+        evict(&mut merged);
+        write_cache_file(&merged);
+        self.entries = Some(merged);
+    }
+}
+
+impl Drop for TypeCheckCache {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+// Remove the cache file (`fix clean --cache-only`).
+pub fn clear_type_check_cache() {
+    let path = Path::new(TYPE_CHECK_CACHE_PATH);
+    if path.exists() {
+        fs::remove_file(path)
+            .map_err(|e| {
+                error_exit(&format!(
+                    "Failed to remove `{}`: {}",
+                    TYPE_CHECK_CACHE_PATH, e
+                ))
+            })
+            .unwrap();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn header() -> String {
+    format!(
+        "fix type check cache {} {}\n",
+        FORMAT_VERSION,
+        build_time_utc!()
+    )
+}
+
+// Read all entries of the cache file. If the file does not exist or is created by another build of the compiler, returns no entries.
+fn read_cache_file() -> HashMap<String, CacheEntry> {
+    let mut entries = HashMap::default();
+    let bytes = match fs::read(TYPE_CHECK_CACHE_PATH) {
+        Ok(bytes) => bytes,
+        Err(_) => return entries,
+    };
+    let header = header();
+    if !bytes.starts_with(header.as_bytes()) {
+        return entries;
+    }
+    let mut rest = &bytes[header.len()..];
+    while let Some(key) = split_bytes(&mut rest, KEY_LEN) {
+        let key = String::from_utf8_lossy(key).to_string();
+        let entry = (|| {
+            let last_used = u64::from_le_bytes(split_bytes(&mut rest, 8)?.try_into().unwrap());
+            let len = u64::from_le_bytes(split_bytes(&mut rest, 8)?.try_into().unwrap());
+            let data = split_bytes(&mut rest, len as usize)?.to_vec();
+            Some(CacheEntry { last_used, data })
+        })();
+        match entry {
+            Some(entry) => {
+                entries.insert(key, entry);
+            }
+            None => {
+                eprintln!(
+                    "warning: Type check cache `{}` is truncated.",
+                    TYPE_CHECK_CACHE_PATH
+                );
+                break;
+            }
+        }
+    }
+    entries
+}
+
+// Take the exclusive lock on the cache, which is released when the returned file is dropped.
+// If locking fails (e.g., on a file system without locks), the cache is saved without the lock.
+fn lock_cache_file() -> Option<fs::File> {
+    touch_directory(DOT_FIXLANG);
+    let res = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(TYPE_CHECK_CACHE_LOCK_PATH)
+        .and_then(|file| file.lock().map(|_| file));
+    match res {
+        Ok(file) => Some(file),
+        Err(why) => {
+            eprintln!("warning: Failed to lock type check cache: {}.", why);
+            None
+        }
+    }
+}
+
+// Split the first `len` bytes from `bytes`.
+fn split_bytes<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if bytes.len() < len {
+        return None;
+    }
+    let (res, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Some(res)
+}
+
+// Write entries to the cache file.
+// The file is written to a temporary file and then renamed, so that other processes do not read an incomplete file.
+fn write_cache_file(entries: &HashMap<String, CacheEntry>) {
+    let mut bytes = header().into_bytes();
+    for (key, entry) in entries {
+        bytes.extend(key.as_bytes());
+        bytes.extend(entry.last_used.to_le_bytes());
+        bytes.extend((entry.data.len() as u64).to_le_bytes());
+        bytes.extend(&entry.data);
+    }
+    touch_directory(DOT_FIXLANG);
+    let tmp_path = format!("{}.{}.tmp", TYPE_CHECK_CACHE_PATH, std::process::id());
+    if let Err(why) = fs::write(&tmp_path, &bytes) {
+        eprintln!("warning: Failed to write type check cache: {}.", why);
+        return;
+    }
+    if let Err(why) = fs::rename(&tmp_path, TYPE_CHECK_CACHE_PATH) {
+        eprintln!("warning: Failed to write type check cache: {}.", why);
+    }
+}
+
+// Evict entries which are not used for a long time, and least recently used entries while the total size exceeds the limit.
+fn evict(entries: &mut HashMap<String, CacheEntry>) {
+    let now = now();
+    entries.retain(|_, entry| entry.last_used + MAX_AGE_SECS >= now);
+
+    let mut keys = entries
+        .iter()
+        .map(|(key, entry)| (entry.last_used, key.clone()))
+        .collect::<Vec<_>>();
+    keys.sort_by(|a, b| b.cmp(a));
+    let mut total_size = 0;
+    for (_, key) in keys {
+        total_size += KEY_LEN + 16 + entries[&key].data.len();
+        if total_size > MAX_TOTAL_SIZE {
+            entries.remove(&key);
+        }
+    }
+}
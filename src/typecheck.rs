@@ -248,6 +248,44 @@ impl Substitution {
         }
     }
 
+    // Find the innermost component where `ty1` and `ty2` fail to satisfy `relation` (`Substitution::unify` or `Substitution::matching`).
+    // Returns the path to the component, or None if they satisfy `relation`.
+    pub fn find_mismatch(
+        kind_map: &HashMap<TyCon, Rc<Kind>>,
+        ty1: &Rc<TypeNode>,
+        ty2: &Rc<TypeNode>,
+        relation: fn(&HashMap<TyCon, Rc<Kind>>, &Rc<TypeNode>, &Rc<TypeNode>) -> Option<Self>,
+    ) -> Option<Vec<TypeComponent>> {
+        if relation(kind_map, ty1, ty2).is_some() {
+            return None;
+        }
+        let (component, sub1, sub2) = match (&ty1.ty, &ty2.ty) {
+            (Type::TyApp(fun1, arg1), Type::TyApp(fun2, arg2)) => {
+                // We do not descend into type constructors: if they differ, the whole types are reported.
+                if relation(kind_map, fun1, fun2).is_none() {
+                    return Some(vec![]);
+                }
+                (TypeComponent::TyAppArg, arg1, arg2)
+            }
+            (Type::FunTy(src1, dst1), Type::FunTy(src2, dst2)) => {
+                if relation(kind_map, src1, src2).is_none() {
+                    (TypeComponent::FunTySrc, src1, src2)
+                } else {
+                    (TypeComponent::FunTyDst, dst1, dst2)
+                }
+            }
+            _ => return Some(vec![]),
+        };
+        match Self::find_mismatch(kind_map, sub1, sub2, relation) {
+            Some(mut path) => {
+                path.insert(0, component);
+                Some(path)
+            }
+            // Each component satisfies the relation, but they conflict with each other.
+            None => Some(vec![]),
+        }
+    }
+
     // Subroutine of unify().
     fn unify_tyvar(
         kind_map: &HashMap<TyCon, Rc<Kind>>,
@@ -497,6 +535,25 @@ impl TypeCheckContext {
         self.resolver.unify(ty1, ty2)
     }
 
+    // Raise a type mismatch error for an expression at `src`.
+    // The error message shows the differing components of the expected and the found types, and where these types are specified.
+    fn error_exit_type_mismatch(
+        &self,
+        expected: &Rc<TypeNode>,
+        found: &Rc<TypeNode>,
+        src: &Option<Span>,
+    ) -> ! {
+        let expected = self.substitute_type(expected);
+        let found = self.substitute_type(found);
+        error_exit_type_mismatch(
+            &self.type_env.kinds(),
+            &expected,
+            &found,
+            Substitution::unify,
+            src,
+        )
+    }
+
     // Reduce predicates.
     // Returns Err(p) if predicates are unsatisfiable due to predicate p.
     pub fn reduce_predicates(&mut self) -> Result<(), Predicate> {
@@ -561,11 +618,12 @@ impl TypeCheckContext {
                     .collect();
                 let ok_count = candidates.iter().filter(|cand| cand.is_ok()).count();
                 if ok_count == 0 {
-                    error_exit_with_src(
+                    let expected_ty = self.substitute_type(&ty);
+                    error_exit_with_src_notes(
                         &format!(
                             "No value named `{}` matches the expected type `{}`.\n{}",
                             var.name.to_string(),
-                            &self.substitute_type(&ty).to_string_normalize(),
+                            &expected_ty.to_string_normalize(),
                             candidates
                                 .iter()
                                 .map(|cand| cand.as_ref().err().unwrap().clone())
@@ -573,6 +631,7 @@ impl TypeCheckContext {
                                 .join("\n")
                         ),
                         &ei.source,
+                        &type_source_notes(&expected_ty, "expected", &ei.source),
                     );
                 } else if ok_count >= 2 {
                     let candidates_str = candidates
@@ -604,14 +663,7 @@ impl TypeCheckContext {
             }
             Expr::LLVM(lit) => {
                 if !self.unify(&lit.ty, &ty) {
-                    error_exit_with_src(
-                        &format!(
-                            "Type mismatch. Expected `{}`, found `{}`",
-                            &self.substitute_type(&ty).to_string_normalize(),
-                            &lit.ty.to_string_normalize(),
-                        ),
-                        &ei.source,
-                    );
+                    self.error_exit_type_mismatch(&ty, &lit.ty, &ei.source);
                 }
                 ei.clone()
             }
@@ -636,14 +688,7 @@ impl TypeCheckContext {
                 let body_ty = type_tyvar_star(&self.new_tyvar());
                 let fun_ty = type_fun(arg_ty.clone(), body_ty.clone());
                 if !self.unify(&fun_ty, &ty) {
-                    error_exit_with_src(
-                        &format!(
-                            "Type mismatch. Expected `{}`, found `{}`",
-                            &self.substitute_type(&ty).to_string_normalize(),
-                            &self.substitute_type(&fun_ty).to_string_normalize(),
-                        ),
-                        &ei.source,
-                    );
+                    self.error_exit_type_mismatch(&ty, &fun_ty, &ei.source);
                 }
                 assert!(arg.name.is_local());
                 self.scope.push(&arg.name.name, &Scheme::from_type(arg_ty));
@@ -687,14 +732,7 @@ impl TypeCheckContext {
                     )
                 }
                 if !self.unify(&ty, anno_ty) {
-                    error_exit_with_src(
-                        &format!(
-                            "Type mismatch. Expected `{}`, found `{}`.",
-                            &self.substitute_type(&ty).to_string_normalize(),
-                            &self.substitute_type(&anno_ty).to_string_normalize(),
-                        ),
-                        &ei.source,
-                    );
+                    self.error_exit_type_mismatch(&ty, anno_ty, &ei.source);
                 }
                 let e = self.unify_type_of_expr(e, ty.clone());
                 ei.set_tyanno_expr(e)
@@ -736,14 +774,7 @@ impl TypeCheckContext {
                 // Get field types.
                 let struct_ty = tc.get_struct_union_value_type(self);
                 if !self.unify(&struct_ty, &ty) {
-                    error_exit_with_src(
-                        &format!(
-                            "Type mismatch. Expected `{}`, found `{}`.",
-                            &self.substitute_type(&ty).to_string_normalize(),
-                            &self.substitute_type(&struct_ty).to_string_normalize(),
-                        ),
-                        &ei.source,
-                    );
+                    self.error_exit_type_mismatch(&ty, &struct_ty, &ei.source);
                 }
                 let field_tys = struct_ty.field_types(&self.type_env);
                 assert_eq!(field_tys.len(), fields.len());
@@ -766,13 +797,7 @@ impl TypeCheckContext {
                 let elem_ty = type_tyvar_star(&self.new_tyvar());
                 let array_ty = type_tyapp(make_array_ty(), elem_ty.clone());
                 if !self.unify(&array_ty, &ty) {
-                    error_exit_with_src(
-                        &format!(
-                            "Type mismatch. Expected `{}`, found an array.",
-                            &self.substitute_type(&ty).to_string_normalize(),
-                        ),
-                        &ei.source,
-                    );
+                    self.error_exit_type_mismatch(&ty, &array_ty, &ei.source);
                 }
                 let mut ei = ei.clone();
                 for (i, e) in elems.iter().enumerate() {
@@ -781,22 +806,19 @@ impl TypeCheckContext {
                 }
                 ei
             }
-            Expr::CallC(_, ret_ty, param_tys, is_va_args, args) => {
+            Expr::CallC(fun_name, ret_ty, param_tys, is_va_args, args) => {
                 let ret_ty = type_tycon(ret_ty);
                 if !self.unify(&ty, &ret_ty) {
-                    error_exit_with_src(
-                        &format!(
-                            "Type mismatch. Expected `{}`, found `{}`.",
-                            ty.to_string(),
-                            ret_ty.to_string()
-                        ),
-                        &ei.source,
-                    );
+                    self.error_exit_type_mismatch(&ty, &ret_ty, &ei.source);
                 }
-                let param_tys = param_tys
+                let mut param_tys = param_tys
                     .iter()
                     .map(|tc| type_tycon(tc))
                     .collect::<Vec<_>>();
+                if fun_name == CALL_C_INDIRECT_NAME {
+                    // The first argument is the function pointer.
+                    param_tys.insert(0, make_ptr_ty());
+                }
                 let mut ei = ei.clone();
                 for (i, e) in args.iter().enumerate() {
                     let expect_ty = if i < param_tys.len() {
@@ -826,12 +848,12 @@ impl TypeCheckContext {
 
         let s = Substitution::matching(&self.type_env.kinds(), &deduced_ty, &specified_ty);
         if s.is_none() {
-            error_exit_with_src(
-                &format!(
-                    "Type mismatch. Expected `{}`, found `{}`.",
-                    specified_ty.to_string_normalize(),
-                    deduced_ty.to_string_normalize()
-                ),
+            error_exit_type_mismatch(
+                &self.type_env.kinds(),
+                &specified_ty,
+                &deduced_ty,
+                // The check above is whether `deduced_ty` matches `specified_ty`, i.e., the arguments are flipped.
+                |kind_map, specified, deduced| Substitution::matching(kind_map, deduced, specified),
                 &expr.source,
             );
         }
@@ -855,4 +877,100 @@ impl TypeCheckContext {
 
         expr
     }
+
+    // Infer the most general type of an expression.
+    pub fn infer_type(&mut self, expr: Rc<ExprNode>) -> Rc<Scheme> {
+        assert!(self.predicates.is_empty()); // This function is available only when predicates are empty.
+        let ty = type_tyvar_star(&self.new_tyvar());
+        let expr = self.unify_type_of_expr(&expr, ty.clone());
+        let ty = self.substitute_type(&ty);
+        if let Err(p) = self.reduce_predicates() {
+            error_exit_with_src(
+                &format!(
+                    "Constraint `{}` is required for this expression but cannot be satisfied.",
+                    p.to_string_normalize()
+                ),
+                &expr.source,
+            );
+        }
+        let preds = std::mem::replace(&mut self.predicates, Default::default());
+        let mut vars = ty.free_vars();
+        for p in &preds {
+            vars.extend(p.ty.free_vars());
+        }
+        Scheme::generalize(vars, preds, ty)
+    }
+}
+
+// Raise a type mismatch error for an expression at `src`.
+// `relation` is the relation which `expected` and `found` failed to satisfy, and is used to find the differing components.
+pub fn error_exit_type_mismatch(
+    kind_map: &HashMap<TyCon, Rc<Kind>>,
+    expected: &Rc<TypeNode>,
+    found: &Rc<TypeNode>,
+    relation: fn(&HashMap<TyCon, Rc<Kind>>, &Rc<TypeNode>, &Rc<TypeNode>) -> Option<Substitution>,
+    src: &Option<Span>,
+) -> ! {
+    let path = Substitution::find_mismatch(kind_map, expected, found, relation).unwrap_or_default();
+    // Normalize both types by a common renaming, so that a type variable has the same name in them.
+    let normalized = TypeNode::normalize_tyvars_of(&[expected.clone(), found.clone()]);
+    let (expected_str, expected_range) = normalized[0].to_string_highlight(&path);
+    let (found_str, found_range) = normalized[1].to_string_highlight(&path);
+
+    let mut msg = format!(
+        "Type mismatch. Expected `{}`, found `{}`.\n",
+        expected_str, found_str
+    );
+    // Show the differing components by carets under the types.
+    for (label, ty_str, (start, end)) in [
+        ("expected", &expected_str, expected_range),
+        ("found", &found_str, found_range),
+    ] {
+        let label = format!("{:>10}: ", label);
+        msg += &format!("{}{}\n", label, ty_str);
+        if !path.is_empty() {
+            msg += &format!(
+                "{}{}\n",
+                " ".repeat(label.len() + start),
+                "^".repeat(end - start)
+            );
+        }
+    }
+    if !path.is_empty() {
+        msg += &format!(
+            "`{}` and `{}` do not match.",
+            &expected_str[expected_range.0..expected_range.1],
+            &found_str[found_range.0..found_range.1]
+        );
+    }
+
+    let mut notes = type_source_notes(expected, "expected", src);
+    notes.append(&mut type_source_notes(found, "found", src));
+    error_exit_with_src_notes(&msg, src, &notes)
+}
+
+// Create a note which shows where a type (`kind` is "expected" or "found") is specified, e.g., a type signature or a type annotation.
+// Returns no note if the type has no source or the source is the same as `expr_src`.
+fn type_source_notes(
+    ty: &Rc<TypeNode>,
+    kind: &str,
+    expr_src: &Option<Span>,
+) -> Vec<(String, Option<Span>)> {
+    let ty_src = match ty.get_source() {
+        Some(ty_src) => ty_src,
+        None => return vec![],
+    };
+    if let Some(expr_src) = expr_src {
+        if ty_src.is_same_range(expr_src) {
+            return vec![];
+        }
+    }
+    vec![(
+        format!(
+            "The {} type `{}` is specified here.",
+            kind,
+            ty.to_string_normalize()
+        ),
+        Some(ty_src.clone()),
+    )]
 }
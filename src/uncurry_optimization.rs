@@ -6,7 +6,8 @@ use super::*;
 // Global closures are uncurried as long as possible, and converted to function pointers (= has no field for captured values).
 // NOTE: I hope to implement higher-order uncurrying optimization (https://xavierleroy.org/publi/higher-order-uncurrying.pdf) in a future!
 
-pub fn uncurry_optimization(fix_mod: &mut Program) {
+// Symbols in `excluded` are not uncurried.
+pub fn uncurry_optimization(fix_mod: &mut Program, excluded: &HashSet<FullName>) {
     // First, define uncurried version of global symbols.
     let syms = std::mem::replace(&mut fix_mod.instantiated_global_symbols, Default::default());
     for (sym_name, sym) in syms {
@@ -15,6 +16,9 @@ pub fn uncurry_optimization(fix_mod: &mut Program) {
         fix_mod
             .instantiated_global_symbols
             .insert(sym_name.clone(), sym.clone());
+        if excluded.contains(&sym_name) {
+            continue;
+        }
 
         // Add function pointer version as long as possible.
         for arg_cnt in 1..(FUNPTR_ARGS_MAX + 1) {
@@ -0,0 +1,84 @@
+// Tests of the programmatic API, using only the items exported from the crate root.
+
+use std::path::PathBuf;
+
+use fixlang::{Configuration, FixProgram};
+use serial_test::serial;
+
+const SOURCE: &str = r##"
+module Main;
+
+greet : String -> String;
+greet = |name| "Hello, " + name + "!";
+
+main : IO ();
+main = (
+    let args = *get_args;
+    println(greet(args.@(1)))
+);
+
+other_main : IO ();
+other_main = (
+    eval *eprintln("from other_main");
+    exit(3)
+);
+"##;
+
+fn sources() -> Vec<(PathBuf, String)> {
+    vec![(PathBuf::from("api_test.fix"), SOURCE.to_string())]
+}
+
+#[test]
+#[serial]
+fn test_check_and_types() {
+    let mut program = FixProgram::load_from_sources(&sources(), Configuration::release()).unwrap();
+    assert!(program.check().is_empty());
+    assert_eq!(
+        program.type_of("Main::greet").unwrap().unwrap(),
+        "Std::String -> Std::String"
+    );
+    assert!(program.type_of("Main::no_such_value").unwrap().is_none());
+}
+
+#[test]
+#[serial]
+fn test_run_with_args_and_entry_point() {
+    let mut config = Configuration::release();
+    config.program_args = vec!["Fix".to_string()];
+    let program = FixProgram::load_from_sources(&sources(), config).unwrap();
+    let output = program.run().unwrap();
+    assert_eq!(output.exit_code, Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Hello, Fix!\n");
+
+    // Another global value of type `IO ()` can be run as the program.
+    let mut config = Configuration::release();
+    config.entry_point = "Main::other_main".to_string();
+    let program = FixProgram::load_from_sources(&sources(), config).unwrap();
+    let output = program.run().unwrap();
+    assert_eq!(output.exit_code, Some(3));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "from other_main\n"
+    );
+
+    // An unknown entry point is reported as a diagnostic.
+    let mut config = Configuration::release();
+    config.entry_point = "Main::no_such_main".to_string();
+    let program = FixProgram::load_from_sources(&sources(), config).unwrap();
+    assert!(program.run().is_err());
+}
+
+#[test]
+#[serial]
+fn test_diagnostics() {
+    let bad_source = SOURCE.replace("\"Hello, \" + name", "1 + name");
+    let path = PathBuf::from("api_test.fix");
+    let mut program =
+        FixProgram::load_from_sources(&[(path.clone(), bad_source)], Configuration::release())
+            .unwrap();
+    let errors = program.check();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].locations[0].file, path);
+    assert_eq!(errors[0].locations[0].line, 5);
+    assert!(errors[0].rendered.starts_with(&errors[0].message));
+}
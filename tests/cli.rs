@@ -0,0 +1,60 @@
+// Tests of the command line interface, running the `fix` executable.
+
+use std::{path::PathBuf, process::Command};
+
+use serial_test::serial;
+
+const SOURCE: &str = r##"
+module Main;
+
+double : I64 -> I64;
+double = |x| 2 * x;
+
+main : IO ();
+main = println(double(3).to_string);
+"##;
+
+fn source_file() -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("cli_test.fix");
+    std::fs::write(&path, SOURCE).unwrap();
+    path
+}
+
+#[test]
+#[serial]
+fn test_emit_prints_to_stdout() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fix"))
+        .arg("run")
+        .arg("-f")
+        .arg(source_file())
+        .arg("--emit=ast,typed,instances")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let ast = stdout.find("// --emit=ast\n").unwrap();
+    let typed = stdout.find("// --emit=typed\n").unwrap();
+    let instances = stdout.find("// --emit=instances\n").unwrap();
+    assert!(ast < typed && typed < instances);
+    assert!(stdout[ast..typed].contains("Main::double : I64 -> I64;"));
+    assert!(stdout[typed..instances].contains("Lam |x| : I64 -> I64"));
+    assert!(stdout[instances..].contains("(instance of Main::double)"));
+    assert!(stdout.ends_with("6\n"));
+}
+
+#[test]
+#[serial]
+fn test_emit_unknown_kind() {
+    // An unknown kind is rejected as an error of the arguments, not by a panic.
+    let output = Command::new(env!("CARGO_BIN_EXE_fix"))
+        .arg("run")
+        .arg("-f")
+        .arg(source_file())
+        .arg("--emit=tokens")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("tokens"));
+    assert!(!stderr.contains("panicked"));
+}